@@ -0,0 +1,292 @@
+//! Procedural macros for the [`metatype`](https://docs.rs/metatype) crate.
+//!
+//! These are re-exported by `metatype`, and shouldn't be depended upon directly.
+
+#![doc(html_root_url = "https://docs.rs/metatype-derive/0.2.1")]
+#![warn(
+	missing_copy_implementations,
+	missing_debug_implementations,
+	missing_docs,
+	trivial_casts,
+	trivial_numeric_casts,
+	unused_import_braces,
+	unused_qualifications,
+	unused_results,
+	clippy::pedantic
+)] // from https://github.com/rust-unofficial/patterns/blob/master/anti_patterns/deny-warnings.md
+#![allow(clippy::must_use_candidate)]
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+	parse_macro_input, parse_quote, visit_mut::{self, VisitMut}, Data, DeriveInput, Error, GenericParam, Generics, Ident, ItemTrait, TraitBoundModifier, TraitItem, Type, TypeParamBound, WherePredicate
+};
+
+/// Register a concrete type as an implementor of a trait object type, the
+/// first time the registry is accessed.
+///
+/// See `metatype::register_impl`.
+#[proc_macro_attribute]
+pub fn register_impl(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let trait_object = parse_macro_input!(attr as Type);
+	let input = parse_macro_input!(item as DeriveInput);
+	if !input.generics.params.is_empty() {
+		return Error::new_spanned(
+			&input.generics,
+			"`register_impl` can't be applied to generic types",
+		)
+		.to_compile_error()
+		.into();
+	}
+	let ident = &input.ident;
+	let expanded = quote! {
+		#input
+		::metatype::inventory::submit! {
+			::metatype::registry::Registration(
+				::metatype::registry::register_impl::<#ident, #trait_object>
+			)
+		}
+	};
+	expanded.into()
+}
+
+/// Describe a trait to the `metatype` registry, and add methods to its trait
+/// object type for getting the vtables of implementors, recovering the
+/// concrete type, and downcasting.
+///
+/// See `metatype::dyn_trait`.
+#[proc_macro_attribute]
+pub fn dyn_trait(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let item = parse_macro_input!(item as ItemTrait);
+	if !attr.is_empty() {
+		return Error::new_spanned(
+			proc_macro2::TokenStream::from(attr),
+			"`dyn_trait` takes no arguments",
+		)
+		.to_compile_error()
+		.into();
+	}
+	if !item.generics.params.is_empty() {
+		return Error::new_spanned(
+			&item.generics,
+			"`dyn_trait` can't be applied to generic traits",
+		)
+		.to_compile_error()
+		.into();
+	}
+	let (vis, ident) = (&item.vis, &item.ident);
+	let methods = item.items.iter().filter_map(|item| match item {
+		TraitItem::Fn(method) => Some(method.sig.ident.to_string()),
+		_ => None,
+	});
+	let expanded = quote! {
+		#item
+		impl ::metatype::registry::Interface for dyn #ident {
+			const DESCRIPTOR: ::metatype::registry::InterfaceDescriptor =
+				::metatype::registry::InterfaceDescriptor::new(
+					::core::stringify!(#ident),
+					&[#(#methods),*],
+				);
+		}
+		impl dyn #ident {
+			/// Get the canonical vtable of `T` as this trait object,
+			/// registering it if it isn't already.
+			#[track_caller]
+			#vis fn vtable_of<T: #ident + 'static>() -> ::metatype::TraitObject {
+				::metatype::registry::register::<T, Self>()
+			}
+			/// Get the type id of the concrete type, if it was registered.
+			#vis fn concrete_type_id(&self) -> ::core::option::Option<::core::any::TypeId> {
+				::metatype::registry::type_id_of_val(self)
+			}
+			/// Whether the concrete type is `T`.
+			#vis fn is<T: #ident + 'static>(&self) -> bool {
+				::metatype::registry::is::<T, Self>(self)
+			}
+			/// Downcast to a `&T` if the concrete type is `T`.
+			#vis fn downcast_ref<T: #ident + 'static>(&self) -> ::core::option::Option<&T> {
+				::metatype::registry::downcast_ref::<T, Self>(self)
+			}
+			/// Downcast to a `&mut T` if the concrete type is `T`.
+			#vis fn downcast_mut<T: #ident + 'static>(&mut self) -> ::core::option::Option<&mut T> {
+				::metatype::registry::downcast_mut::<T, Self>(self)
+			}
+			/// Downcast to a `Box<T>` if the concrete type is `T`.
+			///
+			/// # Errors
+			///
+			/// Returns `self` if the concrete type isn't `T`.
+			#vis fn downcast<T: #ident + 'static>(
+				self: ::std::boxed::Box<Self>,
+			) -> ::core::result::Result<::std::boxed::Box<T>, ::std::boxed::Box<Self>> {
+				::metatype::registry::downcast_box::<T, Self>(self)
+			}
+		}
+	};
+	expanded.into()
+}
+
+/// Implement `metatype::Type` for a struct whose last field is a slice, `str`
+/// or another such type, forwarding its meta data to that field as a
+/// `metatype::Composite`.
+///
+/// See `metatype::Type`.
+#[proc_macro_derive(Type)]
+pub fn derive_type(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let tail = match &input.data {
+		Data::Struct(data) => data.fields.iter().next_back().map(|field| field.ty.clone()),
+		_ => None,
+	};
+	let Some(tail) = tail else {
+		return Error::new_spanned(
+			&input.ident,
+			"`Type` can only be derived for structs with an unsized last field",
+		)
+		.to_compile_error()
+		.into();
+	};
+	let param = input.generics.type_params().find(|param| match &tail {
+		Type::Path(path) => path.qself.is_none() && path.path.is_ident(&param.ident),
+		_ => false,
+	});
+	let expanded = if let Some(param) = param {
+		// A generic tail might be sized, for which the blanket impl applies, so
+		// instead implement for each of the slice-like types it might be.
+		let param = param.ident.clone();
+		let elem = format_ident!("__MetatypeElem");
+		let slice = substitute(
+			&input.ident,
+			&input.generics,
+			&param,
+			&parse_quote!([#elem]),
+		);
+		let str_ = substitute(&input.ident, &input.generics, &param, &parse_quote!(str));
+		let mut slice_generics = slice.0;
+		slice_generics.params.push(parse_quote!(#elem));
+		let slice = impl_type(&slice_generics, &slice.1, &parse_quote!([#elem]));
+		let str_ = impl_type(&str_.0, &str_.1, &parse_quote!(str));
+		quote! { #slice #str_ }
+	} else {
+		let ident = &input.ident;
+		let (_, ty_generics, _) = input.generics.split_for_impl();
+		impl_type(&input.generics, &parse_quote!(#ident #ty_generics), &tail)
+	};
+	expanded.into()
+}
+
+/// Replace the type parameter `param` with `with`, returning the generics
+/// without it and the resulting type.
+fn substitute(ident: &Ident, generics: &Generics, param: &Ident, with: &Type) -> (Generics, Type) {
+	struct Substitute<'a>(&'a Ident, &'a Type);
+	impl VisitMut for Substitute<'_> {
+		fn visit_type_mut(&mut self, ty: &mut Type) {
+			if let Type::Path(path) = ty {
+				if path.qself.is_none() && path.path.is_ident(self.0) {
+					*ty = self.1.clone();
+					return;
+				}
+			}
+			visit_mut::visit_type_mut(self, ty);
+		}
+	}
+	let (_, ty_generics, _) = generics.split_for_impl();
+	let mut self_ty: Type = parse_quote!(#ident #ty_generics);
+	let mut generics = generics.clone();
+	let mut bounds = Vec::new();
+	generics.params = generics
+		.params
+		.into_iter()
+		.filter(|generic| match generic {
+			GenericParam::Type(type_param) if type_param.ident == *param => {
+				bounds.extend(
+					type_param
+						.bounds
+						.iter()
+						.filter(|bound| !is_maybe(bound))
+						.cloned(),
+				);
+				false
+			}
+			_ => true,
+		})
+		.collect();
+	if let Some(where_clause) = &mut generics.where_clause {
+		where_clause.predicates = where_clause
+			.predicates
+			.clone()
+			.into_iter()
+			.filter_map(|mut predicate| {
+				if let WherePredicate::Type(predicate) = &mut predicate {
+					if matches!(&predicate.bounded_ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(param)) {
+						predicate.bounds = predicate.bounds.clone().into_iter().filter(|bound| !is_maybe(bound)).collect();
+						if predicate.bounds.is_empty() {
+							return None;
+						}
+					}
+				}
+				Some(predicate)
+			})
+			.collect();
+	}
+	if !bounds.is_empty() {
+		generics
+			.make_where_clause()
+			.predicates
+			.push(parse_quote!(#param: #(#bounds)+*));
+	}
+	let mut substitute = Substitute(param, with);
+	substitute.visit_generics_mut(&mut generics);
+	substitute.visit_type_mut(&mut self_ty);
+	(generics, self_ty)
+}
+
+/// Whether `bound` is a relaxed bound like `?Sized`, which can't be applied to
+/// the substituted type.
+fn is_maybe(bound: &TypeParamBound) -> bool {
+	matches!(bound, TypeParamBound::Trait(bound) if matches!(bound.modifier, TraitBoundModifier::Maybe(_)))
+}
+
+fn impl_type(generics: &Generics, self_ty: &Type, tail: &Type) -> proc_macro2::TokenStream {
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	quote! {
+		impl #impl_generics ::metatype::Type for #self_ty #where_clause {
+			const METATYPE: ::metatype::MetaType = ::metatype::MetaType::Composite;
+			const SIZE: ::core::option::Option<usize> = ::core::option::Option::None;
+			const ALIGN: ::core::option::Option<usize> = ::core::option::Option::None;
+			type Meta = ::metatype::Composite;
+			#[inline]
+			fn meta(self: *const Self) -> Self::Meta {
+				::metatype::derive::tail_meta(self)
+			}
+			#[inline]
+			unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+				::metatype::derive::tail_meta(self)
+			}
+			::metatype::__derive_try_meta!();
+			#[inline]
+			fn data(self: *const Self) -> *const () {
+				self.cast()
+			}
+			#[inline]
+			fn data_mut(self: *mut Self) -> *mut () {
+				self.cast()
+			}
+			fn dangling(t: Self::Meta) -> ::core::ptr::NonNull<Self> {
+				::metatype::derive::tail_dangling(t)
+			}
+			fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self {
+				::metatype::derive::tail_fatten(thin, t)
+			}
+			#[inline]
+			fn size_from_meta(t: &Self::Meta) -> ::core::option::Option<usize> {
+				::metatype::derive::tail_size::<Self, #tail>(t)
+			}
+			#[inline]
+			fn align_from_meta(_t: &Self::Meta) -> ::core::option::Option<usize> {
+				::core::option::Option::Some(::metatype::derive::tail_align::<Self>())
+			}
+		}
+		impl #impl_generics ::metatype::IsComposite for #self_ty #where_clause {}
+	}
+}
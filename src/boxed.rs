@@ -0,0 +1,149 @@
+//! Conversions between boxed unsized types that reuse the allocation.
+//!
+//! These go through the raw parts of the box, so the conversion neither
+//! reallocates nor, for the unchecked variants, re-validates the contents.
+//! Boxes in any [`Allocator`] are supported, the allocator being carried
+//! through the conversion.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::boxed;
+//! let a: Box<[u8]> = b"abc".to_vec().into_boxed_slice();
+//! let a: Box<str> = boxed::bytes_into_str(a).unwrap();
+//! assert_eq!(&*a, "abc");
+//! ```
+
+use super::Type;
+use alloc::{
+	alloc::{Allocator, Global}, boxed::Box
+};
+use core::{
+	error::Error, fmt, marker::Unsize, ptr::{self, NonNull}, str::Utf8Error
+};
+
+/// Split a box into its thin data pointer, its metadata and its allocator.
+///
+/// The value can be reassembled with [`from_raw_parts_in`].
+pub fn into_raw_parts<T: ?Sized + Type, A: Allocator>(b: Box<T, A>) -> (NonNull<()>, T::Meta, A) {
+	let meta = Type::meta(ptr::from_ref(&*b));
+	let (ptr, alloc) = Box::into_raw_with_allocator(b);
+	let thin = unsafe { NonNull::new_unchecked(Type::data_mut(ptr)) };
+	(thin, meta, alloc)
+}
+
+/// Reassemble a box from its thin data pointer, its metadata and its
+/// allocator.
+///
+/// # Safety
+///
+/// The parts must be equivalent to those returned by [`into_raw_parts`] for a
+/// `Box<T, A>`.
+pub unsafe fn from_raw_parts_in<T: ?Sized + Type, A: Allocator>(
+	thin: NonNull<()>, t: T::Meta, alloc: A,
+) -> Box<T, A> {
+	Box::from_raw_in(T::fatten(thin.as_ptr(), t), alloc)
+}
+
+/// Coerce a box to one of an unsized type, such as `Box<dyn Trait, A>`, in
+/// generic code where `T: Unsize<U>` is known.
+pub fn unsize_box<T: Unsize<U>, U: ?Sized, A: Allocator>(b: Box<T, A>) -> Box<U, A> {
+	b
+}
+
+/// Convert a `Box<str>` into a `Box<[u8]>` without reallocating.
+pub fn str_into_bytes<A: Allocator>(s: Box<str, A>) -> Box<[u8], A> {
+	let (thin, meta, alloc) = into_raw_parts(s);
+	unsafe { from_raw_parts_in(thin, meta, alloc) }
+}
+
+/// Convert a `Box<[u8]>` into a `Box<str>` without reallocating, if it is
+/// valid UTF-8.
+///
+/// # Errors
+///
+/// Returns a [`FromBoxedUtf8Error`], from which the bytes can be recovered, if
+/// `bytes` isn't valid UTF-8.
+pub fn bytes_into_str<A: Allocator>(
+	bytes: Box<[u8], A>,
+) -> Result<Box<str, A>, FromBoxedUtf8Error<A>> {
+	match core::str::from_utf8(&bytes) {
+		Ok(_) => Ok(unsafe { bytes_into_str_unchecked(bytes) }),
+		Err(error) => Err(FromBoxedUtf8Error { bytes, error }),
+	}
+}
+
+/// Convert a `Box<[u8]>` into a `Box<str>` without reallocating or checking
+/// that it is valid UTF-8.
+///
+/// # Safety
+///
+/// `bytes` must be valid UTF-8.
+pub unsafe fn bytes_into_str_unchecked<A: Allocator>(bytes: Box<[u8], A>) -> Box<str, A> {
+	let (thin, meta, alloc) = into_raw_parts(bytes);
+	from_raw_parts_in(thin, meta, alloc)
+}
+
+/// Error returned by [`bytes_into_str`] when the bytes aren't valid UTF-8.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FromBoxedUtf8Error<A: Allocator = Global> {
+	bytes: Box<[u8], A>,
+	error: Utf8Error,
+}
+impl<A: Allocator> FromBoxedUtf8Error<A> {
+	/// The bytes that were attempted to be converted.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+	/// Recover the bytes that were attempted to be converted.
+	pub fn into_bytes(self) -> Box<[u8], A> {
+		self.bytes
+	}
+	/// The underlying UTF-8 validation error.
+	pub fn utf8_error(&self) -> Utf8Error {
+		self.error
+	}
+}
+impl<A: Allocator> fmt::Display for FromBoxedUtf8Error<A> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.error.fmt(f)
+	}
+}
+impl<A: Allocator + fmt::Debug> Error for FromBoxedUtf8Error<A> {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{bytes_into_str, from_raw_parts_in, into_raw_parts, str_into_bytes, unsize_box};
+	use std::{alloc::System, fmt::Debug};
+
+	#[test]
+	fn str_bytes() {
+		let a: Box<str> = "abc".into();
+		let ptr = a.as_ptr();
+		let a = str_into_bytes(a);
+		assert_eq!((&*a, a.as_ptr()), (&b"abc"[..], ptr));
+		let a = bytes_into_str(a).unwrap();
+		assert_eq!((&*a, a.as_ptr()), ("abc", ptr));
+		let err = bytes_into_str(vec![b'a', 0xff].into_boxed_slice()).unwrap_err();
+		assert_eq!(err.utf8_error().valid_up_to(), 1);
+		assert_eq!(&*err.into_bytes(), [b'a', 0xff]);
+	}
+
+	#[test]
+	fn allocator() {
+		let a: Box<dyn Debug, System> = unsize_box(Box::new_in(123_u16, System));
+		let (thin, meta, alloc) = into_raw_parts(a);
+		let a: Box<dyn Debug, System> = unsafe { from_raw_parts_in(thin, meta, alloc) };
+		assert_eq!(format!("{a:?}"), "123");
+		let mut a = Vec::with_capacity_in(2, System);
+		a.extend_from_slice(b"ab");
+		let a: Box<str, System> = bytes_into_str(a.into_boxed_slice()).unwrap();
+		let a: Box<[u8], System> = str_into_bytes(a);
+		let a = bytes_into_str(a).unwrap();
+		assert_eq!(&*a, "ab");
+	}
+}
@@ -0,0 +1,150 @@
+//! Support for [`#[derive(Type)]`](crate::Type); not public API.
+
+use super::{type_coerce, Composite, MetaType, Slice, Type};
+use core::{
+	convert::TryFrom, mem::{align_of_val_raw, size_of_val_raw}, ptr::{self, Pointee}
+};
+
+#[inline]
+pub fn tail_meta<T: ?Sized + Pointee<Metadata = usize>>(ptr: *const T) -> Composite {
+	Composite {
+		tail: Slice {
+			len: ptr::metadata(ptr),
+		},
+	}
+}
+#[inline]
+pub fn tail_fatten<T: ?Sized + Pointee<Metadata = usize>>(thin: *mut (), t: Composite) -> *mut T {
+	ptr::from_raw_parts_mut(thin, t.tail.len)
+}
+#[inline]
+pub fn tail_align<T: ?Sized + Pointee<Metadata = usize>>() -> usize {
+	let empty: *const T = ptr::from_raw_parts(ptr::null::<()>(), 0);
+	// alignment doesn't depend on the length
+	unsafe { align_of_val_raw(empty) }
+}
+#[inline]
+pub fn tail_size<T: ?Sized + Pointee<Metadata = usize>, Tail: ?Sized + Type>(
+	t: &Composite,
+) -> Option<usize> {
+	let tail = match Tail::METATYPE {
+		MetaType::Slice => Tail::size_from_meta(&type_coerce(t.tail)),
+		MetaType::Composite => Tail::size_from_meta(&type_coerce(*t)),
+		_ => None,
+	}?;
+	let empty: *const T = ptr::from_raw_parts(ptr::null::<()>(), 0);
+	// the size of the fields before the tail, rounded up to the alignment
+	let head = unsafe { size_of_val_raw(empty) };
+	// bounds the size, so that size_of_val_raw's safety requirement is met
+	let _ = head
+		.checked_add(tail)?
+		.checked_add(tail_align::<T>() - 1)
+		.filter(|&size| isize::try_from(size).is_ok())?;
+	let ptr: *const T = ptr::from_raw_parts(ptr::null::<()>(), t.tail.len);
+	Some(unsafe { size_of_val_raw(ptr) })
+}
+#[inline]
+pub fn tail_dangling<T: ?Sized + Pointee<Metadata = usize>>(t: Composite) -> ptr::NonNull<T> {
+	let thin = ptr::without_provenance_mut(tail_align::<T>());
+	unsafe { ptr::NonNull::new_unchecked(tail_fatten(thin, t)) }
+}
+
+#[cfg(feature = "no-panic")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __derive_try_meta {
+	() => {
+		#[inline]
+		fn try_meta(
+			self: *const Self,
+		) -> ::core::result::Result<Self::Meta, $crate::MetatypeError> {
+			::core::result::Result::Ok($crate::derive::tail_meta(self))
+		}
+	};
+}
+#[cfg(not(feature = "no-panic"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __derive_try_meta {
+	() => {};
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{match_meta, Composite, IsComposite, Meta, MetaType, Slice, Type};
+	use std::ptr;
+
+	#[derive(crate::Type)]
+	struct Header<T: ?Sized> {
+		a: u32,
+		tail: T,
+	}
+	#[derive(crate::Type)]
+	struct Bytes(u16, [u8]);
+	#[derive(crate::Type)]
+	struct Nested(u64, Header<[u64]>);
+
+	fn composite<T: ?Sized + IsComposite>(a: *const T) -> Composite {
+		T::composite(Type::meta(a))
+	}
+	fn tail(len: usize) -> Composite {
+		Composite {
+			tail: Slice { len },
+		}
+	}
+
+	#[test]
+	fn derive() {
+		let header: &Header<[u16]> = &Header {
+			a: 1,
+			tail: [2_u16, 3, 4],
+		};
+		assert_eq!(<Header<[u16]>>::METATYPE, MetaType::Composite);
+		assert_eq!(composite(header), tail(3));
+		assert_eq!(Type::meta_erased(header), Meta::Composite(tail(3)));
+		let len = match_meta! { header;
+			Concrete(_) | Slice(_) => None,
+			TraitObject(_) => None,
+			Composite(composite) => Some(composite.tail.len),
+		};
+		assert_eq!(len, Some(3));
+		assert!(crate::fmt_fat::fat(header).to_string().ends_with(" (composite, len 3)"));
+		assert_eq!(
+			<Header<[u16]>>::size_from_meta(&composite(header)),
+			Some(12)
+		);
+		assert_eq!(
+			<Header<[u16]>>::align_from_meta(&composite(header)),
+			Some(4)
+		);
+		assert_eq!(<Header<[u16]>>::size_from_meta(&tail(usize::MAX)), None);
+		let shorter = <Header<[u16]>>::fatten_const(Type::data(ptr::from_ref(header)), tail(2));
+		assert_eq!(
+			unsafe { ((*shorter).a, &(*shorter).tail) },
+			(1, &[2, 3][..])
+		);
+
+		let string = Header { a: 1, tail: *b"ab" };
+		let string = ptr::from_ref::<Header<[u8]>>(&string) as *const Header<str>;
+		assert_eq!(
+			(composite(string), unsafe { &(*string).tail }),
+			(tail(2), "ab")
+		);
+		assert_eq!(<Header<str>>::layout(tail(2)).size(), 8);
+
+		let bytes = [0_u16; 3];
+		let bytes: *const Bytes = ptr::from_raw_parts(bytes.as_ptr().cast::<()>(), 3);
+		assert_eq!(composite(bytes), tail(3));
+		assert_eq!(unsafe { ((*bytes).0, &(*bytes).1) }, (0, &[0; 3][..]));
+		assert_eq!(Bytes::size_from_meta(&tail(3)), Some(6));
+
+		assert_eq!(
+			Nested::dangling(tail(1)).as_ptr().cast::<()>(),
+			ptr::without_provenance_mut(8)
+		);
+		assert_eq!(Nested::size_from_meta(&tail(1)), Some(24));
+		let nested = [0_u64; 3];
+		let nested: *const Nested = ptr::from_raw_parts(nested.as_ptr().cast::<()>(), 1);
+		assert_eq!(unsafe { ((*nested).0, (*nested).1.a) }, (0, 0));
+	}
+}
@@ -0,0 +1,207 @@
+/// Statically dispatch on the [`MetaType`](crate::MetaType) of a type,
+/// binding its [`Type::Meta`](crate::Type::Meta) as the corresponding
+/// [`Concrete`](crate::Concrete), [`Slice`](crate::Slice) or
+/// [`TraitObject`](crate::TraitObject) in each arm.
+///
+/// A `Composite(meta) => ...` arm, binding [`Composite`](crate::Composite),
+/// may follow for types that might derive `Type`, and then an
+/// `Extern(meta) => ...` arm, binding [`ExternMeta`](crate::ExternMeta), for
+/// types that might be `extern type`s; if either is omitted, dispatching on
+/// such a type panics.
+///
+/// The arms are selected by the associated constant
+/// [`Type::METATYPE`](crate::Type::METATYPE), so each instantiation keeps only
+/// the arm for its type, and the metadata is converted via
+/// [`type_coerce`](crate::type_coerce), which can't fail in the selected arm.
+///
+/// ```
+/// # use metatype::{dispatch_meta, Type};
+/// fn describe<T: ?Sized + Type>(meta: T::Meta) -> String {
+///     dispatch_meta! { T, meta;
+///         Concrete(_) => String::from("concrete"),
+///         Slice(slice) => format!("slice of {}", slice.len),
+///         TraitObject(_) => String::from("trait object"),
+///     }
+/// }
+/// assert_eq!(describe::<u8>(Type::meta(&0_u8)), "concrete");
+/// assert_eq!(describe::<[u8]>(Type::meta(&[0_u8; 3][..])), "slice of 3");
+/// let a: &dyn std::any::Any = &0_u8;
+/// assert_eq!(describe::<dyn std::any::Any>(Type::meta(a)), "trait object");
+/// ```
+#[macro_export]
+macro_rules! dispatch_meta {
+	(
+		$t:ty, $meta:expr;
+		Concrete($concrete:pat) => $concrete_body:expr,
+		Slice($slice:pat) => $slice_body:expr,
+		TraitObject($trait_object:pat) => $trait_object_body:expr
+		$(, Composite($composite:pat) => $composite_body:expr)?
+		$(, Extern($extern:pat) => $extern_body:expr)? $(,)?
+	) => {{
+		let meta: <$t as $crate::Type>::Meta = $meta;
+		match <$t as $crate::Type>::METATYPE {
+			$crate::MetaType::Concrete => {
+				let $concrete: $crate::Concrete = $crate::type_coerce(meta);
+				$concrete_body
+			}
+			$crate::MetaType::Slice => {
+				let $slice: $crate::Slice = $crate::type_coerce(meta);
+				$slice_body
+			}
+			$crate::MetaType::TraitObject => {
+				let $trait_object: $crate::TraitObject = $crate::type_coerce(meta);
+				$trait_object_body
+			}
+			$crate::MetaType::Composite => {
+				$crate::__composite_arm!(meta; $($composite => $composite_body)?)
+			}
+			$crate::MetaType::Extern => {
+				$crate::__extern_arm!(meta; $($extern => $extern_body)?)
+			}
+		}
+	}};
+}
+
+/// Match on the [`MetaType`](crate::MetaType) of the value behind a pointer
+/// or reference, binding its metadata as the corresponding
+/// [`Concrete`](crate::Concrete), [`Slice`](crate::Slice) or
+/// [`TraitObject`](crate::TraitObject) in each arm, and optionally
+/// [`Composite`](crate::Composite) and [`ExternMeta`](crate::ExternMeta) as
+/// for [`dispatch_meta!`].
+///
+/// ```
+/// # use metatype::match_meta;
+/// use std::any::Any;
+///
+/// let a: &dyn Any = &0_u8;
+/// let b: &[u16] = &[1, 2, 3];
+/// let len = match_meta! { b;
+///     Concrete(_) => None,
+///     Slice(slice) => Some(slice.len),
+///     TraitObject(_) => None,
+/// };
+/// assert_eq!(len, Some(3));
+/// let vtable = match_meta! { a;
+///     Concrete(_) | Slice(_) => None,
+///     TraitObject(trait_object) => Some(trait_object.vtable),
+/// };
+/// assert!(vtable.is_some());
+/// ```
+#[macro_export]
+macro_rules! match_meta {
+	(
+		$ptr:expr;
+		Concrete($concrete:pat) | Slice($slice:pat) => $body:expr,
+		TraitObject($trait_object:pat) => $trait_object_body:expr
+		$(, Composite($composite:pat) => $composite_body:expr)?
+		$(, Extern($extern:pat) => $extern_body:expr)? $(,)?
+	) => {
+		$crate::match_meta! { $ptr;
+			Concrete($concrete) => $body,
+			Slice($slice) => $body,
+			TraitObject($trait_object) => $trait_object_body,
+			$(Composite($composite) => $composite_body,)?
+			$(Extern($extern) => $extern_body,)?
+		}
+	};
+	(
+		$ptr:expr;
+		Concrete($concrete:pat) => $concrete_body:expr,
+		Slice($slice:pat) => $slice_body:expr,
+		TraitObject($trait_object:pat) => $trait_object_body:expr
+		$(, Composite($composite:pat) => $composite_body:expr)?
+		$(, Extern($extern:pat) => $extern_body:expr)? $(,)?
+	) => {{
+		let ptr: *const _ = $ptr;
+		let meta = $crate::Type::meta(ptr);
+		match $crate::Type::meta_type(ptr) {
+			$crate::MetaType::Concrete => {
+				let $concrete: $crate::Concrete = $crate::type_coerce(meta);
+				$concrete_body
+			}
+			$crate::MetaType::Slice => {
+				let $slice: $crate::Slice = $crate::type_coerce(meta);
+				$slice_body
+			}
+			$crate::MetaType::TraitObject => {
+				let $trait_object: $crate::TraitObject = $crate::type_coerce(meta);
+				$trait_object_body
+			}
+			$crate::MetaType::Composite => {
+				$crate::__composite_arm!(meta; $($composite => $composite_body)?)
+			}
+			$crate::MetaType::Extern => {
+				$crate::__extern_arm!(meta; $($extern => $extern_body)?)
+			}
+		}
+	}};
+}
+
+/// The `Composite` arm of [`dispatch_meta!`] and [`match_meta!`], which is
+/// unreachable if omitted.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __composite_arm {
+	($meta:ident;) => {
+		::core::unreachable!("no arm for composite type")
+	};
+	($meta:ident; $composite:pat => $composite_body:expr) => {{
+		let $composite: $crate::Composite = $crate::type_coerce($meta);
+		$composite_body
+	}};
+}
+
+/// The `Extern` arm of [`dispatch_meta!`] and [`match_meta!`], which is
+/// unreachable if omitted.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __extern_arm {
+	($meta:ident;) => {
+		::core::unreachable!("no arm for extern type")
+	};
+	($meta:ident; $extern:pat => $extern_body:expr) => {{
+		let $extern: $crate::ExternMeta = $crate::type_coerce($meta);
+		$extern_body
+	}};
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::Type;
+	use std::{any::Any, ptr};
+
+	fn size<T: ?Sized + Type>(meta: T::Meta, elem: usize) -> usize {
+		dispatch_meta! { T, meta;
+			Concrete(_) => elem,
+			Slice(slice) => slice.len * elem,
+			TraitObject(_) => 0,
+		}
+	}
+
+	#[test]
+	fn dispatch_meta() {
+		let a = [0_u32; 5];
+		assert_eq!(size::<[u32]>(Type::meta(ptr::from_ref(&a[..])), 4), 20);
+		assert_eq!(size::<[u32; 5]>(Type::meta(ptr::from_ref(&a)), 20), 20);
+		let a: &dyn Any = &a;
+		assert_eq!(size::<dyn Any>(Type::meta(a), 4), 0);
+	}
+
+	#[test]
+	fn match_meta() {
+		let a = String::from("abc");
+		let len = |a: *const str| {
+			match_meta! { a;
+				Concrete(_) => 1,
+				Slice(slice) => slice.len,
+				TraitObject(_) => 0,
+			}
+		};
+		assert_eq!(len(a.as_str()), 3);
+		let a: &mut dyn Any = &mut [0_u8; 2];
+		assert!(match_meta! { a;
+			Concrete(_) | Slice(_) => false,
+			TraitObject(trait_object) => trait_object == crate::type_coerce(Type::meta(a)),
+		});
+	}
+}
@@ -0,0 +1,236 @@
+use super::{vtable_of, IsTraitObject, Type};
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+	any::{type_name, Any}, ptr
+};
+
+/// Whether the concrete type of `a` is `T`, checked by comparing its vtable
+/// with that of `T` before falling back to the virtual
+/// [`type_id`](Any::type_id) call.
+///
+/// The fallback is only taken if the vtables differ, which for a value of
+/// type `T` only happens when it was coerced in a different codegen unit.
+///
+/// ```
+/// # use metatype::is_exactly;
+/// let a: &dyn std::any::Any = &1_u8;
+/// assert!(is_exactly::<u8>(a));
+/// assert!(!is_exactly::<i8>(a));
+/// ```
+#[inline]
+pub fn is_exactly<T: Any>(a: &dyn Any) -> bool {
+	<dyn Any>::trait_object(Type::meta(a)) == vtable_of::<T, dyn Any>() || a.is::<T>()
+}
+
+/// Downcast a pointer to a `dyn Any` to a pointer to its concrete type `T`,
+/// without checking that it is `T`.
+///
+/// With debug assertions enabled this is checked with [`is_exactly`].
+///
+/// ```
+/// # use metatype::downcast_unchecked;
+/// let a: *const dyn std::any::Any = &1_u8;
+/// assert_eq!(unsafe { *downcast_unchecked::<u8>(a) }, 1);
+/// ```
+///
+/// # Safety
+///
+/// `ptr` must point to a valid value of type `T`.
+#[inline]
+pub unsafe fn downcast_unchecked<T: Any>(ptr: *const dyn Any) -> *const T {
+	debug_assert!(
+		is_exactly::<T>(&*ptr),
+		"value isn't of type {}",
+		type_name::<T>()
+	);
+	Type::data(ptr).cast()
+}
+
+/// Downcast a `&dyn Any` to a `&T`, without checking that it is of type `T`.
+///
+/// # Safety
+///
+/// The value must be of type `T`.
+#[inline]
+pub unsafe fn downcast_ref_unchecked<T: Any>(a: &dyn Any) -> &T {
+	&*downcast_unchecked(a)
+}
+
+/// Downcast a `&mut dyn Any` to a `&mut T`, without checking that it is of
+/// type `T`.
+///
+/// # Safety
+///
+/// The value must be of type `T`.
+#[inline]
+pub unsafe fn downcast_mut_unchecked<T: Any>(a: &mut dyn Any) -> &mut T {
+	debug_assert!(
+		is_exactly::<T>(a),
+		"value isn't of type {}",
+		type_name::<T>()
+	);
+	&mut *Type::data_mut(ptr::from_mut(a)).cast()
+}
+
+/// Downcast a `Box<dyn Any>` to a `Box<T>`, without checking that it is of
+/// type `T`.
+///
+/// # Safety
+///
+/// The value must be of type `T`.
+#[cfg(feature = "alloc")]
+#[inline]
+pub unsafe fn downcast_box_unchecked<T: Any>(a: Box<dyn Any>) -> Box<T> {
+	Box::from_raw(downcast_unchecked::<T>(Box::into_raw(a)).cast_mut())
+}
+
+/// Implement `is`, `downcast_ref`, `downcast_mut` and `downcast_box` on
+/// `dyn Trait`, for a user trait with [`Any`](std::any::Any) as a supertrait.
+///
+/// The concrete type is checked via the trait object's vtable, and the
+/// reference rebuilt from its thin data pointer.
+///
+/// ```
+/// # use metatype::impl_downcast;
+/// use std::any::Any;
+///
+/// trait Shape: Any {
+///     fn area(&self) -> f64;
+/// }
+/// impl_downcast!(Shape);
+///
+/// struct Square(f64);
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let a: Box<dyn Shape> = Box::new(Square(2.0));
+/// assert!(a.is::<Square>());
+/// assert_eq!(a.downcast_ref::<Square>().map(|a| a.0), Some(2.0));
+/// let a: Box<Square> = a.downcast_box().ok().unwrap();
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! impl_downcast {
+	($trait:path) => {
+		impl dyn $trait {
+			/// Whether the concrete type of the value is `T`.
+			#[inline]
+			pub fn is<T: $trait>(&self) -> bool {
+				::core::any::Any::type_id(self) == ::core::any::TypeId::of::<T>()
+			}
+			/// The value as a `&T`, or `None` if it isn't of type `T`.
+			#[inline]
+			pub fn downcast_ref<T: $trait>(&self) -> ::core::option::Option<&T> {
+				if self.is::<T>() {
+					let thin = $crate::Type::data(::core::ptr::from_ref(self));
+					::core::option::Option::Some(unsafe { &*thin.cast::<T>() })
+				} else {
+					::core::option::Option::None
+				}
+			}
+			/// The value as a `&mut T`, or `None` if it isn't of type `T`.
+			#[inline]
+			pub fn downcast_mut<T: $trait>(&mut self) -> ::core::option::Option<&mut T> {
+				if self.is::<T>() {
+					let thin = $crate::Type::data_mut(::core::ptr::from_mut(self));
+					::core::option::Option::Some(unsafe { &mut *thin.cast::<T>() })
+				} else {
+					::core::option::Option::None
+				}
+			}
+			/// The boxed value as a `Box<T>`, or the original box if it isn't
+			/// of type `T`.
+			///
+			/// # Errors
+			///
+			/// Returns `self` if the value isn't of type `T`.
+			#[inline]
+			pub fn downcast_box<T: $trait>(
+				self: $crate::__Box<Self>,
+			) -> ::core::result::Result<$crate::__Box<T>, $crate::__Box<Self>> {
+				if self.is::<T>() {
+					let thin = $crate::Type::data_mut($crate::__Box::into_raw(self));
+					::core::result::Result::Ok(unsafe {
+						$crate::__Box::from_raw(thin.cast::<T>())
+					})
+				} else {
+					::core::result::Result::Err(self)
+				}
+			}
+		}
+	};
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+	use super::{
+		downcast_box_unchecked, downcast_mut_unchecked, downcast_ref_unchecked, is_exactly
+	};
+	use std::any::Any;
+
+	trait Animal: Any {
+		fn name(&self) -> String;
+	}
+	crate::impl_downcast!(Animal);
+
+	#[derive(PartialEq, Debug)]
+	struct Dog(String);
+	impl Animal for Dog {
+		fn name(&self) -> String {
+			self.0.clone()
+		}
+	}
+	struct Cat;
+	impl Animal for Cat {
+		fn name(&self) -> String {
+			String::from("cat")
+		}
+	}
+
+	#[test]
+	fn downcast() {
+		let mut a: Box<dyn Animal> = Box::new(Dog(String::from("rex")));
+		assert!(a.is::<Dog>() && !a.is::<Cat>());
+		assert!(a.downcast_ref::<Cat>().is_none());
+		a.downcast_mut::<Dog>().unwrap().0.push('!');
+		assert_eq!(a.name(), "rex!");
+		let a = a.downcast_box::<Cat>().err().unwrap();
+		assert_eq!(
+			*a.downcast_box::<Dog>().ok().unwrap(),
+			Dog(String::from("rex!"))
+		);
+	}
+
+	#[test]
+	fn downcast_unchecked() {
+		let mut a: Box<dyn Any> = Box::new(Dog(String::from("rex")));
+		unsafe { downcast_mut_unchecked::<Dog>(&mut *a) }
+			.0
+			.push('!');
+		assert_eq!(unsafe { downcast_ref_unchecked::<Dog>(&*a) }.0, "rex!");
+		assert_eq!(
+			*unsafe { downcast_box_unchecked::<Dog>(a) },
+			Dog(String::from("rex!"))
+		);
+	}
+
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "value isn't of type")]
+	fn downcast_unchecked_checked() {
+		let a: &dyn Any = &Cat;
+		let _ = unsafe { downcast_ref_unchecked::<Dog>(a) };
+	}
+
+	#[test]
+	fn is_exactly_() {
+		let a: Box<dyn Any> = Box::new(Cat);
+		assert!(is_exactly::<Cat>(&*a) && !is_exactly::<Dog>(&*a));
+		let b: &dyn Any = &*a;
+		assert!(is_exactly::<Box<dyn Any>>(&a) && !is_exactly::<Cat>(&a) && is_exactly::<Cat>(b));
+	}
+}
@@ -0,0 +1,387 @@
+//! Type-erased views of data, carrying the element layout and type id in
+//! place of the static type.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::erased::ErasedSlice;
+//! let a: &[u32] = &[1, 2, 3];
+//! let erased = ErasedSlice::new(a);
+//! assert_eq!(erased.len(), 3);
+//! assert_eq!(erased.downcast::<u32>(), Some(a));
+//! assert_eq!(erased.downcast::<i32>(), None);
+//! ```
+
+mod blob;
+mod cell;
+mod column;
+mod dyn_layout;
+#[cfg(feature = "std")]
+mod sync_cell;
+
+pub use self::{
+	blob::BlobStorage, cell::{BorrowError, CellRef, CellRefMut, ErasedCell}, column::{ErasedColumn, PushError, VecMut}, dyn_layout::{DynInstance, DynLayout, DynLayoutBuilder}
+};
+#[cfg(feature = "std")]
+pub use self::sync_cell::{ErasedSyncCell, SyncCellRef, SyncCellRefMut};
+
+use core::{
+	alloc::Layout, any::{type_name, TypeId}, fmt, marker::PhantomData, mem::needs_drop, ops::{Bound, RangeBounds}, ptr::{self, NonNull}, slice
+};
+
+/// Drops the value behind a pointer in place.
+pub type DropFn = unsafe fn(*mut u8);
+/// Clones the value behind the first pointer into the uninitialized memory
+/// behind the second.
+pub type CloneFn = unsafe fn(*const u8, *mut u8);
+
+/// Runtime description of a `'static` type: its layout, identity, and how to
+/// drop and clone it.
+#[derive(Copy, Clone, Debug)]
+pub struct TypeInfo {
+	layout: Layout,
+	type_id: TypeId,
+	type_name: &'static str,
+	drop: Option<DropFn>,
+	clone: Option<CloneFn>,
+}
+impl TypeInfo {
+	/// Describe `T`. The clone function is populated if `T: Clone`.
+	pub fn of<T: 'static>() -> Self {
+		Self {
+			layout: Layout::new::<T>(),
+			type_id: TypeId::of::<T>(),
+			type_name: type_name::<T>(),
+			drop: needs_drop::<T>().then_some::<DropFn>(drop_in_place::<T>),
+			clone: <T as MaybeClone>::CLONE,
+		}
+	}
+	/// The layout of the type.
+	pub fn layout(&self) -> Layout {
+		self.layout
+	}
+	/// The type id of the type.
+	pub fn type_id(&self) -> TypeId {
+		self.type_id
+	}
+	/// The name of the type.
+	pub fn type_name(&self) -> &'static str {
+		self.type_name
+	}
+	/// The function that drops a value in place, or `None` if dropping is a
+	/// no-op.
+	pub fn drop_fn(&self) -> Option<DropFn> {
+		self.drop
+	}
+	/// The function that clones a value, or `None` if the type isn't `Clone`.
+	pub fn clone_fn(&self) -> Option<CloneFn> {
+		self.clone
+	}
+}
+impl PartialEq for TypeInfo {
+	fn eq(&self, other: &Self) -> bool {
+		self.type_id == other.type_id
+	}
+}
+impl Eq for TypeInfo {}
+
+unsafe fn drop_in_place<T>(ptr: *mut u8) {
+	ptr::drop_in_place(ptr.cast::<T>());
+}
+
+trait MaybeClone {
+	const CLONE: Option<CloneFn>;
+}
+impl<T> MaybeClone for T {
+	default const CLONE: Option<CloneFn> = None;
+}
+impl<T: Clone> MaybeClone for T {
+	const CLONE: Option<CloneFn> = Some(clone::<T>);
+}
+unsafe fn clone<T: Clone>(src: *const u8, dst: *mut u8) {
+	dst.cast::<T>().write((*src.cast::<T>()).clone());
+}
+
+/// A borrowed slice of some `'static` type, erased to its element layout and
+/// type id.
+#[derive(Copy, Clone)]
+pub struct ErasedSlice<'a> {
+	ptr: NonNull<u8>,
+	len: usize,
+	layout: Layout,
+	type_id: TypeId,
+	marker: PhantomData<&'a [u8]>,
+}
+impl<'a> ErasedSlice<'a> {
+	/// Erase the type of `slice`.
+	pub fn new<T: 'static>(slice: &'a [T]) -> Self {
+		Self {
+			ptr: NonNull::from(slice).cast(),
+			len: slice.len(),
+			layout: Layout::new::<T>(),
+			type_id: TypeId::of::<T>(),
+			marker: PhantomData,
+		}
+	}
+	/// Create an `ErasedSlice` from its raw parts.
+	///
+	/// # Safety
+	///
+	/// `ptr` must point to `len` valid, consecutive elements of the type
+	/// identified by `type_id`, with layout `layout`, borrowed for `'a`.
+	pub unsafe fn from_raw_parts(
+		ptr: NonNull<u8>, len: usize, layout: Layout, type_id: TypeId,
+	) -> Self {
+		Self {
+			ptr,
+			len,
+			layout,
+			type_id,
+			marker: PhantomData,
+		}
+	}
+	/// The number of elements.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	/// Whether there are no elements.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The layout of each element.
+	pub fn element_layout(&self) -> Layout {
+		self.layout
+	}
+	/// The type id of the elements.
+	pub fn type_id(&self) -> TypeId {
+		self.type_id
+	}
+	/// A pointer to the first element.
+	pub fn as_ptr(&self) -> *const u8 {
+		self.ptr.as_ptr()
+	}
+	/// A pointer to the element at `index`, or `None` if out of bounds.
+	pub fn get_ptr(&self, index: usize) -> Option<*const u8> {
+		(index < self.len).then(|| unsafe { self.as_ptr().add(index * self.layout.size()) })
+	}
+	/// The elements as a `&[T]`, or `None` if they aren't of type `T`.
+	pub fn downcast<T: 'static>(&self) -> Option<&'a [T]> {
+		(self.type_id == TypeId::of::<T>())
+			.then(|| unsafe { slice::from_raw_parts(self.ptr.cast().as_ptr(), self.len) })
+	}
+	/// The subslice of the elements in `range`, or `None` if out of bounds.
+	pub fn get<R: RangeBounds<usize>>(&self, range: R) -> Option<Self> {
+		let start = match range.start_bound() {
+			Bound::Included(&start) => start,
+			Bound::Excluded(&start) => start.checked_add(1)?,
+			Bound::Unbounded => 0,
+		};
+		let end = match range.end_bound() {
+			Bound::Included(&end) => end.checked_add(1)?,
+			Bound::Excluded(&end) => end,
+			Bound::Unbounded => self.len,
+		};
+		if start > end || end > self.len {
+			return None;
+		}
+		Some(Self {
+			ptr: unsafe { self.ptr.add(start * self.layout.size()) },
+			len: end - start,
+			..*self
+		})
+	}
+	/// Divide into two at `mid`.
+	///
+	/// Panics if `mid > len`.
+	pub fn split_at(&self, mid: usize) -> (Self, Self) {
+		assert!(mid <= self.len, "mid > len");
+		(self.get(..mid).unwrap(), self.get(mid..).unwrap())
+	}
+	/// Iterate over the elements.
+	pub fn iter(&self) -> Iter<'a> {
+		Iter(*self)
+	}
+	/// Iterate over `chunk_size` elements at a time; the last chunk may be
+	/// shorter.
+	///
+	/// Panics if `chunk_size` is 0.
+	pub fn chunks(&self, chunk_size: usize) -> Chunks<'a> {
+		assert_ne!(chunk_size, 0, "chunk size must be non-zero");
+		Chunks(*self, chunk_size)
+	}
+}
+impl fmt::Debug for ErasedSlice<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedSlice")
+			.field("ptr", &self.ptr)
+			.field("len", &self.len)
+			.field("layout", &self.layout)
+			.field("type_id", &self.type_id)
+			.finish()
+	}
+}
+impl<'a> IntoIterator for ErasedSlice<'a> {
+	type IntoIter = Iter<'a>;
+	type Item = ErasedRef<'a>;
+
+	fn into_iter(self) -> Iter<'a> {
+		self.iter()
+	}
+}
+impl<'a> IntoIterator for &ErasedSlice<'a> {
+	type IntoIter = Iter<'a>;
+	type Item = ErasedRef<'a>;
+
+	fn into_iter(self) -> Iter<'a> {
+		self.iter()
+	}
+}
+impl PartialEq for ErasedSlice<'_> {
+	fn eq(&self, other: &Self) -> bool {
+		ptr::eq(self.as_ptr(), other.as_ptr())
+			&& self.len == other.len
+			&& self.type_id == other.type_id
+	}
+}
+impl Eq for ErasedSlice<'_> {}
+
+/// A borrowed value of some `'static` type, erased to its layout and type id.
+#[derive(Copy, Clone, Debug)]
+pub struct ErasedRef<'a> {
+	ptr: NonNull<u8>,
+	layout: Layout,
+	type_id: TypeId,
+	marker: PhantomData<&'a u8>,
+}
+impl<'a> ErasedRef<'a> {
+	/// Erase the type of `value`.
+	pub fn new<T: 'static>(value: &'a T) -> Self {
+		Self {
+			ptr: NonNull::from(value).cast(),
+			layout: Layout::new::<T>(),
+			type_id: TypeId::of::<T>(),
+			marker: PhantomData,
+		}
+	}
+	/// The layout of the value.
+	pub fn layout(&self) -> Layout {
+		self.layout
+	}
+	/// The type id of the value.
+	pub fn type_id(&self) -> TypeId {
+		self.type_id
+	}
+	/// A pointer to the value.
+	pub fn as_ptr(&self) -> *const u8 {
+		self.ptr.as_ptr()
+	}
+	/// The value as a `&T`, or `None` if it isn't of type `T`.
+	pub fn downcast<T: 'static>(&self) -> Option<&'a T> {
+		(self.type_id == TypeId::of::<T>()).then(|| unsafe { self.ptr.cast().as_ref() })
+	}
+}
+impl PartialEq for ErasedRef<'_> {
+	fn eq(&self, other: &Self) -> bool {
+		ptr::eq(self.as_ptr(), other.as_ptr()) && self.type_id == other.type_id
+	}
+}
+impl Eq for ErasedRef<'_> {}
+
+/// Iterator over the elements of an [`ErasedSlice`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a>(ErasedSlice<'a>);
+impl<'a> Iterator for Iter<'a> {
+	type Item = ErasedRef<'a>;
+
+	#[inline]
+	fn next(&mut self) -> Option<ErasedRef<'a>> {
+		let head = self.0.get_ptr(0)?;
+		self.0 = self.0.get(1..).unwrap();
+		Some(ErasedRef {
+			ptr: unsafe { NonNull::new_unchecked(head.cast_mut()) },
+			layout: self.0.layout,
+			type_id: self.0.type_id,
+			marker: PhantomData,
+		})
+	}
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.0.len, Some(self.0.len))
+	}
+}
+impl<'a> DoubleEndedIterator for Iter<'a> {
+	#[inline]
+	fn next_back(&mut self) -> Option<ErasedRef<'a>> {
+		let last = self.0.len.checked_sub(1)?;
+		let tail = self.0.get_ptr(last).unwrap();
+		self.0.len = last;
+		Some(ErasedRef {
+			ptr: unsafe { NonNull::new_unchecked(tail.cast_mut()) },
+			layout: self.0.layout,
+			type_id: self.0.type_id,
+			marker: PhantomData,
+		})
+	}
+}
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Iterator over chunks of an [`ErasedSlice`].
+#[derive(Clone, Debug)]
+pub struct Chunks<'a>(ErasedSlice<'a>, usize);
+impl<'a> Iterator for Chunks<'a> {
+	type Item = ErasedSlice<'a>;
+
+	#[inline]
+	fn next(&mut self) -> Option<ErasedSlice<'a>> {
+		if self.0.is_empty() {
+			return None;
+		}
+		let (head, tail) = self.0.split_at(self.1.min(self.0.len));
+		self.0 = tail;
+		Some(head)
+	}
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.0.len.div_ceil(self.1);
+		(len, Some(len))
+	}
+}
+impl ExactSizeIterator for Chunks<'_> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{ErasedRef, ErasedSlice};
+	use std::alloc::Layout;
+
+	#[test]
+	fn erased_slice() {
+		let a: &[u16] = &[1, 2, 3, 4];
+		let erased = ErasedSlice::new(a);
+		assert_eq!(erased.element_layout(), Layout::new::<u16>());
+		assert_eq!(erased.get_ptr(2), Some(a[2..].as_ptr().cast()));
+		assert_eq!(erased.get_ptr(4), None);
+		assert_eq!(erased.get(1..=2).unwrap().downcast::<u16>(), Some(&a[1..3]));
+		assert_eq!(erased.get(4..).unwrap().len(), 0);
+		assert!(erased.get(3..5).is_none());
+		assert_eq!(erased.get(..), Some(erased));
+		assert_eq!(erased.downcast::<u8>(), None);
+	}
+
+	#[test]
+	fn iter() {
+		let a: &[u16] = &[1, 2, 3, 4, 5];
+		let erased = ErasedSlice::new(a);
+		let all: Vec<u16> = erased
+			.iter()
+			.map(|x| *x.downcast::<u16>().unwrap())
+			.collect();
+		assert_eq!(all, a);
+		assert_eq!(erased.iter().next_back(), Some(ErasedRef::new(&a[4])));
+		assert_eq!(erased.iter().len(), 5);
+		let chunks: Vec<&[u16]> = erased.chunks(2).map(|x| x.downcast().unwrap()).collect();
+		assert_eq!(chunks, [&a[..2], &a[2..4], &a[4..]]);
+		assert_eq!(erased.chunks(2).len(), 3);
+		let (left, right) = erased.split_at(1);
+		assert_eq!((left.len(), right.downcast::<u16>()), (1, Some(&a[1..])));
+	}
+}
@@ -0,0 +1,119 @@
+use super::{ErasedColumn, ErasedRef, PushError, TypeInfo};
+use alloc::collections::BTreeMap;
+use core::any::TypeId;
+
+/// Values of many types, stored in an [`ErasedColumn`] per type.
+#[derive(Debug, Default)]
+pub struct BlobStorage {
+	columns: BTreeMap<TypeId, ErasedColumn>,
+}
+impl BlobStorage {
+	/// Create an empty `BlobStorage`.
+	pub const fn new() -> Self {
+		Self {
+			columns: BTreeMap::new(),
+		}
+	}
+	/// Insert `value` into the column for `T`.
+	pub fn insert<T: 'static>(&mut self, value: T) {
+		self.column_mut(TypeInfo::of::<T>()).push(value);
+	}
+	/// Insert a clone of the borrowed `value` into the column for its type,
+	/// described by `info`.
+	///
+	/// # Errors
+	///
+	/// Returns a [`PushError`] if `value` isn't of the type described by
+	/// `info`, or that type isn't `Clone`.
+	pub fn insert_erased(&mut self, info: TypeInfo, value: ErasedRef<'_>) -> Result<(), PushError> {
+		if value.type_id != info.type_id {
+			return Err(PushError::TypeMismatch);
+		}
+		self.column_mut(info).push_erased(value)
+	}
+	/// Insert the value at `src` into the column for its type, described by
+	/// `info`, moving it into the storage.
+	///
+	/// # Safety
+	///
+	/// `src` must point to a valid value of the type described by `info`,
+	/// which is logically moved and must not be used or dropped afterwards.
+	pub unsafe fn insert_raw(&mut self, info: TypeInfo, src: *const u8) {
+		self.column_mut(info).push_raw(src);
+	}
+	/// The column for the type identified by `type_id`, if any values of it
+	/// have been inserted.
+	pub fn column(&self, type_id: TypeId) -> Option<&ErasedColumn> {
+		self.columns.get(&type_id)
+	}
+	/// The column for the type described by `info`, creating it if needed.
+	pub fn column_mut(&mut self, info: TypeInfo) -> &mut ErasedColumn {
+		self.columns
+			.entry(info.type_id)
+			.or_insert_with(|| ErasedColumn::new(info))
+	}
+	/// Iterate over the values of type `T`.
+	pub fn iter<T: 'static>(&self) -> impl Iterator<Item = &T> {
+		self.column(TypeId::of::<T>())
+			.and_then(ErasedColumn::downcast_ref)
+			.unwrap_or(&[])
+			.iter()
+	}
+	/// Iterate over the columns.
+	pub fn columns(&self) -> impl Iterator<Item = &ErasedColumn> {
+		self.columns.values()
+	}
+	/// The total number of values.
+	pub fn len(&self) -> usize {
+		self.columns.values().map(ErasedColumn::len).sum()
+	}
+	/// Whether there are no values.
+	pub fn is_empty(&self) -> bool {
+		self.columns.values().all(ErasedColumn::is_empty)
+	}
+	/// Remove and drop the value at `index` in the column for `type_id`,
+	/// replacing it with the last value of that column.
+	///
+	/// Panics if there's no such value.
+	pub fn swap_remove(&mut self, type_id: TypeId, index: usize) {
+		self.columns
+			.get_mut(&type_id)
+			.expect("no column for type")
+			.swap_remove(index);
+	}
+	/// Move the value at `index` in the column for `type_id` into `other`,
+	/// replacing it with the last value of that column.
+	///
+	/// Panics if there's no such value.
+	pub fn move_to(&mut self, type_id: TypeId, index: usize, other: &mut BlobStorage) {
+		let column = self.columns.get_mut(&type_id).expect("no column for type");
+		column.swap_remove_to(index, other.column_mut(*column.type_info()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BlobStorage, TypeInfo};
+	use crate::erased::ErasedRef;
+	use std::any::TypeId;
+
+	#[test]
+	fn blob_storage() {
+		let mut a = BlobStorage::new();
+		a.insert(1_u32);
+		a.insert(String::from("a"));
+		a.insert(2_u32);
+		a.insert_erased(TypeInfo::of::<String>(), ErasedRef::new(&String::from("b")))
+			.unwrap();
+		assert_eq!((a.len(), a.columns().count()), (4, 2));
+		assert_eq!(a.iter::<u32>().collect::<Vec<_>>(), [&1, &2]);
+		assert_eq!(a.iter::<u8>().count(), 0);
+
+		let mut b = BlobStorage::new();
+		a.move_to(TypeId::of::<String>(), 0, &mut b);
+		assert_eq!(a.iter::<String>().collect::<Vec<_>>(), ["b"]);
+		assert_eq!(b.iter::<String>().collect::<Vec<_>>(), ["a"]);
+		a.swap_remove(TypeId::of::<u32>(), 1);
+		assert_eq!(a.len(), 2);
+	}
+}
@@ -0,0 +1,226 @@
+use super::TypeInfo;
+use alloc::{alloc::dealloc, boxed::Box};
+use core::{
+	alloc::Layout, any::TypeId, cell::Cell, error::Error, fmt, marker::PhantomData, ops::{Deref, DerefMut}, ptr::NonNull
+};
+
+/// A value of a runtime-chosen type with dynamically checked borrows, like a
+/// `RefCell<dyn Any>` whose borrows check the type too.
+///
+/// ```
+/// # use metatype::erased::ErasedCell;
+/// let cell = ErasedCell::new(String::from("a"));
+/// cell.borrow_mut::<String>().unwrap().push('b');
+/// assert_eq!(&*cell.borrow::<String>().unwrap(), "ab");
+/// assert!(cell.borrow::<u8>().is_err());
+/// ```
+pub struct ErasedCell {
+	data: NonNull<u8>,
+	info: TypeInfo,
+	borrow: Cell<isize>,
+}
+impl ErasedCell {
+	/// Box and erase `value`.
+	pub fn new<T: 'static>(value: T) -> Self {
+		Self {
+			data: NonNull::from(Box::leak(Box::new(value))).cast(),
+			info: TypeInfo::of::<T>(),
+			borrow: Cell::new(0),
+		}
+	}
+	/// The description of the type of the value.
+	pub fn type_info(&self) -> &TypeInfo {
+		&self.info
+	}
+	/// Immutably borrow the value as a `T`.
+	///
+	/// # Errors
+	///
+	/// Returns a [`BorrowError`] if the value isn't of type `T`, or is
+	/// mutably borrowed.
+	pub fn borrow<T: 'static>(&self) -> Result<CellRef<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		let borrow = self.borrow.get();
+		if borrow < 0 || borrow == isize::MAX {
+			return Err(BorrowError::AlreadyBorrowed);
+		}
+		self.borrow.set(borrow + 1);
+		Ok(CellRef {
+			value: unsafe { self.data.cast().as_ref() },
+			borrow: &self.borrow,
+		})
+	}
+	/// Mutably borrow the value as a `T`.
+	///
+	/// # Errors
+	///
+	/// Returns a [`BorrowError`] if the value isn't of type `T`, or is
+	/// borrowed.
+	pub fn borrow_mut<T: 'static>(&self) -> Result<CellRefMut<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		if self.borrow.get() != 0 {
+			return Err(BorrowError::AlreadyBorrowed);
+		}
+		self.borrow.set(-1);
+		Ok(CellRefMut {
+			value: self.data.cast(),
+			borrow: &self.borrow,
+			marker: PhantomData,
+		})
+	}
+	/// Mutably borrow the value as a `T`, without a runtime borrow check as
+	/// the cell is borrowed mutably.
+	pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+		self.check::<T>().ok()?;
+		Some(unsafe { self.data.cast().as_mut() })
+	}
+	/// Unwrap the value as a `T`.
+	///
+	/// # Errors
+	///
+	/// Returns `self` if the value isn't of type `T`.
+	pub fn into_inner<T: 'static>(self) -> Result<T, Self> {
+		if self.check::<T>().is_err() {
+			return Err(self);
+		}
+		let data = self.data.cast::<T>();
+		core::mem::forget(self);
+		Ok(*unsafe { Box::from_raw(data.as_ptr()) })
+	}
+	fn check<T: 'static>(&self) -> Result<(), BorrowError> {
+		if self.info.type_id == TypeId::of::<T>() {
+			Ok(())
+		} else {
+			Err(BorrowError::TypeMismatch)
+		}
+	}
+}
+impl Drop for ErasedCell {
+	fn drop(&mut self) {
+		unsafe {
+			if let Some(drop) = self.info.drop {
+				drop(self.data.as_ptr());
+			}
+			free(self.data, self.info.layout);
+		}
+	}
+}
+impl fmt::Debug for ErasedCell {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedCell")
+			.field("type", &self.info.type_name)
+			.field("borrow", &self.borrow.get())
+			.finish_non_exhaustive()
+	}
+}
+
+pub(super) unsafe fn free(data: NonNull<u8>, layout: Layout) {
+	if layout.size() != 0 {
+		dealloc(data.as_ptr(), layout);
+	}
+}
+
+/// An immutable borrow of the value in an [`ErasedCell`].
+pub struct CellRef<'a, T> {
+	value: &'a T,
+	borrow: &'a Cell<isize>,
+}
+impl<T> Deref for CellRef<'_, T> {
+	type Target = T;
+
+	#[inline]
+	fn deref(&self) -> &T {
+		self.value
+	}
+}
+impl<T> Drop for CellRef<'_, T> {
+	fn drop(&mut self) {
+		self.borrow.set(self.borrow.get() - 1);
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for CellRef<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.value.fmt(f)
+	}
+}
+
+/// A mutable borrow of the value in an [`ErasedCell`].
+pub struct CellRefMut<'a, T> {
+	value: NonNull<T>,
+	borrow: &'a Cell<isize>,
+	marker: PhantomData<&'a mut T>,
+}
+impl<T> Deref for CellRefMut<'_, T> {
+	type Target = T;
+
+	#[inline]
+	fn deref(&self) -> &T {
+		unsafe { self.value.as_ref() }
+	}
+}
+impl<T> DerefMut for CellRefMut<'_, T> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut T {
+		unsafe { self.value.as_mut() }
+	}
+}
+impl<T> Drop for CellRefMut<'_, T> {
+	fn drop(&mut self) {
+		self.borrow.set(0);
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for CellRefMut<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).fmt(f)
+	}
+}
+
+/// Error returned when borrowing the value in an [`ErasedCell`] fails.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BorrowError {
+	/// The value isn't of the requested type
+	TypeMismatch,
+	/// The value is already borrowed incompatibly
+	AlreadyBorrowed,
+}
+impl fmt::Display for BorrowError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::TypeMismatch => f.write_str("value isn't of the requested type"),
+			Self::AlreadyBorrowed => f.write_str("value is already borrowed"),
+		}
+	}
+}
+impl Error for BorrowError {}
+
+#[cfg(test)]
+mod tests {
+	use super::{BorrowError, ErasedCell};
+
+	#[test]
+	fn erased_cell() {
+		let mut cell = ErasedCell::new(vec![1_u8]);
+		{
+			let a = cell.borrow::<Vec<u8>>().unwrap();
+			let b = cell.borrow::<Vec<u8>>().unwrap();
+			assert_eq!(*a, *b);
+			assert_eq!(
+				cell.borrow_mut::<Vec<u8>>().unwrap_err(),
+				BorrowError::AlreadyBorrowed
+			);
+		}
+		{
+			let mut a = cell.borrow_mut::<Vec<u8>>().unwrap();
+			a.push(2);
+			assert_eq!(
+				cell.borrow::<Vec<u8>>().unwrap_err(),
+				BorrowError::AlreadyBorrowed
+			);
+		}
+		assert_eq!(cell.borrow::<u8>().unwrap_err(), BorrowError::TypeMismatch);
+		cell.get_mut::<Vec<u8>>().unwrap().push(3);
+		let cell = cell.into_inner::<u8>().unwrap_err();
+		assert_eq!(cell.into_inner::<Vec<u8>>().ok(), Some(vec![1, 2, 3]));
+		drop(ErasedCell::new(()));
+	}
+}
@@ -0,0 +1,363 @@
+use super::{ErasedRef, ErasedSlice, TypeInfo};
+use alloc::{
+	alloc::{alloc, dealloc, handle_alloc_error, realloc}, vec::Vec
+};
+use core::{
+	alloc::Layout, any::TypeId, error::Error, fmt, marker::PhantomData, mem::{forget, ManuallyDrop}, ops::{Deref, DerefMut}, ptr::{self, NonNull}, slice
+};
+
+/// A growable array, like `Vec<T>`, whose element type is chosen at runtime
+/// from a [`TypeInfo`].
+pub struct ErasedColumn {
+	info: TypeInfo,
+	ptr: NonNull<u8>,
+	len: usize,
+	cap: usize,
+}
+impl ErasedColumn {
+	/// Create an empty column of elements described by `info`.
+	pub fn new(info: TypeInfo) -> Self {
+		let cap = if info.layout.size() == 0 {
+			usize::MAX
+		} else {
+			0
+		};
+		Self {
+			info,
+			ptr: dangling(info.layout),
+			len: 0,
+			cap,
+		}
+	}
+	/// Create an empty column with space for at least `capacity` elements.
+	pub fn with_capacity(info: TypeInfo, capacity: usize) -> Self {
+		let mut column = Self::new(info);
+		column.reserve(capacity);
+		column
+	}
+	/// The description of the element type.
+	pub fn type_info(&self) -> &TypeInfo {
+		&self.info
+	}
+	/// The number of elements.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	/// Whether there are no elements.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The number of elements there's space for without reallocating.
+	pub fn capacity(&self) -> usize {
+		self.cap
+	}
+	/// Reserve space for at least `additional` more elements.
+	pub fn reserve(&mut self, additional: usize) {
+		let required = self.len.checked_add(additional).expect("capacity overflow");
+		if required <= self.cap {
+			return;
+		}
+		let cap = required.max(self.cap * 2).max(4);
+		let layout = array(self.info.layout, cap);
+		let ptr = unsafe {
+			if self.cap == 0 {
+				alloc(layout)
+			} else {
+				realloc(
+					self.ptr.as_ptr(),
+					array(self.info.layout, self.cap),
+					layout.size(),
+				)
+			}
+		};
+		self.ptr = NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout));
+		self.cap = cap;
+	}
+	/// Append the value at `src`, moving it into the column.
+	///
+	/// # Safety
+	///
+	/// `src` must point to a valid value of the element type, which is
+	/// logically moved and must not be used or dropped afterwards.
+	pub unsafe fn push_raw(&mut self, src: *const u8) {
+		self.reserve(1);
+		let size = self.info.layout.size();
+		ptr::copy_nonoverlapping(src, self.ptr.as_ptr().add(self.len * size), size);
+		self.len += 1;
+	}
+	/// Append `value`.
+	///
+	/// Panics if `T` isn't the element type.
+	pub fn push<T: 'static>(&mut self, value: T) {
+		self.assert_type::<T>();
+		unsafe { self.push_raw((&raw const value).cast()) };
+		forget(value);
+	}
+	/// Append a clone of the borrowed `value`.
+	///
+	/// # Errors
+	///
+	/// Returns a [`PushError`] if `value` isn't of the element type, or the
+	/// element type isn't `Clone`.
+	pub fn push_erased(&mut self, value: ErasedRef<'_>) -> Result<(), PushError> {
+		if value.type_id != self.info.type_id {
+			return Err(PushError::TypeMismatch);
+		}
+		let clone = self.info.clone.ok_or(PushError::NotClone)?;
+		self.reserve(1);
+		unsafe {
+			let dst = self.ptr.as_ptr().add(self.len * self.info.layout.size());
+			clone(value.as_ptr(), dst);
+		}
+		self.len += 1;
+		Ok(())
+	}
+	/// The element at `index`, or `None` if out of bounds.
+	pub fn get(&self, index: usize) -> Option<ErasedRef<'_>> {
+		self.as_slice().iter().nth(index)
+	}
+	/// The elements as an [`ErasedSlice`].
+	pub fn as_slice(&self) -> ErasedSlice<'_> {
+		unsafe {
+			ErasedSlice::from_raw_parts(self.ptr, self.len, self.info.layout, self.info.type_id)
+		}
+	}
+	/// A pointer to the first element.
+	pub fn as_mut_ptr(&mut self) -> *mut u8 {
+		self.ptr.as_ptr()
+	}
+	/// Remove and drop the element at `index`, replacing it with the last
+	/// element.
+	///
+	/// Panics if `index` is out of bounds.
+	pub fn swap_remove(&mut self, index: usize) {
+		assert!(index < self.len, "index out of bounds");
+		let size = self.info.layout.size();
+		self.len -= 1;
+		unsafe {
+			let (removed, last) = (
+				self.ptr.as_ptr().add(index * size),
+				self.ptr.as_ptr().add(self.len * size),
+			);
+			if index != self.len {
+				ptr::swap_nonoverlapping(removed, last, size);
+			}
+			if let Some(drop) = self.info.drop {
+				drop(last);
+			}
+		}
+	}
+	/// Move the element at `index` to `dst`, replacing it with the last
+	/// element.
+	///
+	/// Panics if `index` is out of bounds.
+	///
+	/// # Safety
+	///
+	/// `dst` must be valid for writes of the element type; the caller takes
+	/// ownership of the moved value.
+	pub unsafe fn swap_remove_into(&mut self, index: usize, dst: *mut u8) {
+		assert!(index < self.len, "index out of bounds");
+		let size = self.info.layout.size();
+		let base = self.ptr.as_ptr();
+		ptr::copy_nonoverlapping(base.add(index * size), dst, size);
+		self.len -= 1;
+		ptr::copy(base.add(self.len * size), base.add(index * size), size);
+	}
+	/// Move the element at `index` onto the end of `other`, replacing it with
+	/// the last element.
+	///
+	/// Panics if `index` is out of bounds or `other` has a different element
+	/// type.
+	pub fn swap_remove_to(&mut self, index: usize, other: &mut ErasedColumn) {
+		assert_eq!(self.info, other.info, "columns of different element types");
+		other.reserve(1);
+		unsafe {
+			let dst = other.ptr.as_ptr().add(other.len * other.info.layout.size());
+			self.swap_remove_into(index, dst);
+		}
+		other.len += 1;
+	}
+	/// Drop all elements.
+	pub fn clear(&mut self) {
+		let len = self.len;
+		self.len = 0;
+		if let Some(drop) = self.info.drop {
+			let size = self.info.layout.size();
+			for i in 0..len {
+				unsafe { drop(self.ptr.as_ptr().add(i * size)) };
+			}
+		}
+	}
+	/// The elements as a `&[T]`, or `None` if they aren't of type `T`.
+	pub fn downcast_ref<T: 'static>(&self) -> Option<&[T]> {
+		(self.info.type_id == TypeId::of::<T>())
+			.then(|| unsafe { slice::from_raw_parts(self.ptr.cast().as_ptr(), self.len) })
+	}
+	/// The elements as a `Vec<T>` that is written back on drop, or `None` if
+	/// they aren't of type `T`.
+	pub fn downcast_mut<T: 'static>(&mut self) -> Option<VecMut<'_, T>> {
+		if self.info.type_id != TypeId::of::<T>() {
+			return None;
+		}
+		// Leave the column empty while the Vec is borrowed, so leaking the
+		// guard only leaks the elements.
+		let vec = unsafe { Vec::from_raw_parts(self.ptr.cast().as_ptr(), self.len, self.cap) };
+		self.ptr = dangling(self.info.layout);
+		self.len = 0;
+		self.cap = Self::new(self.info).cap;
+		Some(VecMut {
+			column: self,
+			vec: ManuallyDrop::new(vec),
+			marker: PhantomData,
+		})
+	}
+	fn assert_type<T: 'static>(&self) {
+		assert_eq!(
+			self.info.type_id,
+			TypeId::of::<T>(),
+			"column of {} can't hold {}",
+			self.info.type_name,
+			core::any::type_name::<T>()
+		);
+	}
+}
+impl Drop for ErasedColumn {
+	fn drop(&mut self) {
+		self.clear();
+		if self.info.layout.size() != 0 && self.cap != 0 {
+			unsafe { dealloc(self.ptr.as_ptr(), array(self.info.layout, self.cap)) };
+		}
+	}
+}
+impl fmt::Debug for ErasedColumn {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedColumn")
+			.field("type", &self.info.type_name)
+			.field("len", &self.len)
+			.field("cap", &self.cap)
+			.finish_non_exhaustive()
+	}
+}
+
+/// Typed access to an [`ErasedColumn`] as a `Vec<T>`, returned by
+/// [`ErasedColumn::downcast_mut`].
+pub struct VecMut<'a, T> {
+	column: &'a mut ErasedColumn,
+	vec: ManuallyDrop<Vec<T>>,
+	marker: PhantomData<&'a mut Vec<T>>,
+}
+impl<T> Deref for VecMut<'_, T> {
+	type Target = Vec<T>;
+
+	#[inline]
+	fn deref(&self) -> &Vec<T> {
+		&self.vec
+	}
+}
+impl<T> DerefMut for VecMut<'_, T> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut Vec<T> {
+		&mut self.vec
+	}
+}
+impl<T> Drop for VecMut<'_, T> {
+	fn drop(&mut self) {
+		let vec = &mut *self.vec;
+		self.column.ptr = NonNull::new(vec.as_mut_ptr().cast()).unwrap();
+		self.column.len = vec.len();
+		self.column.cap = vec.capacity();
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for VecMut<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.vec.fmt(f)
+	}
+}
+
+/// Error returned by [`ErasedColumn::push_erased`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PushError {
+	/// The value isn't of the column's element type
+	TypeMismatch,
+	/// The element type isn't `Clone`, so a borrowed value can't be pushed
+	NotClone,
+}
+impl fmt::Display for PushError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::TypeMismatch => f.write_str("value isn't of the column's element type"),
+			Self::NotClone => f.write_str("column's element type isn't Clone"),
+		}
+	}
+}
+impl Error for PushError {}
+
+fn dangling(layout: Layout) -> NonNull<u8> {
+	unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(layout.align())) }
+}
+
+fn array(layout: Layout, cap: usize) -> Layout {
+	layout
+		.size()
+		.checked_mul(cap)
+		.and_then(|size| Layout::from_size_align(size, layout.align()).ok())
+		.expect("capacity overflow")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{super::TypeInfo, ErasedColumn, PushError};
+	use crate::erased::ErasedRef;
+	use std::{cell::Cell, rc::Rc};
+
+	#[test]
+	fn column() {
+		let mut column = ErasedColumn::new(TypeInfo::of::<String>());
+		column.push(String::from("a"));
+		column
+			.push_erased(ErasedRef::new(&String::from("b")))
+			.unwrap();
+		assert_eq!(
+			column.push_erased(ErasedRef::new(&1_u8)),
+			Err(PushError::TypeMismatch)
+		);
+		column
+			.downcast_mut::<String>()
+			.unwrap()
+			.push(String::from("c"));
+		assert_eq!(column.downcast_ref::<String>().unwrap(), ["a", "b", "c"]);
+		column.swap_remove(0);
+		assert_eq!(column.downcast_ref::<String>().unwrap(), ["c", "b"]);
+		assert_eq!(column.get(1).unwrap().downcast::<String>().unwrap(), "b");
+		assert!(column.downcast_mut::<u8>().is_none());
+
+		let mut column = ErasedColumn::with_capacity(TypeInfo::of::<()>(), 10);
+		column.push(());
+		column.push(());
+		assert_eq!(column.downcast_ref::<()>().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn drops() {
+		struct Guard(Rc<Cell<usize>>);
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = Rc::new(Cell::new(0));
+		let mut column = ErasedColumn::new(TypeInfo::of::<Guard>());
+		for _ in 0..10 {
+			column.push(Guard(drops.clone()));
+		}
+		assert_eq!(
+			column.push_erased(ErasedRef::new(&Guard(drops.clone()))),
+			Err(PushError::NotClone)
+		);
+		column.swap_remove(3);
+		assert_eq!(drops.get(), 2);
+		drop(column);
+		assert_eq!(drops.get(), 11);
+	}
+}
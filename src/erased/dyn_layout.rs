@@ -0,0 +1,290 @@
+use super::{ErasedRef, TypeInfo};
+use alloc::{
+	alloc::{alloc, dealloc, handle_alloc_error}, vec, vec::Vec
+};
+use core::{
+	alloc::Layout, any::TypeId, fmt, marker::PhantomData, mem::ManuallyDrop, ptr::{self, NonNull}
+};
+
+/// The layout of a struct composed at runtime, following `repr(C)` rules.
+///
+/// ```
+/// # use metatype::erased::{DynLayout, TypeInfo};
+/// let layout = DynLayout::builder()
+///     .field("a", TypeInfo::of::<u8>())
+///     .field("b", TypeInfo::of::<u32>())
+///     .build();
+/// assert_eq!((layout.offset(1), layout.layout().size()), (Some(4), 8));
+///
+/// let mut instance = layout.instance();
+/// instance.set(0, 1_u8);
+/// instance.set(1, 2_u32);
+/// assert_eq!(instance.get::<u32>(1), Some(&2));
+/// ```
+#[derive(Clone, Debug)]
+pub struct DynLayout {
+	layout: Layout,
+	fields: Vec<Field>,
+}
+#[derive(Clone, Debug)]
+struct Field {
+	name: &'static str,
+	info: TypeInfo,
+	offset: usize,
+}
+impl DynLayout {
+	/// Start composing a layout.
+	pub fn builder() -> DynLayoutBuilder {
+		DynLayoutBuilder {
+			layout: Layout::new::<()>(),
+			fields: Vec::new(),
+		}
+	}
+	/// The layout of the whole struct.
+	pub fn layout(&self) -> Layout {
+		self.layout
+	}
+	/// The number of fields.
+	pub fn field_count(&self) -> usize {
+		self.fields.len()
+	}
+	/// The index of the field named `name`.
+	pub fn field_index(&self, name: &str) -> Option<usize> {
+		self.fields.iter().position(|field| field.name == name)
+	}
+	/// The name of the field at `index`.
+	pub fn field_name(&self, index: usize) -> Option<&'static str> {
+		self.fields.get(index).map(|field| field.name)
+	}
+	/// The type of the field at `index`.
+	pub fn field_type(&self, index: usize) -> Option<&TypeInfo> {
+		self.fields.get(index).map(|field| &field.info)
+	}
+	/// The offset in bytes of the field at `index`.
+	pub fn offset(&self, index: usize) -> Option<usize> {
+		self.fields.get(index).map(|field| field.offset)
+	}
+	/// Allocate an instance with all fields uninitialized.
+	pub fn instance(&self) -> DynInstance<'_> {
+		let ptr = if self.layout.size() == 0 {
+			unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(self.layout.align())) }
+		} else {
+			NonNull::new(unsafe { alloc(self.layout) })
+				.unwrap_or_else(|| handle_alloc_error(self.layout))
+		};
+		DynInstance {
+			layout: self,
+			ptr,
+			init: vec![false; self.fields.len()],
+		}
+	}
+}
+
+/// Builder for a [`DynLayout`], returned by [`DynLayout::builder`].
+#[derive(Clone, Debug)]
+pub struct DynLayoutBuilder {
+	layout: Layout,
+	fields: Vec<Field>,
+}
+impl DynLayoutBuilder {
+	/// Append a field named `name` of the type described by `info`.
+	///
+	/// Panics if the struct's size would overflow `isize`.
+	#[must_use]
+	pub fn field(mut self, name: &'static str, info: TypeInfo) -> Self {
+		let (layout, offset) = self.layout.extend(info.layout).expect("struct too large");
+		self.layout = layout;
+		self.fields.push(Field { name, info, offset });
+		self
+	}
+	/// Finish composing the layout, padding it to its alignment.
+	pub fn build(self) -> DynLayout {
+		DynLayout {
+			layout: self.layout.pad_to_align(),
+			fields: self.fields,
+		}
+	}
+}
+
+/// An instance of a [`DynLayout`], tracking which fields are initialized and
+/// dropping them when it is dropped.
+pub struct DynInstance<'a> {
+	layout: &'a DynLayout,
+	ptr: NonNull<u8>,
+	init: Vec<bool>,
+}
+impl DynInstance<'_> {
+	/// A pointer to the start of the instance.
+	pub fn as_ptr(&self) -> *const u8 {
+		self.ptr.as_ptr()
+	}
+	/// Whether every field is initialized.
+	pub fn is_complete(&self) -> bool {
+		self.init.iter().all(|&init| init)
+	}
+	/// Whether the field at `index` is initialized.
+	pub fn is_set(&self, index: usize) -> bool {
+		self.init.get(index).copied().unwrap_or(false)
+	}
+	/// Set the field at `index` to `value`, dropping any previous value.
+	///
+	/// Panics if there's no such field or it isn't of type `T`.
+	pub fn set<T: 'static>(&mut self, index: usize, value: T) {
+		let ptr = self
+			.field_ptr(index, TypeId::of::<T>())
+			.expect("no such field of this type");
+		unsafe {
+			self.drop_field(index);
+			ptr.cast::<T>().write(value);
+		}
+		self.init[index] = true;
+	}
+	/// Set the field at `index` to the value at `src`, dropping any previous
+	/// value.
+	///
+	/// Panics if there's no such field.
+	///
+	/// # Safety
+	///
+	/// `src` must point to a valid value of the field's type, which is
+	/// logically moved and must not be used or dropped afterwards.
+	pub unsafe fn set_raw(&mut self, index: usize, src: *const u8) {
+		let field = &self.layout.fields[index];
+		let dst = self.ptr.as_ptr().add(field.offset);
+		self.drop_field(index);
+		ptr::copy_nonoverlapping(src, dst, field.info.layout.size());
+		self.init[index] = true;
+	}
+	/// The field at `index`, or `None` if there's no such field, it isn't of
+	/// type `T`, or it's uninitialized.
+	pub fn get<T: 'static>(&self, index: usize) -> Option<&T> {
+		self.get_erased(index)?.downcast()
+	}
+	/// The field at `index` as an [`ErasedRef`], or `None` if there's no such
+	/// field or it's uninitialized.
+	pub fn get_erased(&self, index: usize) -> Option<ErasedRef<'_>> {
+		let field = self.layout.fields.get(index)?;
+		self.init[index].then(|| ErasedRef {
+			ptr: unsafe { self.ptr.add(field.offset) },
+			layout: field.info.layout,
+			type_id: field.info.type_id,
+			marker: PhantomData,
+		})
+	}
+	/// The field at `index` as a `&mut T`, or `None` if there's no such field,
+	/// it isn't of type `T`, or it's uninitialized.
+	pub fn get_mut<T: 'static>(&mut self, index: usize) -> Option<&mut T> {
+		let ptr = self.field_ptr(index, TypeId::of::<T>())?;
+		self.init[index].then(|| unsafe { &mut *ptr.cast::<T>() })
+	}
+	/// Release ownership of the initialized instance, returning a pointer to
+	/// it. It must be freed with [`DynInstance::from_raw`].
+	///
+	/// Panics if any field is uninitialized.
+	pub fn into_raw(self) -> NonNull<u8> {
+		assert!(self.is_complete(), "instance has uninitialized fields");
+		let this = ManuallyDrop::new(self);
+		drop(unsafe { ptr::read(&this.init) });
+		this.ptr
+	}
+	fn field_ptr(&self, index: usize, type_id: TypeId) -> Option<*mut u8> {
+		let field = self.layout.fields.get(index)?;
+		(field.info.type_id == type_id).then(|| unsafe { self.ptr.as_ptr().add(field.offset) })
+	}
+	unsafe fn drop_field(&mut self, index: usize) {
+		let field = &self.layout.fields[index];
+		if self.init[index] {
+			self.init[index] = false;
+			if let Some(drop) = field.info.drop {
+				drop(self.ptr.as_ptr().add(field.offset));
+			}
+		}
+	}
+}
+impl<'a> DynInstance<'a> {
+	/// Reclaim an instance released with [`DynInstance::into_raw`].
+	///
+	/// # Safety
+	///
+	/// `ptr` must have been returned by `into_raw` on an instance of `layout`.
+	pub unsafe fn from_raw(layout: &'a DynLayout, ptr: NonNull<u8>) -> Self {
+		Self {
+			layout,
+			ptr,
+			init: vec![true; layout.fields.len()],
+		}
+	}
+}
+impl Drop for DynInstance<'_> {
+	fn drop(&mut self) {
+		for index in 0..self.init.len() {
+			unsafe { self.drop_field(index) };
+		}
+		if self.layout.layout.size() != 0 {
+			unsafe { dealloc(self.ptr.as_ptr(), self.layout.layout) };
+		}
+	}
+}
+impl fmt::Debug for DynInstance<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DynInstance")
+			.field("ptr", &self.ptr)
+			.field("init", &self.init)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(clippy::cast_ptr_alignment)]
+	use super::{DynLayout, TypeInfo};
+	use std::{cell::Cell, rc::Rc};
+
+	#[test]
+	fn dyn_layout() {
+		#[repr(C)]
+		struct Expected(u8, String, u16);
+		let layout = DynLayout::builder()
+			.field("a", TypeInfo::of::<u8>())
+			.field("b", TypeInfo::of::<String>())
+			.field("c", TypeInfo::of::<u16>())
+			.build();
+		assert_eq!(layout.layout(), std::alloc::Layout::new::<Expected>());
+		assert_eq!(layout.offset(2), Some(std::mem::offset_of!(Expected, 2)));
+		assert_eq!(layout.field_index("b"), Some(1));
+
+		let mut instance = layout.instance();
+		instance.set(1, String::from("x"));
+		instance.set(1, String::from("y"));
+		assert!(!instance.is_complete());
+		assert_eq!(instance.get::<String>(1).map(String::as_str), Some("y"));
+		assert_eq!(instance.get::<u8>(0), None);
+		assert_eq!(instance.get::<u16>(1), None);
+		instance.set(0, 1_u8);
+		instance.set(2, 2_u16);
+		*instance.get_mut::<u16>(2).unwrap() += 1;
+		let ptr = instance.into_raw();
+		let expected = unsafe { &*ptr.as_ptr().cast::<Expected>() };
+		assert_eq!((expected.0, &*expected.1, expected.2), (1, "y", 3));
+		drop(unsafe { super::DynInstance::from_raw(&layout, ptr) });
+	}
+
+	#[test]
+	fn drops() {
+		struct Guard(Rc<Cell<usize>>);
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = Rc::new(Cell::new(0));
+		let layout = DynLayout::builder()
+			.field("a", TypeInfo::of::<Guard>())
+			.field("b", TypeInfo::of::<Guard>())
+			.build();
+		let mut instance = layout.instance();
+		instance.set(0, Guard(drops.clone()));
+		drop(instance);
+		assert_eq!(drops.get(), 1);
+	}
+}
@@ -0,0 +1,224 @@
+use super::{cell::free, BorrowError, TypeInfo};
+use core::{
+	any::TypeId, fmt, ops::{Deref, DerefMut}, ptr::NonNull
+};
+use std::{
+	boxed::Box, sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError}
+};
+
+/// The thread-safe counterpart of [`ErasedCell`](super::ErasedCell), with
+/// `RwLock` semantics: concurrent typed reads and exclusive typed writes.
+///
+/// As the value is only reachable through the lock's guards, a panic while
+/// it's borrowed doesn't poison the cell.
+///
+/// ```
+/// # use metatype::erased::ErasedSyncCell;
+/// use std::{sync::Arc, thread};
+///
+/// let cell = Arc::new(ErasedSyncCell::new(0_u64));
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let cell = cell.clone();
+///         thread::spawn(move || *cell.write::<u64>().unwrap() += 1)
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+/// assert_eq!(*cell.read::<u64>().unwrap(), 4);
+/// ```
+pub struct ErasedSyncCell {
+	data: NonNull<u8>,
+	info: TypeInfo,
+	lock: RwLock<()>,
+}
+// The value is `Send + Sync`, as required by `new`, and access to it is
+// synchronized by the lock.
+unsafe impl Send for ErasedSyncCell {}
+unsafe impl Sync for ErasedSyncCell {}
+impl ErasedSyncCell {
+	/// Box and erase `value`.
+	pub fn new<T: Send + Sync + 'static>(value: T) -> Self {
+		Self {
+			data: NonNull::from(Box::leak(Box::new(value))).cast(),
+			info: TypeInfo::of::<T>(),
+			lock: RwLock::new(()),
+		}
+	}
+	/// The description of the type of the value.
+	pub fn type_info(&self) -> &TypeInfo {
+		&self.info
+	}
+	/// Lock the value for reading as a `T`, blocking while it's locked for
+	/// writing.
+	///
+	/// # Errors
+	///
+	/// Returns [`BorrowError::TypeMismatch`] if the value isn't of type `T`.
+	pub fn read<T: 'static>(&self) -> Result<SyncCellRef<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		let guard = self.lock.read().unwrap_or_else(PoisonError::into_inner);
+		Ok(self.read_guard(guard))
+	}
+	/// Lock the value for writing as a `T`, blocking while it's locked.
+	///
+	/// # Errors
+	///
+	/// Returns [`BorrowError::TypeMismatch`] if the value isn't of type `T`.
+	pub fn write<T: 'static>(&self) -> Result<SyncCellRefMut<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		let guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+		Ok(self.write_guard(guard))
+	}
+	/// Lock the value for reading as a `T`, without blocking.
+	///
+	/// # Errors
+	///
+	/// Returns a [`BorrowError`] if the value isn't of type `T`, or is locked
+	/// for writing.
+	pub fn try_read<T: 'static>(&self) -> Result<SyncCellRef<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		let guard = match self.lock.try_read() {
+			Ok(guard) => guard,
+			Err(TryLockError::Poisoned(e)) => e.into_inner(),
+			Err(TryLockError::WouldBlock) => return Err(BorrowError::AlreadyBorrowed),
+		};
+		Ok(self.read_guard(guard))
+	}
+	/// Lock the value for writing as a `T`, without blocking.
+	///
+	/// # Errors
+	///
+	/// Returns a [`BorrowError`] if the value isn't of type `T`, or is locked.
+	pub fn try_write<T: 'static>(&self) -> Result<SyncCellRefMut<'_, T>, BorrowError> {
+		self.check::<T>()?;
+		let guard = match self.lock.try_write() {
+			Ok(guard) => guard,
+			Err(TryLockError::Poisoned(e)) => e.into_inner(),
+			Err(TryLockError::WouldBlock) => return Err(BorrowError::AlreadyBorrowed),
+		};
+		Ok(self.write_guard(guard))
+	}
+	/// Mutably borrow the value as a `T`, without locking as the cell is
+	/// borrowed mutably.
+	pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+		self.check::<T>().ok()?;
+		Some(unsafe { self.data.cast().as_mut() })
+	}
+	/// Unwrap the value as a `T`.
+	///
+	/// # Errors
+	///
+	/// Returns `self` if the value isn't of type `T`.
+	pub fn into_inner<T: 'static>(self) -> Result<T, Self> {
+		if self.check::<T>().is_err() {
+			return Err(self);
+		}
+		let data = self.data.cast::<T>();
+		core::mem::forget(self);
+		Ok(*unsafe { Box::from_raw(data.as_ptr()) })
+	}
+	fn check<T: 'static>(&self) -> Result<(), BorrowError> {
+		if self.info.type_id == TypeId::of::<T>() {
+			Ok(())
+		} else {
+			Err(BorrowError::TypeMismatch)
+		}
+	}
+	fn read_guard<'a, T>(&'a self, guard: RwLockReadGuard<'a, ()>) -> SyncCellRef<'a, T> {
+		SyncCellRef {
+			value: unsafe { self.data.cast().as_ref() },
+			_guard: guard,
+		}
+	}
+	fn write_guard<'a, T>(&'a self, guard: RwLockWriteGuard<'a, ()>) -> SyncCellRefMut<'a, T> {
+		SyncCellRefMut {
+			value: self.data.cast(),
+			_guard: guard,
+		}
+	}
+}
+impl Drop for ErasedSyncCell {
+	fn drop(&mut self) {
+		unsafe {
+			if let Some(drop) = self.info.drop {
+				drop(self.data.as_ptr());
+			}
+			free(self.data, self.info.layout);
+		}
+	}
+}
+impl fmt::Debug for ErasedSyncCell {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedSyncCell")
+			.field("type", &self.info.type_name)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A read lock on the value in an [`ErasedSyncCell`].
+pub struct SyncCellRef<'a, T> {
+	value: &'a T,
+	_guard: RwLockReadGuard<'a, ()>,
+}
+impl<T> Deref for SyncCellRef<'_, T> {
+	type Target = T;
+
+	#[inline]
+	fn deref(&self) -> &T {
+		self.value
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for SyncCellRef<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.value.fmt(f)
+	}
+}
+
+/// A write lock on the value in an [`ErasedSyncCell`].
+pub struct SyncCellRefMut<'a, T> {
+	value: NonNull<T>,
+	_guard: RwLockWriteGuard<'a, ()>,
+}
+impl<T> Deref for SyncCellRefMut<'_, T> {
+	type Target = T;
+
+	#[inline]
+	fn deref(&self) -> &T {
+		unsafe { self.value.as_ref() }
+	}
+}
+impl<T> DerefMut for SyncCellRefMut<'_, T> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut T {
+		unsafe { self.value.as_mut() }
+	}
+}
+impl<T: fmt::Debug> fmt::Debug for SyncCellRefMut<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BorrowError, ErasedSyncCell};
+
+	#[test]
+	fn erased_sync_cell() {
+		let cell = ErasedSyncCell::new(String::from("a"));
+		{
+			let a = cell.read::<String>().unwrap();
+			let b = cell.try_read::<String>().unwrap();
+			assert_eq!(*a, *b);
+			assert_eq!(
+				cell.try_write::<String>().unwrap_err(),
+				BorrowError::AlreadyBorrowed
+			);
+		}
+		cell.write::<String>().unwrap().push('b');
+		assert_eq!(cell.read::<u8>().unwrap_err(), BorrowError::TypeMismatch);
+		assert_eq!(cell.into_inner::<String>().ok().as_deref(), Some("ab"));
+	}
+}
@@ -0,0 +1,279 @@
+//! Closures erased to a thin data pointer and a call shim, without
+//! allocation.
+//!
+//! [`ErasedFn`] borrows an `Fn` closure and [`ErasedFnMut`] an `FnMut`
+//! closure, storing a pointer to it alongside a function monomorphized to call
+//! it. They are two pointers wide, `Copy` where the borrow allows, and
+//! callable as closures themselves. [`ErasedFnOnce`] owns an `FnOnce` closure
+//! moved into caller-provided storage.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::erased_fn::ErasedFn;
+//! let offset = 10;
+//! let add = |a: i32, b: i32| a + b + offset;
+//! let erased: ErasedFn<(i32, i32), i32> = ErasedFn::new(&add);
+//! assert_eq!(erased(1, 2), 13);
+//! ```
+
+use super::{emplace_raw, FattenError, Type};
+use core::{
+	fmt, marker::{PhantomData, Tuple}, mem::{forget, MaybeUninit}, ptr
+};
+
+/// A borrowed `Fn` closure taking `Args` and returning `Ret`, erased to a
+/// thin data pointer and a call shim.
+pub struct ErasedFn<'a, Args: Tuple, Ret> {
+	data: *const (),
+	call: unsafe fn(*const (), Args) -> Ret,
+	marker: PhantomData<&'a dyn Fn<Args, Output = Ret>>,
+}
+impl<'a, Args: Tuple, Ret> ErasedFn<'a, Args, Ret> {
+	/// Erase the type of `f`.
+	pub fn new<F: Fn<Args, Output = Ret>>(f: &'a F) -> Self {
+		unsafe fn call<F: Fn<Args, Output = Ret>, Args: Tuple, Ret>(
+			data: *const (), args: Args,
+		) -> Ret {
+			(*data.cast::<F>()).call(args)
+		}
+		Self {
+			data: ptr::from_ref(f).cast(),
+			call: call::<F, Args, Ret>,
+			marker: PhantomData,
+		}
+	}
+	/// The thin pointer to the closure's data.
+	pub fn data(&self) -> *const () {
+		self.data
+	}
+	/// Call the closure.
+	#[inline]
+	pub fn call(&self, args: Args) -> Ret {
+		unsafe { (self.call)(self.data, args) }
+	}
+}
+impl<Args: Tuple, Ret> Copy for ErasedFn<'_, Args, Ret> {}
+impl<Args: Tuple, Ret> Clone for ErasedFn<'_, Args, Ret> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<Args: Tuple, Ret> FnOnce<Args> for ErasedFn<'_, Args, Ret> {
+	type Output = Ret;
+
+	extern "rust-call" fn call_once(self, args: Args) -> Ret {
+		ErasedFn::call(&self, args)
+	}
+}
+impl<Args: Tuple, Ret> FnMut<Args> for ErasedFn<'_, Args, Ret> {
+	extern "rust-call" fn call_mut(&mut self, args: Args) -> Ret {
+		ErasedFn::call(self, args)
+	}
+}
+impl<Args: Tuple, Ret> Fn<Args> for ErasedFn<'_, Args, Ret> {
+	extern "rust-call" fn call(&self, args: Args) -> Ret {
+		ErasedFn::call(self, args)
+	}
+}
+impl<Args: Tuple, Ret> fmt::Debug for ErasedFn<'_, Args, Ret> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedFn")
+			.field("data", &self.data)
+			.finish_non_exhaustive()
+	}
+}
+
+/// A mutably borrowed `FnMut` closure taking `Args` and returning `Ret`,
+/// erased to a thin data pointer and a call shim.
+pub struct ErasedFnMut<'a, Args: Tuple, Ret> {
+	data: *mut (),
+	call: unsafe fn(*mut (), Args) -> Ret,
+	marker: PhantomData<&'a mut dyn FnMut<Args, Output = Ret>>,
+}
+impl<'a, Args: Tuple, Ret> ErasedFnMut<'a, Args, Ret> {
+	/// Erase the type of `f`.
+	pub fn new<F: FnMut<Args, Output = Ret>>(f: &'a mut F) -> Self {
+		unsafe fn call<F: FnMut<Args, Output = Ret>, Args: Tuple, Ret>(
+			data: *mut (), args: Args,
+		) -> Ret {
+			(*data.cast::<F>()).call_mut(args)
+		}
+		Self {
+			data: ptr::from_mut(f).cast(),
+			call: call::<F, Args, Ret>,
+			marker: PhantomData,
+		}
+	}
+	/// The thin pointer to the closure's data.
+	pub fn data(&self) -> *mut () {
+		self.data
+	}
+	/// Call the closure.
+	#[inline]
+	pub fn call(&mut self, args: Args) -> Ret {
+		unsafe { (self.call)(self.data, args) }
+	}
+}
+impl<Args: Tuple, Ret> FnOnce<Args> for ErasedFnMut<'_, Args, Ret> {
+	type Output = Ret;
+
+	extern "rust-call" fn call_once(mut self, args: Args) -> Ret {
+		ErasedFnMut::call(&mut self, args)
+	}
+}
+impl<Args: Tuple, Ret> FnMut<Args> for ErasedFnMut<'_, Args, Ret> {
+	extern "rust-call" fn call_mut(&mut self, args: Args) -> Ret {
+		ErasedFnMut::call(self, args)
+	}
+}
+impl<Args: Tuple, Ret> fmt::Debug for ErasedFnMut<'_, Args, Ret> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedFnMut")
+			.field("data", &self.data)
+			.finish_non_exhaustive()
+	}
+}
+
+/// An `FnOnce` closure taking `Args` and returning `Ret`, moved into
+/// caller-provided storage and erased to a thin data pointer, a call shim and
+/// a drop shim.
+///
+/// The closure is dropped without being called if the `ErasedFnOnce` is
+/// dropped.
+pub struct ErasedFnOnce<'a, Args: Tuple, Ret> {
+	data: *mut (),
+	call: unsafe fn(*mut (), Args) -> Ret,
+	drop: unsafe fn(*mut ()),
+	marker: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+impl<'a, Args: Tuple, Ret> ErasedFnOnce<'a, Args, Ret> {
+	/// Move `f` into `buffer`, at the first suitably aligned offset, and
+	/// erase its type.
+	///
+	/// # Errors
+	///
+	/// Returns `f` and a [`FattenError`] if it doesn't fit in `buffer`.
+	///
+	/// The closure can't outlive what it borrows:
+	///
+	/// ```compile_fail
+	/// # use metatype::erased_fn::ErasedFnOnce;
+	/// # use std::mem::MaybeUninit;
+	/// let mut buffer = [MaybeUninit::uninit(); 32];
+	/// let f;
+	/// {
+	///     let s = String::from("abc");
+	///     let r = &s;
+	///     f = ErasedFnOnce::new_in(&mut buffer, move || r.len()).unwrap_or_else(|_| panic!());
+	/// }
+	/// f();
+	/// ```
+	pub fn new_in<F: FnOnce<Args, Output = Ret> + 'a>(
+		buffer: &'a mut [MaybeUninit<u8>], f: F,
+	) -> Result<Self, (F, FattenError)> {
+		unsafe fn call<F: FnOnce<Args, Output = Ret>, Args: Tuple, Ret>(
+			data: *mut (), args: Args,
+		) -> Ret {
+			data.cast::<F>().read().call_once(args)
+		}
+		unsafe fn drop<F>(data: *mut ()) {
+			ptr::drop_in_place(data.cast::<F>());
+		}
+		let src = ptr::from_ref(&f);
+		match unsafe { emplace_raw::<F>(buffer, Type::meta(src), src.cast()) } {
+			Ok(data) => {
+				forget(f);
+				Ok(Self {
+					data: ptr::from_mut(data).cast(),
+					call: call::<F, Args, Ret>,
+					drop: drop::<F>,
+					marker: PhantomData,
+				})
+			}
+			Err(e) => Err((f, e)),
+		}
+	}
+	/// The thin pointer to the closure's data.
+	pub fn data(&self) -> *mut () {
+		self.data
+	}
+	/// Call the closure, consuming it.
+	#[inline]
+	pub fn call(self, args: Args) -> Ret {
+		let (data, call) = (self.data, self.call);
+		forget(self);
+		unsafe { call(data, args) }
+	}
+}
+impl<Args: Tuple, Ret> FnOnce<Args> for ErasedFnOnce<'_, Args, Ret> {
+	type Output = Ret;
+
+	extern "rust-call" fn call_once(self, args: Args) -> Ret {
+		ErasedFnOnce::call(self, args)
+	}
+}
+impl<Args: Tuple, Ret> Drop for ErasedFnOnce<'_, Args, Ret> {
+	fn drop(&mut self) {
+		unsafe { (self.drop)(self.data) }
+	}
+}
+impl<Args: Tuple, Ret> fmt::Debug for ErasedFnOnce<'_, Args, Ret> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ErasedFnOnce")
+			.field("data", &self.data)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ErasedFn, ErasedFnMut, ErasedFnOnce};
+	use std::{cell::Cell, mem::MaybeUninit, rc::Rc};
+
+	#[test]
+	fn erased_fn() {
+		let s = String::from("abc");
+		let f = |x: usize| s.len() + x;
+		let erased = ErasedFn::<(usize,), usize>::new(&f);
+		assert_eq!(erased.call((1,)), 4);
+		let copy = erased;
+		assert_eq!([1, 2].map(copy), [4, 5]);
+
+		let mut count = 0;
+		let mut g = || count += 1;
+		let mut erased = ErasedFnMut::<(), ()>::new(&mut g);
+		erased();
+		erased.call(());
+		assert_eq!(count, 2);
+	}
+
+	#[test]
+	fn erased_fn_once() {
+		struct Guard(Rc<Cell<usize>>);
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = Rc::new(Cell::new(0));
+		let mut buf = [MaybeUninit::uninit(); 16];
+		let guard = Guard(drops.clone());
+		let f = ErasedFnOnce::<(usize,), usize>::new_in(&mut buf, move |x| {
+			let guard = guard;
+			guard.0.get() + x
+		})
+		.unwrap_or_else(|_| panic!());
+		assert_eq!(f(10), 10);
+		assert_eq!(drops.get(), 1);
+
+		let guard = Guard(drops.clone());
+		let f = ErasedFnOnce::<(), ()>::new_in(&mut buf, move || drop(guard))
+			.unwrap_or_else(|_| panic!());
+		drop(f);
+		assert_eq!(drops.get(), 2);
+
+		let big = [0_u8; 32];
+		assert!(ErasedFnOnce::<(), u8>::new_in(&mut buf, move || big[0]).is_err());
+	}
+}
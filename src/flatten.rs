@@ -0,0 +1,114 @@
+//! Unwrapping of nested `dyn Any` erasure.
+//!
+//! Re-erasing an already erased value, for example boxing a `Box<dyn Any>`
+//! into another `Box<dyn Any>`, produces a value whose type id is that of the
+//! inner box, so downcasting it to the original type fails. These helpers
+//! recurse through such layers, by type id, until reaching a value that isn't
+//! itself an erased box.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::flatten;
+//! use std::any::Any;
+//!
+//! let a: Box<dyn Any> = Box::new(1_u8);
+//! let a: Box<dyn Any> = Box::new(a);
+//! assert!(!a.is::<u8>());
+//! assert_eq!(*flatten::downcast::<u8>(a).unwrap(), 1);
+//! ```
+
+use alloc::boxed::Box;
+use core::any::Any;
+
+/// Unwrap any layers of `Box<dyn Any>` or `Box<dyn Any + Send>` (or
+/// `+ Send + Sync`) around the value.
+pub fn flatten(mut value: Box<dyn Any>) -> Box<dyn Any> {
+	loop {
+		value = match value.downcast::<Box<dyn Any>>() {
+			Ok(inner) => *inner,
+			Err(value) => match value.downcast::<Box<dyn Any + Send>>() {
+				Ok(inner) => *inner,
+				Err(value) => match value.downcast::<Box<dyn Any + Send + Sync>>() {
+					Ok(inner) => *inner,
+					Err(value) => return value,
+				},
+			},
+		};
+	}
+}
+
+/// Unwrap any layers of `Box<dyn Any + Send>` (or `+ Send + Sync`) around
+/// the value.
+pub fn flatten_send(mut value: Box<dyn Any + Send>) -> Box<dyn Any + Send> {
+	loop {
+		value = match value.downcast::<Box<dyn Any + Send>>() {
+			Ok(inner) => *inner,
+			Err(value) => match value.downcast::<Box<dyn Any + Send + Sync>>() {
+				Ok(inner) => *inner,
+				Err(value) => return value,
+			},
+		};
+	}
+}
+
+/// Unwrap any layers of `Box<dyn Any>` or `Box<dyn Any + Send>` (or
+/// `+ Send + Sync`) around the borrowed value.
+pub fn flatten_ref(mut value: &dyn Any) -> &dyn Any {
+	loop {
+		value = if let Some(inner) = value.downcast_ref::<Box<dyn Any>>() {
+			&**inner
+		} else if let Some(inner) = value.downcast_ref::<Box<dyn Any + Send>>() {
+			&**inner
+		} else if let Some(inner) = value.downcast_ref::<Box<dyn Any + Send + Sync>>() {
+			&**inner
+		} else {
+			return value;
+		}
+	}
+}
+
+/// Whether the value is wrapped in at least one layer of erased box.
+pub fn is_nested(value: &dyn Any) -> bool {
+	!core::ptr::addr_eq(flatten_ref(value), value)
+}
+
+/// Unwrap any layers of erased box around the value, and downcast it to `T`.
+///
+/// # Errors
+///
+/// Returns the flattened value if it isn't of type `T`.
+pub fn downcast<T: Any>(value: Box<dyn Any>) -> Result<Box<T>, Box<dyn Any>> {
+	flatten(value).downcast()
+}
+
+/// Unwrap any layers of erased box around the borrowed value, and downcast
+/// it to `T`.
+pub fn downcast_ref<T: Any>(value: &dyn Any) -> Option<&T> {
+	flatten_ref(value).downcast_ref()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{downcast_ref, flatten, flatten_send, is_nested};
+	use std::any::Any;
+
+	#[test]
+	fn nested() {
+		let a: Box<dyn Any + Send> = Box::new(String::from("a"));
+		let a: Box<dyn Any + Send> = Box::new(a);
+		let a: Box<dyn Any> = Box::new(a);
+		let a: Box<dyn Any> = Box::new(a);
+		assert!(is_nested(&*a));
+		assert_eq!(downcast_ref::<String>(&*a).unwrap(), "a");
+		let a = flatten(a);
+		assert!(!is_nested(&*a));
+		assert_eq!(a.downcast_ref::<String>().unwrap(), "a");
+
+		let b: Box<dyn Any + Send + Sync> = Box::new(1_u8);
+		let b: Box<dyn Any + Send> = Box::new(b);
+		assert_eq!(flatten_send(b).downcast_ref::<u8>(), Some(&1));
+		let c: Box<dyn Any> = Box::new(Box::new(1_u8));
+		assert!(flatten(c).is::<Box<u8>>());
+	}
+}
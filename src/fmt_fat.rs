@@ -0,0 +1,112 @@
+//! Formatting of fat pointers as one-liners.
+//!
+//! [`fat`] wraps a pointer to render its data address and metadata, for use in
+//! `Debug` impls and log statements of erased containers.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::fmt_fat::fat;
+//! let a: &[u16] = &[1, 2, 3];
+//! let a = format!("{}", fat(a));
+//! assert!(a.starts_with("*const [u16] @ 0x"));
+//! assert!(a.ends_with(" (slice, len 3)"));
+//! ```
+
+use core::{any::type_name, fmt, ptr};
+
+#[cfg(feature = "std")]
+use super::registry;
+use super::Type;
+
+/// Wrap a pointer to format it with [`Fat`].
+pub fn fat<T: ?Sized>(ptr: *const T) -> Fat<T> {
+	Fat {
+		ptr,
+		#[cfg(feature = "std")]
+		symbolize: false,
+	}
+}
+
+/// A pointer formatted as its pointee type, data address, and metadata: the
+/// length of a slice or of a composite's tail, or the vtable of a trait
+/// object.
+pub struct Fat<T: ?Sized> {
+	ptr: *const T,
+	#[cfg(feature = "std")]
+	symbolize: bool,
+}
+impl<T: ?Sized> Fat<T> {
+	/// Also render the concrete type a trait object's vtable was registered
+	/// for in the [`registry`], if it was.
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn symbolized(self) -> Self {
+		Self {
+			symbolize: true,
+			..self
+		}
+	}
+}
+impl<T: ?Sized> Clone for Fat<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<T: ?Sized> Copy for Fat<T> {}
+impl<T: ?Sized> fmt::Display for Fat<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let data = Type::data(self.ptr);
+		write!(f, "*const {} @ {:p} (", type_name::<T>(), data)?;
+		crate::match_meta! { self.ptr;
+			Concrete(_) => f.write_str("concrete")?,
+			Slice(slice) => write!(f, "slice, len {}", slice.len)?,
+			TraitObject(trait_object) => {
+				write!(f, "trait object, vtable {:p}", ptr::from_ref(trait_object.vtable))?;
+				#[cfg(feature = "std")]
+				if let Some((_, name)) = registry::registered_type(trait_object).filter(|_| self.symbolize) {
+					write!(f, " of {name}")?;
+				}
+			},
+			Composite(composite) => write!(f, "composite, len {}", composite.tail.len)?,
+		}
+		f.write_str(")")
+	}
+}
+impl<T: ?Sized> fmt::Debug for Fat<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+	use super::fat;
+	use crate::{describe_trait, registry, type_coerce, Type};
+	use std::ptr;
+
+	trait Shape {
+		fn area(&self) -> u8;
+	}
+	describe_trait!(Shape { area });
+	struct Square([u8; 3]);
+	impl Shape for Square {
+		fn area(&self) -> u8 {
+			self.0[0] * self.0[0]
+		}
+	}
+
+	#[test]
+	fn fmt_fat() {
+		let a = 0_u32;
+		let a = format!("{:?}", fat(ptr::from_ref(&a)));
+		assert!(a.starts_with("*const u32 @ 0x") && a.ends_with(" (concrete)"));
+		let meta = registry::register::<Square, dyn Shape>();
+		let b = Square([1; 3]);
+		let b: *const dyn Shape = Type::fatten_const(ptr::from_ref(&b).cast(), type_coerce(meta));
+		assert_eq!(unsafe { &*b }.area(), 1);
+		let b = fat(b).symbolized().to_string();
+		assert!(b.contains(" (trait object, vtable 0x"));
+		assert!(b.ends_with("of metatype::fmt_fat::tests::Square)"));
+	}
+}
@@ -0,0 +1,194 @@
+//! Identification of function pointer types by their signature.
+//!
+//! [`FnPtr`] is implemented for safe and `unsafe` function pointers of the
+//! Rust and C ABIs with up to 12 arguments. Each has a normalized name, such
+//! as `extern "C" fn(i32, u8) -> bool`, and an id hashed from it, so that
+//! registries of callbacks can check a signature before transmuting a
+//! function pointer.
+//!
+//! The names, and therefore the ids, are derived from
+//! [`type_name`](std::any::type_name), so they agree between crates built by
+//! the same compiler but aren't guaranteed stable across compiler versions.
+//! Function pointers generic over lifetimes, such as `fn(&str)`, aren't
+//! covered.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::fn_ptr::{ErasedFnPtr, FnPtr};
+//! fn add(a: i32, b: i32) -> i32 {
+//!     a + b
+//! }
+//! assert_eq!(<fn(i32, i32) -> i32>::name(), "fn(i32, i32) -> i32");
+//!
+//! let erased = ErasedFnPtr::new(add as fn(i32, i32) -> i32);
+//! assert_eq!(erased.signature_id(), <fn(i32, i32) -> i32>::id());
+//! let add = erased.downcast::<fn(i32, i32) -> i32>().unwrap();
+//! assert_eq!(add(1, 2), 3);
+//! assert!(erased.downcast::<fn(i32) -> i32>().is_none());
+//! ```
+
+use super::{fnv1a, FNV_OFFSET};
+use std::{
+	any::{type_name, TypeId}, mem::transmute_copy
+};
+
+/// The ABI of a function pointer.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Abi {
+	/// The Rust ABI
+	Rust,
+	/// The C ABI
+	C,
+}
+
+/// Implemented on function pointer types, describing their signature.
+pub trait FnPtr: Copy + 'static {
+	/// The number of arguments.
+	const ARITY: usize;
+	/// The ABI.
+	const ABI: Abi;
+	/// Whether the function is `unsafe`.
+	const UNSAFE: bool;
+
+	/// The names of the argument types.
+	fn args() -> Vec<&'static str>;
+	/// The name of the return type.
+	fn ret() -> &'static str;
+	/// The address of the function.
+	fn addr(self) -> *const ();
+
+	/// The normalized name of the signature, such as
+	/// `unsafe extern "C" fn(i32) -> u8`. The return type is omitted if it is
+	/// `()`.
+	fn name() -> String {
+		let mut name = String::new();
+		if Self::UNSAFE {
+			name.push_str("unsafe ");
+		}
+		if Self::ABI == Abi::C {
+			name.push_str("extern \"C\" ");
+		}
+		name.push_str("fn(");
+		name.push_str(&Self::args().join(", "));
+		name.push(')');
+		let ret = Self::ret();
+		if ret != "()" {
+			name.push_str(" -> ");
+			name.push_str(ret);
+		}
+		name
+	}
+	/// An id hashed from [`FnPtr::name`].
+	fn id() -> u64 {
+		fnv1a(FNV_OFFSET, Self::name().as_bytes())
+	}
+}
+
+macro_rules! fn_ptr {
+	($arity:literal $($arg:ident)*) => {
+		fn_ptr!(@ $arity, Rust, false, fn($($arg),*) -> R, $($arg)*);
+		fn_ptr!(@ $arity, Rust, true, unsafe fn($($arg),*) -> R, $($arg)*);
+		fn_ptr!(@ $arity, C, false, extern "C" fn($($arg),*) -> R, $($arg)*);
+		fn_ptr!(@ $arity, C, true, unsafe extern "C" fn($($arg),*) -> R, $($arg)*);
+	};
+	(@ $arity:literal, $abi:ident, $unsafe:literal, $ty:ty, $($arg:ident)*) => {
+		impl<R: 'static, $($arg: 'static),*> FnPtr for $ty {
+			const ARITY: usize = $arity;
+			const ABI: Abi = Abi::$abi;
+			const UNSAFE: bool = $unsafe;
+
+			fn args() -> Vec<&'static str> {
+				vec![$(type_name::<$arg>()),*]
+			}
+			fn ret() -> &'static str {
+				type_name::<R>()
+			}
+			#[inline]
+			fn addr(self) -> *const () {
+				self as *const ()
+			}
+		}
+	};
+}
+fn_ptr!(0);
+fn_ptr!(1 A);
+fn_ptr!(2 A B);
+fn_ptr!(3 A B C);
+fn_ptr!(4 A B C D);
+fn_ptr!(5 A B C D E);
+fn_ptr!(6 A B C D E F);
+fn_ptr!(7 A B C D E F G);
+fn_ptr!(8 A B C D E F G H);
+fn_ptr!(9 A B C D E F G H I);
+fn_ptr!(10 A B C D E F G H I J);
+fn_ptr!(11 A B C D E F G H I J K);
+fn_ptr!(12 A B C D E F G H I J K L);
+
+/// A function pointer with its type erased, carrying its signature id.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ErasedFnPtr {
+	addr: *const (),
+	type_id: TypeId,
+	signature_id: u64,
+}
+impl ErasedFnPtr {
+	/// Erase the type of `f`.
+	pub fn new<F: FnPtr>(f: F) -> Self {
+		Self {
+			addr: f.addr(),
+			type_id: TypeId::of::<F>(),
+			signature_id: F::id(),
+		}
+	}
+	/// The address of the function.
+	pub fn addr(&self) -> *const () {
+		self.addr
+	}
+	/// The id of the signature, as given by [`FnPtr::id`].
+	pub fn signature_id(&self) -> u64 {
+		self.signature_id
+	}
+	/// The function pointer as `F`, or `None` if it isn't of type `F`.
+	pub fn downcast<F: FnPtr>(&self) -> Option<F> {
+		(self.type_id == TypeId::of::<F>()).then(|| unsafe { transmute_copy(&self.addr) })
+	}
+	/// The function pointer as `F`, if its signature id matches, for example
+	/// when it was received from another crate that may have been built
+	/// separately.
+	///
+	/// # Safety
+	///
+	/// The function must actually have the signature of `F`; the id check
+	/// guards against mistakes, not deliberate misuse or hash collisions.
+	pub unsafe fn downcast_unchecked<F: FnPtr>(&self) -> Option<F> {
+		(self.signature_id == F::id()).then(|| transmute_copy(&self.addr))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Abi, ErasedFnPtr, FnPtr};
+
+	extern "C" fn c_fn(a: u8) -> u8 {
+		a + 1
+	}
+
+	#[test]
+	fn fn_ptr() {
+		type C = extern "C" fn(u8) -> u8;
+		assert_eq!(<fn()>::name(), "fn()");
+		assert_eq!(
+			<unsafe fn(u8, String) -> bool>::name(),
+			"unsafe fn(u8, alloc::string::String) -> bool"
+		);
+		assert_eq!(C::name(), "extern \"C\" fn(u8) -> u8");
+		assert_eq!((C::ARITY, C::ABI), (1, Abi::C));
+		assert_ne!(C::id(), <fn(u8) -> u8>::id());
+
+		let erased = ErasedFnPtr::new::<C>(c_fn);
+		assert_eq!(erased.downcast::<C>().unwrap()(1), 2);
+		assert!(erased.downcast::<fn(u8) -> u8>().is_none());
+		assert_eq!(unsafe { erased.downcast_unchecked::<C>() }.unwrap()(2), 3);
+	}
+}
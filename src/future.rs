@@ -0,0 +1,124 @@
+//! Boxed, type-erased futures that may borrow, with the borrowed lifetime
+//! carried in the type.
+//!
+//! [`ErasedFuture<'a, T>`] holds a `Send` future living for `'a`, and
+//! [`LocalErasedFuture<'a, T>`] one that may not be `Send`. Each is stored as
+//! a raw pointer to `dyn Future<Output = T> + 'a`, so no lifetime is
+//! transmuted to `'static` along the way.
+//!
+//! Both are covariant in `'a`, like `Box<dyn Future + 'a>`: an erased future
+//! can be used where a shorter lifetime is expected, never a longer one. They
+//! are invariant in `T`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::future::ErasedFuture;
+//! # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+//! let data = vec![1, 2, 3];
+//! let fut: ErasedFuture<'_, usize> = ErasedFuture::new(async { data.len() });
+//! let mut cx = Context::from_waker(Waker::noop());
+//! assert_eq!(pin!(fut).poll(&mut cx), Poll::Ready(3));
+//! ```
+
+use alloc::boxed::Box;
+use core::{
+	fmt, future::Future, marker::PhantomData, pin::Pin, ptr::NonNull, task::{Context, Poll}
+};
+
+macro_rules! erased_future {
+	($(#[$attr:meta])* $name:ident $(+ $bound:ident)?) => {
+		$(#[$attr])*
+		pub struct $name<'a, T> {
+			fut: NonNull<dyn Future<Output = T> $(+ $bound)? + 'a>,
+			marker: PhantomData<Box<dyn Future<Output = T> $(+ $bound)? + 'a>>,
+		}
+		impl<'a, T> $name<'a, T> {
+			/// Box and erase `fut`.
+			pub fn new<F: Future<Output = T> $(+ $bound)? + 'a>(fut: F) -> Self {
+				let fut: Box<dyn Future<Output = T> $(+ $bound)? + 'a> = Box::new(fut);
+				Self {
+					fut: NonNull::from(Box::leak(fut)),
+					marker: PhantomData,
+				}
+			}
+			/// Erase an already boxed future.
+			pub fn from_box(fut: Pin<Box<dyn Future<Output = T> $(+ $bound)? + 'a>>) -> Self {
+				let fut = Box::leak(unsafe { Pin::into_inner_unchecked(fut) });
+				Self {
+					fut: NonNull::from(fut),
+					marker: PhantomData,
+				}
+			}
+			/// Convert back into a boxed future.
+			pub fn into_box(self) -> Pin<Box<dyn Future<Output = T> $(+ $bound)? + 'a>> {
+				let fut = self.fut.as_ptr();
+				core::mem::forget(self);
+				unsafe { Pin::new_unchecked(Box::from_raw(fut)) }
+			}
+		}
+		impl<T> Future for $name<'_, T> {
+			type Output = T;
+
+			#[inline]
+			fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+				unsafe { Pin::new_unchecked(&mut *self.fut.as_ptr()) }.poll(cx)
+			}
+		}
+		impl<T> Unpin for $name<'_, T> {}
+		impl<T> Drop for $name<'_, T> {
+			fn drop(&mut self) {
+				drop(unsafe { Box::from_raw(self.fut.as_ptr()) });
+			}
+		}
+		impl<T> fmt::Debug for $name<'_, T> {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.debug_struct(stringify!($name)).field("fut", &self.fut.cast::<()>()).finish_non_exhaustive()
+			}
+		}
+	};
+}
+erased_future!(
+	/// A boxed `Send` future with output `T`, borrowing for `'a`.
+	ErasedFuture + Send
+);
+erased_future!(
+	/// A boxed future with output `T`, borrowing for `'a`, that may not be
+	/// `Send`.
+	LocalErasedFuture
+);
+
+// The future is only accessed through `Pin<&mut Self>`, so sharing
+// `&ErasedFuture` across threads is sound regardless of the future.
+unsafe impl<T> Send for ErasedFuture<'_, T> {}
+unsafe impl<T> Sync for ErasedFuture<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+	use super::{ErasedFuture, LocalErasedFuture};
+	use std::{
+		future::Future, pin::pin, rc::Rc, task::{Context, Poll, Waker}
+	};
+
+	fn poll<F: Future>(fut: F) -> Poll<F::Output> {
+		pin!(fut).poll(&mut Context::from_waker(Waker::noop()))
+	}
+
+	#[test]
+	fn erased_future() {
+		fn shorten<'a>(fut: ErasedFuture<'static, u8>) -> ErasedFuture<'a, u8> {
+			fut
+		}
+		let s = String::from("abc");
+		let fut = ErasedFuture::new(async { s.len() });
+		assert_eq!(poll(fut), Poll::Ready(3));
+		let fut = shorten(ErasedFuture::new(async { 1 }));
+		assert_eq!(poll(ErasedFuture::from_box(fut.into_box())), Poll::Ready(1));
+
+		let rc = Rc::new(5);
+		let fut = LocalErasedFuture::new(async { *rc });
+		assert_eq!(poll(fut), Poll::Ready(5));
+		let pending = LocalErasedFuture::new(std::future::pending::<Rc<u8>>());
+		assert_eq!(poll(pending), Poll::Pending);
+	}
+}
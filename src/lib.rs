@@ -27,14 +27,67 @@
 //!
 //! # Note
 //!
-//! This currently requires Rust nightly for the `ptr_metadata`, `specialization`
-//! and `arbitrary_self_types` features.
+//! This currently requires Rust nightly for the `ptr_metadata`, `specialization`,
+//! `unsize`, `layout_for_ptr`, `fn_traits`, `unboxed_closures`, `tuple_trait`,
+//! `sized_hierarchy`, `allocator_api`, `arbitrary_self_types`, `const_trait_impl`,
+//! `const_destruct` and `const_type_name` features.
+//!
+//! There is no stable mode. The `Type` trait itself is defined in terms of
+//! `*const Self` receivers and `PointeeSized`, and its impls for every type
+//! rely on specialization, so a stable build would need a different `Type`
+//! trait with per-type impls; as a cargo feature that would break any other
+//! crate in the build relying on the nightly API, since features are unified
+//! rather than chosen per dependent.
+//!
+//! The meta data structs are checked at compile time to have the layout of
+//! the meta data half of a fat pointer on the target, including wasm32, where
+//! pointers and `usize` are 4 bytes; so [`Type::fatten`] and [`Type::data`]
+//! are valid there as on any other target.
+//!
+//! The crate is `no_std` with the default `std` feature disabled; the `alloc`
+//! feature brings back the helpers that need `Box`, and `std` the `registry`
+//! and the `OsStr` and `Path` impls. The `static-registry` feature provides a
+//! [`static_registry`] of implementors that needs neither.
+//!
+//! The `vtable-layout` feature reads vtable entries, relying on rustc's
+//! unspecified vtable layout. Miri doesn't permit reading vtables as memory,
+//! so test under Miri with it disabled.
+//!
+//! The `no-panic` feature omits the sanity checks of slice meta data, so
+//! that [`Type::meta`], [`Type::dangling`], [`Type::fatten`],
+//! [`Type::fatten_within`], [`Type::try_fatten`], [`Type::size_from_meta`]
+//! and [`emplace_raw`] have no panic paths, as checked at link time by the
+//! release build's tests. It also adds non-panicking counterparts of the
+//! functions that still panic on invalid input: [`try_type_coerce_err`] for
+//! [`type_coerce`], [`Type::try_meta`] for the checks omitted from
+//! [`Type::meta`], [`Type::try_layout`] for [`Type::layout`] and
+//! [`Type::try_dangling_with_align`] for [`Type::dangling_with_align`]. It
+//! doesn't extend to the `alloc` containers, which document their panics, nor
+//! to the `registry`, which panics if its lock has been poisoned by a panic
+//! during registration.
 
 #![doc(html_root_url = "https://docs.rs/metatype/0.2.1")]
 #![feature(arbitrary_self_types)]
+#![feature(const_eval_select)]
+#![feature(const_destruct)]
+#![feature(const_trait_impl)]
+#![feature(const_type_name)]
+#![feature(core_intrinsics)]
+#![feature(fn_traits)]
+#![feature(layout_for_ptr)]
 #![feature(ptr_metadata)]
+#![feature(sized_hierarchy)]
 #![feature(specialization)]
+#![feature(strict_provenance_lints)]
+#![feature(tuple_trait)]
+#![feature(unboxed_closures)]
+#![feature(unsize)]
+#![cfg_attr(feature = "alloc", feature(allocator_api))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "unsized-locals", feature(unsized_fn_params))]
 #![warn(
+	fuzzy_provenance_casts,
+	lossy_provenance_casts,
 	missing_copy_implementations,
 	missing_debug_implementations,
 	missing_docs,
@@ -50,36 +103,508 @@
 	clippy::not_unsafe_ptr_arg_deref,
 	clippy::use_self,
 	clippy::missing_panics_doc,
-	incomplete_features
+	incomplete_features,
+	internal_features
 )]
 
-use std::{
-	any::{type_name, TypeId}, hash::{Hash, Hasher}, marker::PhantomData, mem::{align_of, align_of_val, forget, size_of, size_of_val, transmute_copy}, ptr::{slice_from_raw_parts_mut, NonNull}
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+	alloc::{alloc, dealloc, handle_alloc_error}, boxed::Box, rc::Rc, vec::Vec
+};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+use core::{
+	alloc::Layout, any::{type_name, Any, TypeId}, cell::{Cell, UnsafeCell}, convert::TryFrom, error::Error, ffi::{CStr, FromBytesWithNulError}, fmt, hash::{Hash, Hasher}, marker::{Destruct, MetaSized, PhantomData, PointeeSized, Unsize}, mem::{
+		align_of, align_of_val, align_of_val_raw, forget, size_of, size_of_val, size_of_val_raw, transmute_copy, ManuallyDrop, MaybeUninit
+	}, ptr::{self, slice_from_raw_parts_mut, DynMetadata, NonNull, Pointee}
+};
+#[cfg(feature = "std")]
+use std::{ffi::OsStr, path::Path};
+
+#[cfg(feature = "alloc")]
+pub mod boxed;
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod derive;
+mod dispatch;
+mod downcast;
+#[cfg(feature = "alloc")]
+pub mod erased;
+pub mod erased_fn;
+#[cfg(feature = "alloc")]
+pub mod flatten;
+pub mod fmt_fat;
+#[cfg(feature = "std")]
+pub mod fn_ptr;
+#[cfg(feature = "alloc")]
+pub mod future;
+pub mod raw;
+#[cfg(feature = "std")]
+pub mod registry;
+pub mod scoped;
+#[cfg(feature = "alloc")]
+pub mod shared;
+#[cfg(feature = "alloc")]
+pub mod soa;
+pub mod stack;
+#[cfg(feature = "static-registry")]
+pub mod static_registry;
+#[cfg(all(feature = "unsized-locals", feature = "alloc"))]
+pub mod unsized_locals;
+#[cfg(kani)]
+mod verification;
+
+#[cfg(feature = "alloc")]
+pub use downcast::downcast_box_unchecked;
+pub use downcast::{
+	downcast_mut_unchecked, downcast_ref_unchecked, downcast_unchecked, is_exactly
 };
 
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use alloc::boxed::Box as __Box;
+
+#[cfg(all(test, any(feature = "derive", feature = "inventory")))]
+extern crate self as metatype;
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+pub use inventory;
+/// Describe a trait to the [`registry`], as [`describe_trait!`] does, and add
+/// methods to its trait object type: `vtable_of::<T>()`, which registers `T`
+/// and returns its canonical vtable; `concrete_type_id`; and `is`,
+/// `downcast_ref`, `downcast_mut` and `downcast`, as with [`dyn Any`](Any)
+/// but without requiring it as a supertrait.
+///
+/// The trait can't be generic. Requires the `derive` feature.
+///
+/// ```
+/// # use metatype::{dyn_trait, registry};
+/// use std::any::TypeId;
+///
+/// #[dyn_trait]
+/// trait Shape {
+///     fn area(&self) -> u64;
+/// }
+/// struct Square(u64);
+/// impl Shape for Square {
+///     fn area(&self) -> u64 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let _ = <dyn Shape>::vtable_of::<Square>();
+/// assert_eq!(registry::descriptor::<dyn Shape>().methods, ["area"]);
+/// let a: Box<dyn Shape> = Box::new(Square(2));
+/// assert_eq!(a.concrete_type_id(), Some(TypeId::of::<Square>()));
+/// assert_eq!(a.downcast_ref::<Square>().map(|a| a.0), Some(2));
+/// assert!(a.downcast::<Square>().is_ok());
+/// ```
+#[cfg(feature = "derive")]
+pub use metatype_derive::dyn_trait;
+/// Register a concrete type as an implementor of a trait object type with
+/// [`registry::register_impl`], the first time the registry is accessed.
+///
+/// Requires the `inventory` feature.
+///
+/// ```
+/// # use metatype::{describe_trait, register_impl, registry};
+/// # use std::any::TypeId;
+/// trait Shape {
+///     fn area(&self) -> u64;
+/// }
+/// describe_trait!(Shape { area });
+///
+/// #[register_impl(dyn Shape)]
+/// #[derive(Clone)]
+/// struct Square(u64);
+/// impl Shape for Square {
+///     fn area(&self) -> u64 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let vtable = registry::canonical_vtable(TypeId::of::<Square>(), TypeId::of::<dyn Shape>());
+/// assert!(vtable.is_some());
+/// ```
+#[cfg(feature = "inventory")]
+pub use metatype_derive::register_impl;
+/// Implement [`Type`] for a struct whose last field is a slice, `str`, or
+/// another [`IsSlice`] or [`IsComposite`] type, forwarding its meta data to
+/// that field.
+///
+/// Its [`Type::METATYPE`] is [`MetaType::Composite`], and its meta data a
+/// [`Composite`] holding that of the last field.
+///
+/// If the last field's type is a type parameter, it's implemented for that
+/// parameter being `[T]` and `str`; for `dyn Trait`, the blanket impl already
+/// suffices. Requires the `derive` feature, and the `specialization` and
+/// `arbitrary_self_types` features in the deriving crate.
+///
+/// ```
+/// #![feature(arbitrary_self_types, specialization)]
+/// # #![allow(incomplete_features)]
+/// # use metatype::*;
+/// #[derive(Type)]
+/// struct Header<T: ?Sized> {
+///     len: u32,
+///     tail: T,
+/// }
+///
+/// let a: &Header<[u8]> = &Header { len: 3, tail: [1, 2, 3] };
+/// let meta = Composite { tail: Slice { len: 3 } };
+/// assert_eq!(<Header<[u8]>>::METATYPE, MetaType::Composite);
+/// assert_eq!(Type::meta(a), meta);
+/// assert_eq!(<Header<[u8]>>::size_from_meta(&meta), Some(8));
+/// ```
+#[cfg(feature = "derive")]
+pub use metatype_derive::Type;
+
 /// Implemented on all types, it provides helper methods to determine whether a type is `TraitObject`, `Slice` or `Concrete`, and work with them respectively.
-pub trait Type {
+pub trait Type: PointeeSized {
 	/// Enum describing whether a type is `TraitObject`, `Slice` or `Concrete`.
 	const METATYPE: MetaType;
+	/// Size in bytes of the type, if it's `Sized`.
+	const SIZE: Option<usize>;
+	/// Alignment in bytes of the type, if it's `Sized`.
+	const ALIGN: Option<usize>;
+	/// Name of the type, as given by [`type_name`](std::any::type_name).
+	const NAME: &'static str = pointee_name(type_name::<*const Self>());
 	/// Type of metadata for type.
-	type Meta: 'static;
+	type Meta: Copy + Send + Sync + Unpin + Eq + fmt::Debug + Hash + 'static;
 	/// Helper method describing whether a type is `TraitObject`, `Slice` or `Concrete`.
 	fn meta_type(self: *const Self) -> MetaType {
 		Self::METATYPE
 	}
+	/// Helper method returning the name of the type, as given by
+	/// [`type_name`](std::any::type_name).
+	///
+	/// For a trait object this is the name of the trait object type, such as
+	/// `dyn core::any::Any`, rather than of the type erased behind it.
+	fn name(self: *const Self) -> &'static str {
+		Self::NAME
+	}
 	/// Retrieve [`TraitObject`], [`Slice`] or [`Concrete`] meta data respectively for a type
+	///
+	/// With the `no-panic` feature enabled, the sanity checks of slice meta
+	/// data are omitted, leaving no panic paths in the implementations of
+	/// `meta`, `dangling`, `fatten`, `size_from_meta`, `fatten_within` and
+	/// `try_fatten`, nor in [`emplace_raw`]. It also adds non-panicking
+	/// counterparts, returning [`MetatypeError`], of functions that otherwise
+	/// panic.
 	fn meta(self: *const Self) -> Self::Meta;
+	/// Retrieve [`TraitObject`], [`Slice`] or [`Concrete`] meta data
+	/// respectively for a type, omitting the sanity checks that
+	/// [`meta`](Type::meta) performs on slice meta data.
+	///
+	/// # Safety
+	///
+	/// The meta data of `self` must be valid for `Self`, as it is for any
+	/// pointer derived from a reference.
+	unsafe fn meta_unchecked(self: *const Self) -> Self::Meta;
+	/// Retrieve [`TraitObject`], [`Slice`] or [`Concrete`] meta data
+	/// respectively for a type, checking it as [`meta`](Type::meta) does
+	/// without the `no-panic` feature, but returning an error rather than
+	/// panicking.
+	///
+	/// # Errors
+	///
+	/// Returns [`MetatypeError::InvalidMeta`] if the size or alignment of the
+	/// value is inconsistent with its meta data.
+	#[cfg(feature = "no-panic")]
+	fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError>;
+	/// Retrieve the meta data for a type as a [`Meta`], erasing which of
+	/// [`TraitObject`], [`Slice`], [`Concrete`], [`ExternMeta`] or
+	/// [`Composite`] it is
+	#[inline]
+	fn meta_erased(self: *const Self) -> Meta {
+		let t = self.meta();
+		match Self::METATYPE {
+			MetaType::TraitObject => Meta::TraitObject(type_coerce(t)),
+			MetaType::Slice => Meta::Slice(type_coerce(t)),
+			MetaType::Concrete => Meta::Concrete(type_coerce(t)),
+			MetaType::Extern => Meta::Extern(type_coerce(t)),
+			MetaType::Composite => Meta::Composite(type_coerce(t)),
+		}
+	}
+	/// Describe the type and the value pointed to, as a [`MetaInfo`]
+	///
+	/// ```
+	/// # use metatype::*;
+	/// let a: &[u16] = &[1, 2, 3];
+	/// let info = Type::info(a);
+	/// assert_eq!((info.name(), info.size(), info.align()), ("[u16]", Some(6), 2));
+	/// assert_eq!(info.type_id(), Some(std::any::TypeId::of::<[u16]>()));
+	/// ```
+	#[inline]
+	fn info(self: *const Self) -> MetaInfo
+	where
+		Self: MetaSized + 'static,
+	{
+		MetaInfo {
+			type_id: Some(TypeId::of::<Self>()),
+			..self.info_non_static()
+		}
+	}
+	/// Describe the type and the value pointed to, as a [`MetaInfo`], for a
+	/// type that mightn't be `'static` and so has no [`TypeId`]
+	#[inline]
+	fn info_non_static(self: *const Self) -> MetaInfo
+	where
+		Self: MetaSized,
+	{
+		let t = self.meta();
+		MetaInfo {
+			name: Self::NAME,
+			type_id: None,
+			meta_type: Self::METATYPE,
+			size: Self::size_from_meta(&t),
+			align: align_of_val_meta::<Self>(t),
+		}
+	}
 	/// Retrieve pointer to the data
 	fn data(self: *const Self) -> *const ();
 	/// Retrieve mut pointer to the data
 	fn data_mut(self: *mut Self) -> *mut ();
+	/// Retrieve pointer to the data and the meta data, the inverse of
+	/// [`fatten`](Type::fatten)
+	///
+	/// ```
+	/// # use metatype::*;
+	/// let a: *const [u8] = &[1, 2, 3][..];
+	/// let (thin, meta) = Type::split(a);
+	/// assert_eq!(<[u8]>::fatten_const(thin, meta), a);
+	/// ```
+	#[inline]
+	fn split(self: *const Self) -> (*const (), Self::Meta) {
+		(self.data(), self.meta())
+	}
+	/// Retrieve mut pointer to the data and the meta data, the inverse of
+	/// [`fatten`](Type::fatten)
+	#[inline]
+	fn split_mut(self: *mut Self) -> (*mut (), Self::Meta) {
+		(self.data_mut(), self.meta())
+	}
+	/// Replace the meta data, keeping the data pointer and its provenance
+	///
+	/// ```
+	/// # use metatype::*;
+	/// let mut a = [1_u8, 2, 3];
+	/// let b = Type::with_meta(&mut a[..] as *mut [u8], Slice { len: 2 });
+	/// assert_eq!(unsafe { &*b }, [1, 2]);
+	/// ```
+	#[inline]
+	#[must_use]
+	fn with_meta(self: *mut Self, t: Self::Meta) -> *mut Self {
+		Self::fatten(self.data_mut(), t)
+	}
+	/// Replace the meta data, keeping the data pointer and its provenance
+	#[inline]
+	#[must_use]
+	fn with_meta_const(self: *const Self, t: Self::Meta) -> *const Self {
+		Self::fatten_const(self.data(), t)
+	}
 	/// Create a dangling non-null `*const Self` with the provided `Self::Meta`.
 	fn dangling(t: Self::Meta) -> NonNull<Self>;
+	/// Create a dangling non-null `*const Self` with the provided `Self::Meta`,
+	/// aligned to `align` rather than to the alignment of `Self`.
+	///
+	/// `align` is typically that of the storage the value will occupy. It's
+	/// used as the address without being inspected, so should be a power of
+	/// two at least the alignment of `Self` for the pointer to be aligned.
+	///
+	/// # Panics
+	///
+	/// Panics if `align` is zero. With the `no-panic` feature,
+	/// [`try_dangling_with_align`](Type::try_dangling_with_align) returns an
+	/// error instead.
+	#[inline]
+	fn dangling_with_align(t: Self::Meta, align: usize) -> NonNull<Self> {
+		NonNull::new(Self::fatten(ptr::without_provenance_mut(align), t))
+			.expect("align must be non-zero")
+	}
+	/// Create a dangling non-null `*const Self` with the provided
+	/// `Self::Meta`, with address `align`.
+	///
+	/// # Errors
+	///
+	/// Returns [`MetatypeError::ZeroAlign`] if `align` is zero.
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	fn try_dangling_with_align(
+		t: Self::Meta, align: usize,
+	) -> Result<NonNull<Self>, MetatypeError> {
+		NonNull::new(Self::fatten(ptr::without_provenance_mut(align), t))
+			.ok_or(MetatypeError::ZeroAlign)
+	}
 	/// Create a `*mut Self` with the provided `Self::Meta`.
 	fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self;
+	/// Create a `*const Self` with the provided `Self::Meta`.
+	#[inline]
+	fn fatten_const(thin: *const (), t: Self::Meta) -> *const Self {
+		Self::fatten(thin.cast_mut(), t)
+	}
+	/// Create a `*mut Self` with the provided `Self::Meta`, checking that the
+	/// parts are plausible, such as when received from an untrusted source.
+	///
+	/// ```
+	/// # use metatype::*;
+	/// let a = [0_u32; 2];
+	/// let thin = a.as_ptr().cast_mut().cast();
+	/// assert!(<[u32]>::try_fatten(thin, Slice { len: 2 }).is_ok());
+	/// assert_eq!(
+	///     <[u32]>::try_fatten(thin, Slice { len: usize::MAX }),
+	///     Err(FattenError::SizeOverflow)
+	/// );
+	/// ```
+	///
+	/// # Errors
+	///
+	/// Returns [`FattenError::Null`] if `thin` is null,
+	/// [`FattenError::InvalidVtable`] if, with debug assertions enabled, the
+	/// vtable address is implausible, [`FattenError::SizeOverflow`] if the
+	/// size of the value would overflow `isize`, and
+	/// [`FattenError::Misaligned`] if `thin` isn't aligned for the value.
+	#[inline]
+	fn try_fatten(thin: *mut (), t: Self::Meta) -> Result<*mut Self, FattenError>
+	where
+		Self: MetaSized,
+	{
+		if thin.is_null() {
+			return Err(FattenError::Null);
+		}
+		if cfg!(debug_assertions) {
+			if let Some(TraitObject { vtable }) = try_type_coerce(t) {
+				let vtable = ptr::from_ref(vtable).addr();
+				if vtable < 4096 || vtable & (align_of::<usize>() - 1) != 0 {
+					return Err(FattenError::InvalidVtable { vtable });
+				}
+			}
+		}
+		if Self::size_from_meta(&t).is_none() {
+			return Err(FattenError::SizeOverflow);
+		}
+		let align = align_of_val_meta::<Self>(t);
+		let addr = thin.addr();
+		if addr & align.wrapping_sub(1) != 0 {
+			return Err(FattenError::Misaligned { addr, align });
+		}
+		Ok(Self::fatten(thin, t))
+	}
+	/// Size in bytes of a value with the provided `Self::Meta`, or `None` if it
+	/// would overflow `isize` or, as for an `extern type`, is unknown.
+	fn size_from_meta(t: &Self::Meta) -> Option<usize>;
+	/// Alignment in bytes of a value with the provided `Self::Meta`, or `None`
+	/// if, as for an `extern type`, it is unknown.
+	fn align_from_meta(t: &Self::Meta) -> Option<usize>;
+	/// The [`Layout`] of a value with the provided `Self::Meta`.
+	///
+	/// # Panics
+	///
+	/// Panics if the size of the value, rounded up to its alignment, would
+	/// overflow `isize`. With the `no-panic` feature,
+	/// [`try_layout`](Type::try_layout) returns an error instead.
+	#[inline]
+	fn layout(t: Self::Meta) -> Layout
+	where
+		Self: MetaSized,
+	{
+		let size = Self::size_from_meta(&t).expect("size of value overflows isize");
+		Layout::from_size_align(size, align_of_val_meta::<Self>(t))
+			.expect("size of value overflows isize")
+	}
+	/// The [`Layout`] of a value with the provided `Self::Meta`.
+	///
+	/// # Errors
+	///
+	/// Returns [`MetatypeError::Fatten`] with [`FattenError::SizeOverflow`] if
+	/// the size of the value, rounded up to its alignment, would overflow
+	/// `isize`.
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	fn try_layout(t: Self::Meta) -> Result<Layout, MetatypeError>
+	where
+		Self: MetaSized,
+	{
+		let size = Self::size_from_meta(&t).ok_or(FattenError::SizeOverflow)?;
+		Layout::from_size_align(size, align_of_val_meta::<Self>(t))
+			.map_err(|_| MetatypeError::Fatten(FattenError::SizeOverflow))
+	}
+	/// Create a `*mut Self` with the provided `Self::Meta`, pointing `offset`
+	/// bytes into `buffer`, if the value would lie entirely within it.
+	///
+	/// # Errors
+	///
+	/// Returns [`FattenError::OutOfBounds`] if the value would extend beyond
+	/// the end of `buffer`, and [`FattenError::SizeOverflow`] if its size
+	/// would overflow `isize`.
+	fn fatten_within(
+		buffer: *mut [u8], offset: usize, t: Self::Meta,
+	) -> Result<*mut Self, FattenError> {
+		let size = Self::size_from_meta(&t).ok_or(FattenError::SizeOverflow)?;
+		let len = buffer.len();
+		if offset.checked_add(size).is_none_or(|end| end > len) {
+			return Err(FattenError::OutOfBounds { offset, size, len });
+		}
+		let thin = unsafe { buffer.cast::<u8>().add(offset) };
+		Ok(Self::fatten(thin.cast(), t))
+	}
+}
+/// Implemented on types whose [`Type::METATYPE`] is [`MetaType::Concrete`],
+/// i.e. all `Sized` types.
+///
+/// ```
+/// # use metatype::*;
+/// fn size<T: IsConcrete>(meta: T::Meta) -> usize {
+///     let Concrete = T::concrete(meta);
+///     std::mem::size_of::<T>()
+/// }
+/// assert_eq!(size::<u16>(Concrete), 2);
+/// ```
+pub trait IsConcrete: Type + Sized {
+	/// Retrieve the [`Concrete`] meta data from `Self::Meta`.
+	#[inline]
+	fn concrete(t: Self::Meta) -> Concrete {
+		type_coerce(t)
+	}
+}
+impl<T: Sized> IsConcrete for T {}
+/// Implemented on types whose [`Type::METATYPE`] is [`MetaType::Slice`],
+/// i.e. `[T]`, `str`, and types wrapping them like `CStr` and
+/// `ManuallyDrop<[T]>`.
+pub trait IsSlice: Type {
+	/// Retrieve the [`Slice`] meta data from `Self::Meta`.
+	#[inline]
+	fn slice(t: Self::Meta) -> Slice {
+		type_coerce(t)
+	}
+}
+impl<T: Sized> IsSlice for [T] {}
+impl IsSlice for str {}
+/// Implemented on types whose [`Type::METATYPE`] is
+/// [`MetaType::TraitObject`] by virtue of being `dyn Trait`.
+pub trait IsTraitObject: Type {
+	/// Retrieve the [`TraitObject`] meta data from `Self::Meta`.
+	#[inline]
+	fn trait_object(t: Self::Meta) -> TraitObject {
+		type_coerce(t)
+	}
+}
+impl<T: ?Sized + Pointee<Metadata = DynMetadata<T>>> IsTraitObject for T {}
+/// Implemented on types whose [`Type::METATYPE`] is
+/// [`MetaType::Composite`], i.e. those deriving `Type`.
+pub trait IsComposite: Type {
+	/// Retrieve the [`Composite`] meta data from `Self::Meta`.
+	#[inline]
+	fn composite(t: Self::Meta) -> Composite {
+		type_coerce(t)
+	}
 }
+
 /// Meta type of a type
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum MetaType {
 	/// Trait object, thus unsized
 	TraitObject,
@@ -87,37 +612,558 @@ pub enum MetaType {
 	Slice,
 	/// Sized type
 	Concrete,
+	/// `extern type`, thus unsized and of unknown size
+	Extern,
+	/// Struct whose last field is unsized, such as one deriving `Type`
+	Composite,
 }
 
 /// Meta data for a trait object
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+///
+/// Equality and hashing are by the address of the vtable.
+///
+/// This is `#[repr(transparent)]`, so is laid out and passed across FFI as a
+/// `const void *`.
+#[derive(Copy, Clone, Debug)]
+#[repr(transparent)]
 pub struct TraitObject {
 	/// Address of vtable
 	pub vtable: &'static (),
 }
+impl PartialEq for TraitObject {
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		ptr::eq(self.vtable, other.vtable)
+	}
+}
+impl Eq for TraitObject {}
+impl Hash for TraitObject {
+	#[inline]
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		ptr::from_ref(self.vtable).hash(state);
+	}
+}
+impl TraitObject {
+	/// Map this vtable to the canonical vtable of its (type, trait) pair, as
+	/// described in [`registry::canonicalize`]. Only registered vtables are
+	/// recognised; see [`registry::canonicalize_of_val`] for unregistered
+	/// duplicates.
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn canonicalize(self) -> Self {
+		registry::canonicalize(self)
+	}
+	/// Size in bytes of the type this is the vtable of.
+	///
+	/// ```
+	/// # use metatype::*;
+	/// let meta = vtable_of::<(u8, u32), dyn std::fmt::Debug>();
+	/// assert_eq!((meta.size_of(), meta.align_of()), (8, 4));
+	/// ```
+	#[inline]
+	#[must_use]
+	pub fn size_of(self) -> usize {
+		self.dyn_metadata().size_of()
+	}
+	/// Alignment in bytes of the type this is the vtable of.
+	#[inline]
+	#[must_use]
+	pub fn align_of(self) -> usize {
+		self.dyn_metadata().align_of()
+	}
+	/// The drop glue of the type this is the vtable of, or `None` if dropping
+	/// it is a no-op.
+	///
+	/// This relies on rustc's vtable layout, of drop glue, size and align
+	/// followed by the methods.
+	///
+	/// ```
+	/// # use metatype::*;
+	/// use std::{fmt::Debug, mem::ManuallyDrop};
+	///
+	/// assert!(vtable_of::<u8, dyn Debug>().drop_fn().is_none());
+	/// let drop_fn = vtable_of::<String, dyn Debug>().drop_fn().unwrap();
+	/// let mut a = ManuallyDrop::new(String::from("abc"));
+	/// unsafe { drop_fn(std::ptr::from_mut(&mut *a).cast()) };
+	/// ```
+	#[cfg(feature = "vtable-layout")]
+	#[inline]
+	#[must_use]
+	pub fn drop_fn(self) -> Option<unsafe fn(*mut ())> {
+		let drop_fn = unsafe { vtable_slots(self, 1) }[0];
+		(!drop_fn.is_null()).then(|| unsafe { transmute_copy(&drop_fn) })
+	}
+	#[inline]
+	fn dyn_metadata(self) -> DynMetadata<dyn Any> {
+		unsafe { transmute_coerce(self.vtable) }
+	}
+}
 /// Meta data for a slice
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+///
+/// This is `#[repr(transparent)]`, so is laid out and passed across FFI as a
+/// `size_t`, the length.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
 pub struct Slice {
 	/// Number of elements in the slice
 	pub len: usize,
 }
+impl Slice {
+	/// The length as a `u64`, to be sent to a target that may have a different
+	/// pointer width.
+	///
+	/// ```
+	/// # use metatype::Slice;
+	/// let wire = Slice { len: 3 }.to_wire();
+	/// assert_eq!(Slice::from_wire(wire), Ok(Slice { len: 3 }));
+	/// ```
+	#[inline]
+	#[must_use]
+	pub const fn to_wire(self) -> u64 {
+		self.len as u64
+	}
+	/// The meta data with the length `wire`, as returned by [`Slice::to_wire`].
+	///
+	/// # Errors
+	///
+	/// [`WireError::Overflow`] if the length doesn't fit in a `usize` on this
+	/// target.
+	#[inline]
+	pub fn from_wire(wire: u64) -> Result<Self, WireError> {
+		match usize::try_from(wire) {
+			Ok(len) => Ok(Self { len }),
+			Err(_) => Err(WireError::Overflow { wire }),
+		}
+	}
+}
+/// Meta data for a struct whose last field is unsized, such as one deriving
+/// `Type`
+///
+/// The last field is, perhaps transitively, a slice or `str`, and `tail` is
+/// its meta data.
+///
+/// This is `#[repr(transparent)]`, so is laid out and passed across FFI as
+/// its [`Slice`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(transparent)]
+pub struct Composite {
+	/// Meta data of the last field
+	pub tail: Slice,
+}
+impl Composite {
+	/// The length of the tail as a `u64`. See [`Slice::to_wire`].
+	#[inline]
+	#[must_use]
+	pub const fn to_wire(self) -> u64 {
+		self.tail.to_wire()
+	}
+	/// The meta data with the tail length `wire`. See [`Slice::from_wire`].
+	///
+	/// # Errors
+	///
+	/// [`WireError::Overflow`] if the length doesn't fit in a `usize` on this
+	/// target.
+	#[inline]
+	pub fn from_wire(wire: u64) -> Result<Self, WireError> {
+		Slice::from_wire(wire).map(|tail| Self { tail })
+	}
+}
 /// Meta data for a concrete, sized type
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+///
+/// This is `#[repr(C)]` and zero-sized, so has no C equivalent; it can be
+/// omitted from C signatures.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(C)]
 pub struct Concrete;
+/// Meta data for an `extern type`
+///
+/// ```
+/// #![feature(extern_types, ptr_metadata)]
+/// # use metatype::*;
+/// extern "C" {
+///     type Foreign;
+/// }
+/// assert_eq!(Foreign::METATYPE, MetaType::Extern);
+/// assert!(Foreign::NAME.ends_with("::Foreign"));
+/// let a = 0_u8;
+/// let a: *const Foreign = std::ptr::from_raw_parts(std::ptr::from_ref(&a), ());
+/// let meta: ExternMeta = type_coerce(Type::meta(a));
+/// assert_eq!(Foreign::fatten_const(Type::data(a), type_coerce(meta)), a);
+/// assert_eq!(Foreign::size_from_meta(&type_coerce(meta)), None);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ExternMeta;
+
+// The meta data structs are the meta data half of a fat pointer, which
+// `fatten` and `data` rely on: a pointer and a `usize`, 4 bytes each on
+// wasm32 and 8 on 64-bit targets. Checked here so a target where that
+// doesn't hold fails to build rather than misbehaving.
+const _: () = {
+	assert!(size_of::<*const dyn Any>() == size_of::<*const ()>() + size_of::<TraitObject>());
+	assert!(size_of::<*const [u8]>() == size_of::<*const ()>() + size_of::<Slice>());
+	assert!(size_of::<TraitObject>() == size_of::<DynMetadata<dyn Any>>());
+	assert!(align_of::<TraitObject>() == align_of::<DynMetadata<dyn Any>>());
+	assert!(size_of::<Slice>() == size_of::<usize>() && align_of::<Slice>() == align_of::<usize>());
+	assert!(size_of::<Composite>() == size_of::<Slice>());
+	#[cfg(target_arch = "wasm32")]
+	assert!(
+		size_of::<TraitObject>() == 4
+			&& size_of::<Slice>() == 4
+			&& size_of::<*const dyn Any>() == 8
+			&& size_of::<*const [u8]>() == 8
+	);
+};
+
+/// Meta data of any type, for storing the meta data of types of differing
+/// [`MetaType`] together
+///
+/// ```
+/// # use metatype::*;
+/// use std::{convert::TryFrom, fmt::Debug};
+///
+/// let a: [*const dyn Debug; 1] = [&1_u8];
+/// let metas = [Type::meta_erased(a[0]), Type::meta_erased(&[1_u8, 2] as *const [u8])];
+/// assert_eq!(metas[0].meta_type(), MetaType::TraitObject);
+/// assert_eq!(Slice::try_from(metas[1]), Ok(Slice { len: 2 }));
+/// assert_eq!(Concrete::try_from(metas[1]), Err(metas[1]));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Meta {
+	/// Meta data for a trait object
+	TraitObject(TraitObject),
+	/// Meta data for a slice
+	Slice(Slice),
+	/// Meta data for a concrete, sized type
+	Concrete(Concrete),
+	/// Meta data for an `extern type`
+	Extern(ExternMeta),
+	/// Meta data for a struct whose last field is unsized
+	Composite(Composite),
+}
+impl Meta {
+	/// The [`MetaType`] of the type this is the meta data of
+	#[inline]
+	#[must_use]
+	pub const fn meta_type(self) -> MetaType {
+		match self {
+			Self::TraitObject(_) => MetaType::TraitObject,
+			Self::Slice(_) => MetaType::Slice,
+			Self::Concrete(_) => MetaType::Concrete,
+			Self::Extern(_) => MetaType::Extern,
+			Self::Composite(_) => MetaType::Composite,
+		}
+	}
+	/// The meta data as a `u64`, to be sent along with its [`MetaType`] to a
+	/// target that may have a different pointer width: the length for a
+	/// slice or composite, and `0` for a concrete type or `extern type`.
+	///
+	/// ```
+	/// # use metatype::{Meta, MetaType, Slice};
+	/// let meta = Meta::Slice(Slice { len: 3 });
+	/// let wire = meta.to_wire().unwrap();
+	/// assert_eq!(Meta::from_wire(meta.meta_type(), wire), Ok(meta));
+	/// ```
+	///
+	/// # Errors
+	///
+	/// [`WireError::TraitObject`] for a trait object, as its vtable is only
+	/// meaningful within this process.
+	#[inline]
+	pub const fn to_wire(self) -> Result<u64, WireError> {
+		match self {
+			Self::TraitObject(_) => Err(WireError::TraitObject),
+			Self::Slice(t) => Ok(t.to_wire()),
+			Self::Composite(t) => Ok(t.to_wire()),
+			Self::Concrete(_) | Self::Extern(_) => Ok(0),
+		}
+	}
+	/// The meta data of type `meta_type` from `wire`, as returned by
+	/// [`Meta::to_wire`].
+	///
+	/// # Errors
+	///
+	/// [`WireError::Overflow`] if a length doesn't fit in a `usize` on this
+	/// target, or is nonzero for a concrete type or `extern type`, and
+	/// [`WireError::TraitObject`] for a trait object.
+	#[inline]
+	pub fn from_wire(meta_type: MetaType, wire: u64) -> Result<Self, WireError> {
+		match meta_type {
+			MetaType::TraitObject => Err(WireError::TraitObject),
+			MetaType::Slice => Slice::from_wire(wire).map(Self::Slice),
+			MetaType::Composite => Composite::from_wire(wire).map(Self::Composite),
+			MetaType::Concrete | MetaType::Extern if wire != 0 => Err(WireError::Overflow { wire }),
+			MetaType::Concrete => Ok(Self::Concrete(Concrete)),
+			MetaType::Extern => Ok(Self::Extern(ExternMeta)),
+		}
+	}
+}
+
+/// Description of a type and of a value of it: its name, [`TypeId`],
+/// [`MetaType`], size and alignment
+///
+/// Returned by [`Type::info`] and [`info_of_val`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MetaInfo {
+	name: &'static str,
+	type_id: Option<TypeId>,
+	meta_type: MetaType,
+	size: Option<usize>,
+	align: usize,
+}
+impl MetaInfo {
+	/// The name of the type, as given by [`Type::NAME`].
+	#[must_use]
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+	/// The type id of the type, or `None` if from
+	/// [`Type::info_non_static`].
+	#[must_use]
+	pub fn type_id(&self) -> Option<TypeId> {
+		self.type_id
+	}
+	/// The meta type of the type.
+	#[must_use]
+	pub fn meta_type(&self) -> MetaType {
+		self.meta_type
+	}
+	/// The size of the value, or `None` if it's unknown, as for an
+	/// `extern type`.
+	#[must_use]
+	pub fn size(&self) -> Option<usize> {
+		self.size
+	}
+	/// The alignment of the value.
+	#[must_use]
+	pub fn align(&self) -> usize {
+		self.align
+	}
+}
+
+macro_rules! meta_conversions {
+	($($variant:ident($meta:ident)),*) => {$(
+		impl From<$meta> for Meta {
+			#[inline]
+			fn from(t: $meta) -> Self {
+				Self::$variant(t)
+			}
+		}
+		impl TryFrom<Meta> for $meta {
+			type Error = Meta;
+
+			#[inline]
+			fn try_from(t: Meta) -> Result<Self, Meta> {
+				match t {
+					Meta::$variant(t) => Ok(t),
+					t => Err(t),
+				}
+			}
+		}
+	)*};
+}
+meta_conversions!(
+	TraitObject(TraitObject),
+	Slice(Slice),
+	Concrete(Concrete),
+	Extern(ExternMeta),
+	Composite(Composite)
+);
+
+/// Error returned when a fat pointer can't be constructed from the provided
+/// parts.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FattenError {
+	/// The data pointer is null
+	Null,
+	/// The data pointer isn't sufficiently aligned for the value
+	Misaligned {
+		/// Address of the data pointer
+		addr: usize,
+		/// Alignment of the value
+		align: usize,
+	},
+	/// The vtable address is implausible
+	InvalidVtable {
+		/// Address of the vtable
+		vtable: usize,
+	},
+	/// The size of the value would overflow `isize`
+	SizeOverflow,
+	/// The value would extend beyond the end of the buffer
+	OutOfBounds {
+		/// Offset of the value into the buffer
+		offset: usize,
+		/// Size of the value
+		size: usize,
+		/// Length of the buffer
+		len: usize,
+	},
+}
+impl fmt::Display for FattenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Null => f.write_str("data pointer is null"),
+			Self::Misaligned { addr, align } => {
+				write!(f, "data pointer {addr:#x} isn't aligned to {align}")
+			}
+			Self::InvalidVtable { vtable } => write!(f, "vtable address {vtable:#x} is invalid"),
+			Self::SizeOverflow => f.write_str("size of value overflows isize"),
+			Self::OutOfBounds { offset, size, len } => write!(
+				f,
+				"value of size {size} at offset {offset} extends beyond buffer of length {len}"
+			),
+		}
+	}
+}
+impl Error for FattenError {}
+
+/// Error returned when meta data can't be converted to or from its portable
+/// `u64` representation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WireError {
+	/// The value doesn't fit in the meta data on this target
+	Overflow {
+		/// The `u64` representation
+		wire: u64,
+	},
+	/// Trait object meta data has no portable representation
+	TraitObject,
+}
+impl fmt::Display for WireError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Overflow { wire } => write!(f, "meta data {wire} overflows this target"),
+			Self::TraitObject => f.write_str("trait object meta data isn't portable"),
+		}
+	}
+}
+impl Error for WireError {}
 
+/// Error returned by the non-panicking counterparts, available with the
+/// `no-panic` feature, of functions that otherwise panic.
+#[cfg(feature = "no-panic")]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MetatypeError {
+	/// The types passed to [`try_type_coerce_err`] differ
+	TypeMismatch {
+		/// Name of the type coerced from
+		from: &'static str,
+		/// Name of the type coerced to
+		to: &'static str,
+	},
+	/// The size or alignment of a value is inconsistent with its meta data
+	InvalidMeta,
+	/// The alignment is zero
+	ZeroAlign,
+	/// A fat pointer can't be constructed from the provided parts
+	Fatten(FattenError),
+}
+#[cfg(feature = "no-panic")]
+impl From<FattenError> for MetatypeError {
+	#[inline]
+	fn from(err: FattenError) -> Self {
+		Self::Fatten(err)
+	}
+}
+#[cfg(feature = "no-panic")]
+impl fmt::Display for MetatypeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::TypeMismatch { from, to } => write!(f, "can't coerce {from} to {to}"),
+			Self::InvalidMeta => {
+				f.write_str("size or alignment of value is inconsistent with its meta data")
+			}
+			Self::ZeroAlign => f.write_str("align must be non-zero"),
+			Self::Fatten(err) => err.fmt(f),
+		}
+	}
+}
+#[cfg(feature = "no-panic")]
+impl Error for MetatypeError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			Self::Fatten(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl<T: PointeeSized> Type for T {
+	#[doc(hidden)]
+	default const METATYPE: MetaType = MetaType::Extern;
+	#[doc(hidden)]
+	default const SIZE: Option<usize> = None;
+	#[doc(hidden)]
+	default const ALIGN: Option<usize> = None;
+	#[doc(hidden)]
+	default type Meta = ExternMeta;
+	#[inline]
+	default fn meta(self: *const Self) -> Self::Meta {
+		type_coerce(ExternMeta)
+	}
+	#[inline]
+	default unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+		self.meta()
+	}
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	default fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError> {
+		Ok(self.meta())
+	}
+	#[inline]
+	default fn data(self: *const Self) -> *const () {
+		self.cast()
+	}
+	#[inline]
+	default fn data_mut(self: *mut Self) -> *mut () {
+		self.cast()
+	}
+	#[inline]
+	default fn dangling(t: Self::Meta) -> NonNull<Self> {
+		// the alignment of an extern type is unknown
+		unsafe { NonNull::new_unchecked(Self::fatten(ptr::without_provenance_mut(1), t)) }
+	}
+	#[inline]
+	default fn fatten(thin: *mut (), _t: Self::Meta) -> *mut Self {
+		// the metadata of an extern type is `()`
+		ptr::from_raw_parts_mut(thin, type_coerce(()))
+	}
+	#[inline]
+	default fn size_from_meta(_t: &Self::Meta) -> Option<usize> {
+		None
+	}
+	#[inline]
+	default fn align_from_meta(_t: &Self::Meta) -> Option<usize> {
+		None
+	}
+}
+#[doc(hidden)]
 impl<T: ?Sized> Type for T {
 	#[doc(hidden)]
 	default const METATYPE: MetaType = MetaType::TraitObject;
 	#[doc(hidden)]
+	default const SIZE: Option<usize> = None;
+	#[doc(hidden)]
+	default const ALIGN: Option<usize> = None;
+	#[doc(hidden)]
 	default type Meta = TraitObject;
 	#[inline]
 	default fn meta(self: *const Self) -> Self::Meta {
 		let ret = TraitObject {
-			vtable: unsafe { transmute_coerce(std::ptr::metadata(self)) },
+			vtable: unsafe { transmute_coerce(ptr::metadata(self)) },
 		};
 		type_coerce(ret)
 	}
 	#[inline]
+	default unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+		self.meta()
+	}
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	default fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError> {
+		Ok(self.meta())
+	}
+	#[inline]
 	default fn data(self: *const Self) -> *const () {
 		self.cast()
 	}
@@ -128,32 +1174,34 @@ impl<T: ?Sized> Type for T {
 	#[inline]
 	default fn dangling(t: Self::Meta) -> NonNull<Self> {
 		let t: TraitObject = type_coerce(t);
-		// align_of_val requires a reference: https://github.com/rust-lang/rfcs/issues/2017
-		// so to placate miri let's create one that's plausibly valid
-		let fake_thin = {
-			#[repr(align(64))]
-			struct Backing(u8);
-			static BACKING: Backing = Backing(0);
-			let backing: *const _ = &BACKING;
-			backing as *mut ()
-		};
-		let dangling_unaligned: NonNull<Self> =
-			NonNull::new(Self::fatten(fake_thin, type_coerce(t))).unwrap();
-		let dangling_unaligned: &Self = unsafe { dangling_unaligned.as_ref() };
-		let align = align_of_val(dangling_unaligned);
-		NonNull::new(Self::fatten(align as _, type_coerce(t))).unwrap()
+		// the alignment is read from the vtable, so the data pointer needn't be valid
+		let align = unsafe { align_of_val_raw(Self::fatten(ptr::null_mut(), type_coerce(t))) };
+		NonNull::new(Self::fatten(ptr::without_provenance_mut(align), type_coerce(t))).unwrap()
 	}
 	#[inline]
 	default fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self {
 		let t: TraitObject = type_coerce(t);
 		let vtable: *const () = t.vtable;
-		let vtable = vtable as *mut ();
-		std::ptr::from_raw_parts_mut(thin, unsafe { transmute_coerce(vtable) })
+		ptr::from_raw_parts_mut(thin, unsafe { transmute_coerce(vtable.cast_mut()) })
+	}
+	#[inline]
+	default fn size_from_meta(t: &Self::Meta) -> Option<usize> {
+		let t: TraitObject = unsafe { transmute_copy(t) };
+		let ptr: *const Self = Self::fatten(ptr::null_mut(), type_coerce(t));
+		Some(unsafe { size_of_val_raw(ptr) })
+	}
+	#[inline]
+	default fn align_from_meta(t: &Self::Meta) -> Option<usize> {
+		let t: TraitObject = unsafe { transmute_copy(t) };
+		let ptr: *const Self = Self::fatten(ptr::null_mut(), type_coerce(t));
+		Some(unsafe { align_of_val_raw(ptr) })
 	}
 }
 #[doc(hidden)]
 impl<T: Sized> Type for T {
 	const METATYPE: MetaType = MetaType::Concrete;
+	const SIZE: Option<usize> = Some(size_of::<T>());
+	const ALIGN: Option<usize> = Some(align_of::<T>());
 	type Meta = Concrete;
 	#[inline]
 	fn meta(self: *const Self) -> Self::Meta {
@@ -173,21 +1221,48 @@ impl<T: Sized> Type for T {
 	fn fatten(thin: *mut (), _t: Self::Meta) -> *mut Self {
 		thin.cast()
 	}
+	#[inline]
+	fn size_from_meta(_t: &Self::Meta) -> Option<usize> {
+		Some(size_of::<T>())
+	}
+	#[inline]
+	fn align_from_meta(_t: &Self::Meta) -> Option<usize> {
+		Some(align_of::<T>())
+	}
 }
 #[doc(hidden)]
 impl<T: Sized> Type for [T] {
 	const METATYPE: MetaType = MetaType::Slice;
+	const SIZE: Option<usize> = None;
+	const ALIGN: Option<usize> = None;
 	type Meta = Slice;
 	#[inline]
 	fn meta(self: *const Self) -> Self::Meta {
 		let self_ = unsafe { &*self }; // https://github.com/rust-lang/rfcs/issues/2017
-		assert_eq!(
-			(size_of_val(self_), align_of_val(self_)),
-			(size_of::<T>() * self_.len(), align_of::<T>())
-		);
+		if cfg!(not(feature = "no-panic")) {
+			assert_eq!(
+				(size_of_val(self_), align_of_val(self_)),
+				(size_of::<T>() * self_.len(), align_of::<T>())
+			);
+		}
 		Slice { len: self_.len() }
 	}
 	#[inline]
+	unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+		Slice { len: self.len() }
+	}
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError> {
+		let self_ = unsafe { &*self }; // https://github.com/rust-lang/rfcs/issues/2017
+		if (size_of_val(self_), align_of_val(self_))
+			!= (size_of::<T>().wrapping_mul(self_.len()), align_of::<T>())
+		{
+			return Err(MetatypeError::InvalidMeta);
+		}
+		Ok(Slice { len: self_.len() })
+	}
+	#[inline]
 	fn data(self: *const Self) -> *const () {
 		self.cast()
 	}
@@ -202,18 +1277,47 @@ impl<T: Sized> Type for [T] {
 	fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self {
 		slice_from_raw_parts_mut(thin.cast(), t.len)
 	}
+	#[inline]
+	fn size_from_meta(t: &Self::Meta) -> Option<usize> {
+		size_of::<T>()
+			.checked_mul(t.len)
+			.filter(|&size| isize::try_from(size).is_ok())
+	}
+	#[inline]
+	fn align_from_meta(_t: &Self::Meta) -> Option<usize> {
+		Some(align_of::<T>())
+	}
 }
 #[doc(hidden)]
 impl Type for str {
 	const METATYPE: MetaType = MetaType::Slice;
+	const SIZE: Option<usize> = None;
+	const ALIGN: Option<usize> = None;
 	type Meta = Slice;
 	#[inline]
 	fn meta(self: *const Self) -> Self::Meta {
 		let self_ = unsafe { &*self }; // https://github.com/rust-lang/rfcs/issues/2017
-		assert_eq!((size_of_val(self_), align_of_val(self_)), (self_.len(), 1));
+		if cfg!(not(feature = "no-panic")) {
+			assert_eq!((size_of_val(self_), align_of_val(self_)), (self_.len(), 1));
+		}
 		Slice { len: self_.len() }
 	}
 	#[inline]
+	unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+		Slice {
+			len: (self as *const [u8]).len(),
+		}
+	}
+	#[cfg(feature = "no-panic")]
+	#[inline]
+	fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError> {
+		let self_ = unsafe { &*self }; // https://github.com/rust-lang/rfcs/issues/2017
+		if (size_of_val(self_), align_of_val(self_)) != (self_.len(), 1) {
+			return Err(MetatypeError::InvalidMeta);
+		}
+		Ok(Slice { len: self_.len() })
+	}
+	#[inline]
 	fn data(self: *const Self) -> *const () {
 		self.cast()
 	}
@@ -228,72 +1332,1205 @@ impl Type for str {
 	fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self {
 		<[u8]>::fatten(thin, t) as *mut Self
 	}
+	#[inline]
+	fn size_from_meta(t: &Self::Meta) -> Option<usize> {
+		<[u8]>::size_from_meta(t)
+	}
+	#[inline]
+	fn align_from_meta(_t: &Self::Meta) -> Option<usize> {
+		Some(1)
+	}
+}
+macro_rules! impl_type_slice_like {
+	($($(#[$attr:meta])* impl<$($g:ident),*> for $t:ty as $inner:ty;)*) => {$(
+		$(#[$attr])*
+		#[doc(hidden)]
+		impl<$($g),*> Type for $t {
+			const METATYPE: MetaType = MetaType::Slice;
+			const SIZE: Option<usize> = None;
+			const ALIGN: Option<usize> = None;
+			type Meta = Slice;
+			#[inline]
+			fn meta(self: *const Self) -> Self::Meta {
+				Slice {
+					len: ptr::metadata(self),
+				}
+			}
+			#[inline]
+			unsafe fn meta_unchecked(self: *const Self) -> Self::Meta {
+				self.meta()
+			}
+			#[cfg(feature = "no-panic")]
+			#[inline]
+			fn try_meta(self: *const Self) -> Result<Self::Meta, MetatypeError> {
+				Ok(self.meta())
+			}
+			#[inline]
+			fn data(self: *const Self) -> *const () {
+				self.cast()
+			}
+			#[inline]
+			fn data_mut(self: *mut Self) -> *mut () {
+				self.cast()
+			}
+			fn dangling(t: Self::Meta) -> NonNull<Self> {
+				let inner: *mut $inner = <$inner>::dangling(t).as_ptr();
+				unsafe { NonNull::new_unchecked(inner as *mut Self) }
+			}
+			fn fatten(thin: *mut (), t: Self::Meta) -> *mut Self {
+				<$inner>::fatten(thin, t) as *mut Self
+			}
+			#[inline]
+			fn size_from_meta(t: &Self::Meta) -> Option<usize> {
+				<$inner>::size_from_meta(t)
+			}
+			#[inline]
+			fn align_from_meta(t: &Self::Meta) -> Option<usize> {
+				<$inner>::align_from_meta(t)
+			}
+		}
+		$(#[$attr])*
+		impl<$($g),*> IsSlice for $t {}
+	)*};
+}
+// These have the same layout and meta data as the slice or `str` they wrap,
+// rather than being trait objects as the blanket impl would have them.
+impl_type_slice_like! {
+	impl<> for CStr as [u8];
+	#[cfg(feature = "std")]
+	impl<> for OsStr as [u8];
+	#[cfg(feature = "std")]
+	impl<> for Path as [u8];
+	impl<T> for ManuallyDrop<[T]> as [T];
+	impl<T> for UnsafeCell<[T]> as [T];
+	impl<T> for Cell<[T]> as [T];
+	impl<> for ManuallyDrop<str> as str;
+	impl<> for UnsafeCell<str> as str;
+	impl<> for Cell<str> as str;
 }
 
-unsafe fn transmute_coerce<A, B>(a: A) -> B {
-	assert_eq!(
-		(size_of::<A>(), align_of::<A>()),
-		(size_of::<B>(), align_of::<B>()),
-		"can't transmute_coerce {} to {} as sizes/alignments differ",
-		type_name::<A>(),
-		type_name::<B>()
-	);
-	let b = transmute_copy(&a);
-	forget(a);
-	b
+/// Convert a `*const Path` to the `*const OsStr` it wraps.
+#[cfg(feature = "std")]
+#[inline]
+pub fn path_as_os_str(ptr: *const Path) -> *const OsStr {
+	ptr as *const OsStr
 }
 
-/// Convert from one type parameter to another, where they are the same type.
-/// Panics with an explanatory message if the types differ.
+/// Convert a `*const OsStr` to a `*const Path` wrapping it.
 ///
-/// In almost all circumstances this isn't needed, but it can be very useful in
-/// cases like [rust-lang/rust#50318](https://github.com/rust-lang/rust/issues/50318).
-pub fn type_coerce<A, B>(a: A) -> B {
-	try_type_coerce(a)
-		.unwrap_or_else(|| panic!("can't coerce {} to {}", type_name::<A>(), type_name::<B>()))
+/// ```
+/// # use metatype::*;
+/// use std::{ffi::OsStr, path::Path};
+///
+/// let a = Path::new("a/b");
+/// assert_eq!(Type::meta(std::ptr::from_ref(a)), Slice { len: 3 });
+/// let b: *const OsStr = path_as_os_str(a);
+/// assert_eq!(unsafe { &*b }, "a/b");
+/// assert_eq!(unsafe { &*os_str_as_path(b) }, a);
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn os_str_as_path(ptr: *const OsStr) -> *const Path {
+	ptr as *const Path
 }
 
-/// Convert from one type parameter to another, where they are the same type.
+/// Create a `*const CStr` with the provided data pointer and [`Slice`] meta
+/// data, whose length includes the nul terminator, checking that the bytes
+/// are nul-terminated with no interior nul.
+///
+/// ```
+/// # use metatype::*;
+/// let a = b"abc\0";
+/// let b = unsafe { fatten_cstr_checked(a.as_ptr().cast(), Slice { len: 4 }) }.unwrap();
+/// assert_eq!(unsafe { &*b }.to_bytes(), b"abc");
+/// assert!(unsafe { fatten_cstr_checked(a.as_ptr().cast(), Slice { len: 3 }) }.is_err());
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`FromBytesWithNulError`] if the bytes aren't nul-terminated or
+/// contain an interior nul.
+///
+/// # Safety
+///
+/// `thin` must be valid for reads of `t.len` bytes.
+pub unsafe fn fatten_cstr_checked(
+	thin: *const (), t: Slice,
+) -> Result<*const CStr, FromBytesWithNulError> {
+	let bytes = &*<[u8]>::fatten_const(thin, t);
+	CStr::from_bytes_with_nul(bytes).map(ptr::from_ref)
+}
+
+/// Strip the `*const ` from the name of a pointer type, as `type_name` of the
+/// pointee itself requires it to be `MetaSized`.
+const fn pointee_name(name: &'static str) -> &'static str {
+	const PREFIX: &[u8] = b"*const ";
+	let bytes = name.as_bytes();
+	if bytes.len() < PREFIX.len() {
+		return name;
+	}
+	let (prefix, rest) = bytes.split_at(PREFIX.len());
+	let mut i = 0;
+	while i < PREFIX.len() {
+		if prefix[i] != PREFIX[i] {
+			return name;
+		}
+		i += 1;
+	}
+	unsafe { core::str::from_utf8_unchecked(rest) }
+}
+
+#[track_caller]
+unsafe fn transmute_coerce<A, B>(a: A) -> B {
+	assert!(
+		(size_of::<A>(), align_of::<A>()) == (size_of::<B>(), align_of::<B>()),
+		"can't transmute_coerce {} to {} as sizes/alignments differ",
+		Described::<A>(PhantomData),
+		Described::<B>(PhantomData)
+	);
+	let b = transmute_copy(&a);
+	forget(a);
+	b
+}
+
+/// A type's name, size, alignment and [`TypeId`], for panic messages.
+struct Described<T>(PhantomData<T>);
+impl<T> fmt::Display for Described<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} (size {}, align {}, {:?})",
+			type_name::<T>(),
+			size_of::<T>(),
+			align_of::<T>(),
+			type_id_non_static::<T>()
+		)
+	}
+}
+
+/// The [`TypeId`] of `T` with its lifetimes erased, which is all `TypeId`
+/// distinguishes anyway.
+fn type_id_non_static<T: ?Sized>() -> TypeId {
+	trait NonStaticAny {
+		fn type_id(&self) -> TypeId
+		where
+			Self: 'static;
+	}
+	impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+		fn type_id(&self) -> TypeId
+		where
+			Self: 'static,
+		{
+			TypeId::of::<T>()
+		}
+	}
+	let phantom = PhantomData::<T>;
+	let phantom: &dyn NonStaticAny = &phantom;
+	// only the lifetimes of `T` are extended, and `type_id` doesn't use them
+	let phantom: &(dyn NonStaticAny + 'static) = unsafe { core::mem::transmute(phantom) };
+	phantom.type_id()
+}
+
+/// Whether `A` and `B` are the same type. Lifetimes aren't compared, as with
+/// [`TypeId`].
+///
+/// This is usable in const contexts:
+///
+/// ```
+/// # use metatype::*;
+/// const SAME: bool = same_type::<[u8], [u8]>();
+/// assert!(SAME && !same_type::<u8, i8>());
+/// ```
+#[inline]
+#[must_use]
+pub const fn same_type<A: ?Sized, B: ?Sized>() -> bool {
+	<A as SameType<B>>::SAME
+}
+
+/// Whether `Self` and `B` are the same type, by specialization.
+trait SameType<B: ?Sized> {
+	const SAME: bool;
+}
+impl<A: ?Sized, B: ?Sized> SameType<B> for A {
+	default const SAME: bool = false;
+}
+impl<A: ?Sized> SameType<A> for A {
+	const SAME: bool = true;
+}
+
+/// Panic as `A` and `B` differ, describing them if not in a const context.
+#[cold]
+#[track_caller]
+const fn coerce_failed<A, B>() -> ! {
+	#[track_caller]
+	const fn compiletime() -> ! {
+		panic!("can't coerce between differing types")
+	}
+	#[track_caller]
+	fn runtime<A, B>() -> ! {
+		panic!(
+			"can't coerce between differing types {} and {}",
+			Described::<A>(PhantomData),
+			Described::<B>(PhantomData)
+		)
+	}
+	core::intrinsics::const_eval_select((), compiletime, runtime::<A, B>)
+}
+
+/// Convert from one type parameter to another, where they are the same type.
+/// Panics if the types differ.
+///
+/// In almost all circumstances this isn't needed, but it can be very useful in
+/// cases like [rust-lang/rust#50318](https://github.com/rust-lang/rust/issues/50318).
+///
+/// This is usable in const contexts, with the `const_trait_impl` feature:
+///
+/// ```
+/// #![feature(const_trait_impl)]
+/// # use metatype::*;
+/// const A: u8 = type_coerce::<u8, u8>(1);
+/// const B: Option<u16> = try_type_coerce::<u8, u16>(1);
+/// assert_eq!((A, B), (1, None));
+/// ```
+///
+/// The panic message includes the names, sizes, alignments and [`TypeId`]s
+/// of `A` and `B`, and its location is the caller's. With the `no-panic`
+/// feature, [`try_type_coerce_err`] returns an error instead.
+#[track_caller]
+pub const fn type_coerce<A: [const] Destruct, B>(a: A) -> B {
+	if same_type::<A, B>() {
+		let a = ManuallyDrop::new(a);
+		unsafe { transmute_copy::<ManuallyDrop<A>, B>(&a) }
+	} else {
+		drop(a);
+		coerce_failed::<A, B>()
+	}
+}
+
+/// Convert from one type parameter to another, where they are the same type.
+/// Returns [`MetatypeError::TypeMismatch`] if the types differ.
+///
+/// ```
+/// # use metatype::*;
+/// assert_eq!(try_type_coerce_err::<u8, u8>(1), Ok(1));
+/// assert_eq!(
+///     try_type_coerce_err::<u8, u16>(1),
+///     Err(MetatypeError::TypeMismatch { from: "u8", to: "u16" })
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns [`MetatypeError::TypeMismatch`] if `A` and `B` differ.
+#[cfg(feature = "no-panic")]
+pub fn try_type_coerce_err<A, B>(a: A) -> Result<B, MetatypeError> {
+	try_type_coerce(a).ok_or(MetatypeError::TypeMismatch {
+		from: type_name::<A>(),
+		to: type_name::<B>(),
+	})
+}
+
+/// Convert from one type parameter to another, where they are the same type.
 /// Returns `None` if the types differ.
 ///
 /// In almost all circumstances this isn't needed, but it can be very useful in
 /// cases like [rust-lang/rust#50318](https://github.com/rust-lang/rust/issues/50318).
-pub fn try_type_coerce<A, B>(a: A) -> Option<B> {
-	trait Eq<B> {
-		fn eq(self) -> Option<B>;
+pub const fn try_type_coerce<A: [const] Destruct, B>(a: A) -> Option<B> {
+	if same_type::<A, B>() {
+		let a = ManuallyDrop::new(a);
+		Some(unsafe { transmute_copy::<ManuallyDrop<A>, B>(&a) })
+	} else {
+		drop(a);
+		None
 	}
+}
 
-	struct Foo<A, B>(A, PhantomData<fn(B)>);
+/// Convert from one type parameter to another, where they are the same type.
+/// Returns `a` back if the types differ, rather than dropping it.
+///
+/// ```
+/// # use metatype::*;
+/// let a = String::from("abc");
+/// let a = type_coerce_or::<String, Vec<u8>>(a).unwrap_err();
+/// assert_eq!(type_coerce_or::<String, String>(a), Ok(String::from("abc")));
+/// ```
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+pub const fn type_coerce_or<A, B>(a: A) -> Result<B, A> {
+	if same_type::<A, B>() {
+		let a = ManuallyDrop::new(a);
+		Ok(unsafe { transmute_copy::<ManuallyDrop<A>, B>(&a) })
+	} else {
+		Err(a)
+	}
+}
+
+/// Convert a reference from one type parameter to another, where they are the
+/// same, possibly unsized, type. Panics if the types differ.
+///
+/// ```
+/// # use metatype::*;
+/// let mut a = [1_u8, 2, 3];
+/// let b: &[u8] = type_coerce_ref::<[u8], [u8]>(&a[..]);
+/// assert_eq!(b, [1, 2, 3]);
+/// type_coerce_mut::<[u8], [u8]>(&mut a[..])[0] = 4;
+/// assert_eq!(try_type_coerce_ref::<[u8], str>(&a[..]), None);
+/// assert_eq!(a, [4, 2, 3]);
+/// ```
+#[track_caller]
+pub const fn type_coerce_ref<A: ?Sized, B: ?Sized>(a: &A) -> &B {
+	match try_type_coerce_ref(a) {
+		Some(b) => b,
+		None => coerce_failed::<&A, &B>(),
+	}
+}
+
+/// Convert a mutable reference from one type parameter to another, where they
+/// are the same, possibly unsized, type. Panics if the types differ.
+#[track_caller]
+pub const fn type_coerce_mut<A: ?Sized, B: ?Sized>(a: &mut A) -> &mut B {
+	match try_type_coerce_mut(a) {
+		Some(b) => b,
+		None => coerce_failed::<&mut A, &mut B>(),
+	}
+}
+
+/// Convert a reference from one type parameter to another, where they are the
+/// same, possibly unsized, type. Returns `None` if the types differ.
+pub const fn try_type_coerce_ref<A: ?Sized, B: ?Sized>(a: &A) -> Option<&B> {
+	if same_type::<A, B>() {
+		Some(unsafe { transmute_copy::<&A, &B>(&a) })
+	} else {
+		None
+	}
+}
+
+/// Convert a mutable reference from one type parameter to another, where they
+/// are the same, possibly unsized, type. Returns `None` if the types differ.
+pub const fn try_type_coerce_mut<A: ?Sized, B: ?Sized>(a: &mut A) -> Option<&mut B> {
+	if same_type::<A, B>() {
+		let a = ManuallyDrop::new(a);
+		Some(unsafe { transmute_copy::<ManuallyDrop<&mut A>, &mut B>(&a) })
+	} else {
+		None
+	}
+}
+
+/// Convert a slice of one type parameter to a slice of another, where they
+/// are the same type, without touching the elements. Panics if the types
+/// differ.
+///
+/// ```
+/// # use metatype::*;
+/// fn sum<T>(a: &[T]) -> u32 {
+///     try_type_coerce_slice::<T, u32>(a).map_or(0, |a| a.iter().sum())
+/// }
+/// assert_eq!((sum(&[1_u32, 2, 3]), sum(&[1_u8])), (6, 0));
+/// ```
+#[track_caller]
+pub const fn type_coerce_slice<A, B>(a: &[A]) -> &[B] {
+	type_coerce_ref(a)
+}
+
+/// Convert a mutable slice of one type parameter to a slice of another,
+/// where they are the same type. Panics if the types differ.
+#[track_caller]
+pub const fn type_coerce_slice_mut<A, B>(a: &mut [A]) -> &mut [B] {
+	type_coerce_mut(a)
+}
+
+/// Convert a reference to a type parameter to a `&str`, where it is `str`.
+/// Panics if it's not.
+#[track_caller]
+pub const fn type_coerce_str<A: ?Sized>(a: &A) -> &str {
+	type_coerce_ref(a)
+}
+
+/// Convert a slice of one type parameter to a slice of another, where they
+/// are the same type. Returns `None` if the types differ.
+pub const fn try_type_coerce_slice<A, B>(a: &[A]) -> Option<&[B]> {
+	try_type_coerce_ref(a)
+}
+
+/// Convert a mutable slice of one type parameter to a slice of another,
+/// where they are the same type. Returns `None` if the types differ.
+pub const fn try_type_coerce_slice_mut<A, B>(a: &mut [A]) -> Option<&mut [B]> {
+	try_type_coerce_mut(a)
+}
 
-	impl<A, B> Eq<B> for Foo<A, B> {
-		default fn eq(self) -> Option<B> {
+/// Convert a reference to a type parameter to a `&str`, where it is `str`.
+/// Returns `None` if it's not.
+pub const fn try_type_coerce_str<A: ?Sized>(a: &A) -> Option<&str> {
+	try_type_coerce_ref(a)
+}
+
+/// Convert a `Vec` of one type parameter to a `Vec` of another, where they are
+/// the same type, without reallocating or moving the elements. Panics if the
+/// types differ.
+///
+/// ```
+/// # use metatype::*;
+/// let a: Vec<u8> = type_coerce_vec::<u8, u8>(vec![1, 2, 3]);
+/// let a = try_type_coerce_vec::<u8, u16>(a).unwrap_err();
+/// assert_eq!(a, [1, 2, 3]);
+/// ```
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn type_coerce_vec<A, B>(a: Vec<A>) -> Vec<B> {
+	type_coerce(a)
+}
+
+/// Convert a boxed slice of one type parameter to a boxed slice of another,
+/// where they are the same type, without reallocating or moving the elements.
+/// Panics if the types differ.
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn type_coerce_boxed_slice<A, B>(a: Box<[A]>) -> Box<[B]> {
+	type_coerce(a)
+}
+
+/// Convert a `Vec` of one type parameter to a `Vec` of another, where they are
+/// the same type. Returns `a` back if the types differ.
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+#[cfg(feature = "alloc")]
+pub fn try_type_coerce_vec<A, B>(a: Vec<A>) -> Result<Vec<B>, Vec<A>> {
+	type_coerce_or(a)
+}
+
+/// Convert a boxed slice of one type parameter to a boxed slice of another,
+/// where they are the same type. Returns `a` back if the types differ.
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+#[cfg(feature = "alloc")]
+pub fn try_type_coerce_boxed_slice<A, B>(a: Box<[A]>) -> Result<Box<[B]>, Box<[A]>> {
+	type_coerce_or(a)
+}
+
+/// Convert a `Box` of one type parameter to a `Box` of another, where they
+/// are the same, possibly unsized, type, without reallocating. Panics if the
+/// types differ.
+///
+/// ```
+/// # use metatype::*;
+/// use std::fmt::Debug;
+///
+/// let a: Box<dyn Debug> = Box::new(1_u8);
+/// let a = type_coerce_box::<dyn Debug, dyn Debug>(a);
+/// let a = try_type_coerce_box::<dyn Debug, dyn Debug + Send>(a).unwrap_err();
+/// assert_eq!(format!("{a:?}"), "1");
+/// ```
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn type_coerce_box<A: ?Sized, B: ?Sized>(a: Box<A>) -> Box<B> {
+	type_coerce(a)
+}
+
+/// Convert an `Rc` of one type parameter to an `Rc` of another, where they are
+/// the same, possibly unsized, type, keeping the same allocation. Panics if the
+/// types differ.
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn type_coerce_rc<A: ?Sized, B: ?Sized>(a: Rc<A>) -> Rc<B> {
+	type_coerce(a)
+}
+
+/// Convert an `Arc` of one type parameter to an `Arc` of another, where they
+/// are the same, possibly unsized, type, keeping the same allocation. Panics if
+/// the types differ.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+#[track_caller]
+pub fn type_coerce_arc<A: ?Sized, B: ?Sized>(a: Arc<A>) -> Arc<B> {
+	type_coerce(a)
+}
+
+/// Convert a `Box` of one type parameter to a `Box` of another, where they
+/// are the same, possibly unsized, type. Returns `a` back if the types differ.
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+#[cfg(feature = "alloc")]
+pub fn try_type_coerce_box<A: ?Sized, B: ?Sized>(a: Box<A>) -> Result<Box<B>, Box<A>> {
+	type_coerce_or(a)
+}
+
+/// Convert an `Rc` of one type parameter to an `Rc` of another, where they are
+/// the same, possibly unsized, type. Returns `a` back if the types differ.
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+#[cfg(feature = "alloc")]
+pub fn try_type_coerce_rc<A: ?Sized, B: ?Sized>(a: Rc<A>) -> Result<Rc<B>, Rc<A>> {
+	type_coerce_or(a)
+}
+
+/// Convert an `Arc` of one type parameter to an `Arc` of another, where they
+/// are the same, possibly unsized, type. Returns `a` back if the types differ.
+///
+/// # Errors
+///
+/// Returns `a` if `A` and `B` differ.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub fn try_type_coerce_arc<A: ?Sized, B: ?Sized>(a: Arc<A>) -> Result<Arc<B>, Arc<A>> {
+	type_coerce_or(a)
+}
+
+// invariant in `T`, while still `Send` and `Sync`
+type Invariant<T> = fn(T) -> T;
+
+/// Proof that `A` and `B` are the same type, for coercing between them
+/// without checking or panicking each time.
+///
+/// ```
+/// # use metatype::*;
+/// fn first_u32<T>(a: &[T]) -> Option<u32> {
+///     let eq = TypeEq::<T, u32>::try_new()?;
+///     a.first().map(|a| *eq.coerce_ref(a))
+/// }
+/// assert_eq!(first_u32(&[1_u32, 2]), Some(1));
+/// assert_eq!(first_u32(&[1_u8, 2]), None);
+/// ```
+pub struct TypeEq<A: ?Sized, B: ?Sized>(PhantomData<Invariant<(*const A, *const B)>>);
+impl<A: ?Sized, B: ?Sized> TypeEq<A, B> {
+	/// The proof that `A` and `B` are the same type, or `None` if they differ.
+	#[inline]
+	#[must_use]
+	pub const fn try_new() -> Option<Self> {
+		if same_type::<A, B>() {
+			Some(Self(PhantomData))
+		} else {
 			None
 		}
 	}
-	impl<A> Eq<A> for Foo<A, A> {
-		fn eq(self) -> Option<A> {
-			Some(self.0)
-		}
+	/// Convert a reference to `A` to a reference to `B`.
+	#[inline]
+	pub const fn coerce_ref(self, a: &A) -> &B {
+		unsafe { transmute_copy::<&A, &B>(&a) }
+	}
+	/// Convert a mutable reference to `A` to a mutable reference to `B`.
+	#[inline]
+	pub const fn coerce_mut(self, a: &mut A) -> &mut B {
+		let a = ManuallyDrop::new(a);
+		unsafe { transmute_copy::<ManuallyDrop<&mut A>, &mut B>(&a) }
+	}
+	/// Convert a `Box<A>` to a `Box<B>`, without reallocating.
+	#[cfg(feature = "alloc")]
+	#[inline]
+	pub fn coerce_box(self, a: Box<A>) -> Box<B> {
+		let a = ManuallyDrop::new(a);
+		unsafe { transmute_copy::<ManuallyDrop<Box<A>>, Box<B>>(&a) }
+	}
+	/// The proof that `B` and `A` are the same type.
+	#[inline]
+	#[must_use]
+	pub const fn inverse(self) -> TypeEq<B, A> {
+		TypeEq(PhantomData)
+	}
+}
+impl<A, B> TypeEq<A, B> {
+	/// Convert an `A` to a `B`.
+	#[inline]
+	pub const fn coerce(self, a: A) -> B {
+		let a = ManuallyDrop::new(a);
+		unsafe { transmute_copy::<ManuallyDrop<A>, B>(&a) }
+	}
+}
+impl<A: ?Sized, B: ?Sized> Clone for TypeEq<A, B> {
+	#[inline]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<A: ?Sized, B: ?Sized> Copy for TypeEq<A, B> {}
+impl<A: ?Sized, B: ?Sized> fmt::Debug for TypeEq<A, B> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "TypeEq<{}, {}>", type_name::<A>(), type_name::<B>())
+	}
+}
+
+/// Size in bytes of a value of type `T` with the provided `T::Meta`, or `None`
+/// if it would overflow `isize`, without a reference to such a value.
+///
+/// ```
+/// # use metatype::*;
+/// assert_eq!(size_of_val_meta::<[u32]>(Slice { len: 3 }), Some(12));
+/// assert_eq!(size_of_val_meta::<[u32]>(Slice { len: usize::MAX }), None);
+/// ```
+#[inline]
+pub fn size_of_val_meta<T: ?Sized + Type>(t: T::Meta) -> Option<usize> {
+	T::size_from_meta(&t)
+}
+
+/// Alignment in bytes of a value of type `T` with the provided `T::Meta`,
+/// without a reference to such a value.
+///
+/// ```
+/// # use metatype::*;
+/// let a: &dyn std::fmt::Debug = &0_u64;
+/// assert_eq!(align_of_val_meta::<dyn std::fmt::Debug>(Type::meta(a)), 8);
+/// ```
+#[inline]
+pub fn align_of_val_meta<T: ?Sized + Type>(t: T::Meta) -> usize {
+	unsafe { align_of_val_raw(T::fatten(ptr::null_mut(), t)) }
+}
+
+/// Retrieve the meta data of the value `val` refers to. See [`Type::meta`].
+///
+/// ```
+/// # use metatype::*;
+/// let a: &[u8] = &[1, 2, 3];
+/// assert_eq!(meta_of_val(a), Slice { len: 3 });
+/// assert_eq!(meta_type_of_val(a), MetaType::Slice);
+/// assert_eq!(data_of_val(a), a.as_ptr().cast());
+///
+/// let b: &dyn std::fmt::Debug = &a;
+/// let meta: TraitObject = type_coerce(meta_of_val(b));
+/// assert_eq!(meta_type_of_val(b), MetaType::TraitObject);
+/// assert_eq!(meta, type_coerce(Type::meta(b)));
+/// ```
+#[inline]
+pub fn meta_of_val<T: ?Sized + Type>(val: &T) -> T::Meta {
+	Type::meta(val)
+}
+
+/// Retrieve the [`MetaType`] of the value `val` refers to. See
+/// [`Type::meta_type`].
+#[inline]
+pub fn meta_type_of_val<T: ?Sized + Type>(val: &T) -> MetaType {
+	Type::meta_type(val)
+}
+
+/// Retrieve a pointer to the data of the value `val` refers to. See
+/// [`Type::data`].
+#[inline]
+pub fn data_of_val<T: ?Sized + Type>(val: &T) -> *const () {
+	Type::data(val)
+}
+
+/// The [`TraitObject`] meta data of a `T` coerced to the trait object type
+/// `U`, without a value of `T`.
+///
+/// ```
+/// # use metatype::*;
+/// use std::fmt::Debug;
+///
+/// let meta = vtable_of::<u16, dyn Debug>();
+/// let a = 1_u16;
+/// let a: *const dyn Debug = <dyn Debug>::fatten_const(std::ptr::from_ref(&a).cast(), type_coerce(meta));
+/// assert_eq!(format!("{:?}", unsafe { &*a }), "1");
+/// ```
+#[inline]
+pub fn vtable_of<T: Unsize<U>, U: ?Sized + IsTraitObject>() -> TraitObject {
+	U::trait_object(coerce_meta::<T, U>())
+}
+
+/// The first `n` entries of the vtable `meta`, for debugging.
+///
+/// This relies on rustc's vtable layout, which is unstable: the entries are
+/// currently drop glue, size and align followed by the methods. It shouldn't
+/// be used other than to diagnose mismatched vtables.
+///
+/// ```
+/// # use metatype::*;
+/// let meta = vtable_of::<u32, dyn std::fmt::Debug>();
+/// let slots = unsafe { vtable_slots(meta, 4) };
+/// assert_eq!((slots[1].addr(), slots[2].addr()), (4, 4));
+/// ```
+///
+/// # Safety
+///
+/// The vtable must have at least `n` entries.
+#[cfg(feature = "vtable-layout")]
+#[must_use]
+pub unsafe fn vtable_slots(meta: TraitObject, n: usize) -> &'static [*const ()] {
+	let vtable: *const () = meta.vtable;
+	core::slice::from_raw_parts(vtable.cast(), n)
+}
+
+/// Whether two trait object pointers plausibly point at values of the same
+/// concrete type.
+///
+/// Vtables are compared by address, falling back to comparing the size,
+/// alignment and drop glue they record, as the same (type, trait) pair can
+/// have duplicate vtables in different codegen units. The fallback can give
+/// false positives for distinct types that agree in all three, such as `u32`
+/// and `i32`; where that matters, register the types and compare
+/// [canonicalized](TraitObject::canonicalize) vtables instead.
+///
+/// ```
+/// # use metatype::*;
+/// use std::fmt::Debug;
+///
+/// let (a, b, c): (&dyn Debug, &dyn Debug, &dyn Debug) = (&1_u8, &2_u8, &"c");
+/// assert!(same_concrete_type::<dyn Debug>(a, b));
+/// assert!(!same_concrete_type::<dyn Debug>(a, c));
+/// ```
+#[cfg(feature = "vtable-layout")]
+pub fn same_concrete_type<T: ?Sized + IsTraitObject>(a: *const T, b: *const T) -> bool {
+	let (a, b) = (
+		T::trait_object(Type::meta(a)),
+		T::trait_object(Type::meta(b)),
+	);
+	a == b
+		|| (a.size_of(), a.align_of(), a.drop_fn().map(|f| f as usize))
+			== (b.size_of(), b.align_of(), b.drop_fn().map(|f| f as usize))
+}
+
+/// The meta data of a `T` coerced to the unsized type `U`, such as `[T; N]` to
+/// `[T]` or `T` to `dyn Trait`, without a value of `T`.
+///
+/// ```
+/// # use metatype::*;
+/// assert_eq!(coerce_meta::<[u8; 3], [u8]>(), Slice { len: 3 });
+/// let meta: TraitObject = type_coerce(coerce_meta::<u8, dyn std::fmt::Debug>());
+/// ```
+#[inline]
+pub fn coerce_meta<T: Unsize<U>, U: ?Sized + Type>() -> U::Meta {
+	let ptr: *const U = ptr::null::<T>();
+	// SAFETY: the meta data is that of an unsizing coercion, so is valid
+	unsafe { Type::meta_unchecked(ptr) }
+}
+
+/// Convert the [`TraitObject`] meta data of a `T`, such as `dyn Sub`, to that
+/// of a supertrait object `U`, such as `dyn Super`, adjusting the vtable.
+///
+/// ```
+/// # use metatype::*;
+/// use std::fmt::Debug;
+///
+/// trait Named: Debug {}
+/// impl Named for u8 {}
+///
+/// let a: &dyn Named = &1_u8;
+/// let meta = upcast_meta::<dyn Named, dyn Debug>(type_coerce(Type::meta(a)));
+/// let b = <dyn Debug>::fatten_const(Type::data(a), type_coerce(meta));
+/// assert_eq!(format!("{:?}", unsafe { &*b }), "1");
+/// ```
+#[inline]
+pub fn upcast_meta<T: ?Sized + Unsize<U> + IsTraitObject, U: ?Sized + IsTraitObject>(
+	t: TraitObject,
+) -> TraitObject {
+	let ptr = T::fatten_const(ptr::null(), type_coerce(t));
+	U::trait_object(Type::meta(upcast::<T, U>(ptr)))
+}
+
+/// Upcast a pointer to a trait object `T`, such as `dyn Sub`, to a pointer to
+/// a supertrait object `U`, such as `dyn Super`.
+///
+/// This is equally an unsizing coercion of `*const T` to `*const U`, of
+/// which upcasting is one kind.
+#[inline]
+pub fn upcast<T: ?Sized + Unsize<U>, U: ?Sized>(ptr: *const T) -> *const U {
+	ptr
+}
+
+/// Describe the type and the value `val` refers to. See [`Type::info`].
+#[inline]
+pub fn info_of_val<T: ?Sized + Type + 'static>(val: &T) -> MetaInfo {
+	Type::info(val)
+}
+
+/// Assert that pointers to `T` are thin, i.e. that its metadata is
+/// zero-sized, failing to compile when instantiated with a type for which they
+/// aren't.
+///
+/// This guards FFI code generic over foreign types, such as `extern type`s,
+/// from treating a fat pointer as a thin one and truncating its metadata.
+///
+/// ```
+/// #![feature(extern_types, ptr_metadata)]
+/// # use metatype::*;
+/// extern "C" {
+///     type Foreign;
+/// }
+/// assert_thin::<Foreign>();
+/// assert_thin::<u64>();
+/// ```
+///
+/// ```compile_fail
+/// metatype::assert_thin::<[u8]>();
+/// ```
+pub const fn assert_thin<T: PointeeSized>() {
+	const {
+		assert!(
+			size_of::<<T as Pointee>::Metadata>() == 0,
+			"pointers to this type are fat"
+		);
 	}
+}
 
-	Foo::<A, B>(a, PhantomData).eq()
+/// Convert a pointer to the thin pointer it's equivalent to, for a type whose
+/// metadata is `()`, such as a `Sized` type or an `extern type`.
+#[inline]
+pub fn as_thin<T: PointeeSized + Pointee<Metadata = ()>>(ptr: *const T) -> *const () {
+	ptr.cast()
+}
+
+/// Convert a thin pointer to a pointer to a type whose metadata is `()`, such
+/// as a `Sized` type or an `extern type`.
+#[inline]
+pub fn from_thin<T: PointeeSized + Pointee<Metadata = ()>>(thin: *const ()) -> *const T {
+	ptr::from_raw_parts(thin, ())
 }
 
 /// Gets an identifier which is globally unique to the specified type. This
 /// function will return the same value for a type regardless of whichever crate
 /// it is invoked in.
+///
+/// This is the low 64 bits of [`type_id_u128`]; see it for how it's derived.
+///
+/// This isn't a `const fn`, though [`TypeId::of`] is: the bytes of a `TypeId`
+/// carry provenance during const evaluation, so can't be hashed then.
+#[allow(clippy::cast_possible_truncation)] // the low 64 bits are wanted
 pub fn type_id<T: ?Sized + 'static>() -> u64 {
-	let type_id = TypeId::of::<T>();
-	let mut hasher = std::collections::hash_map::DefaultHasher::new();
-	type_id.hash(&mut hasher);
+	type_id_u128::<T>() as u64
+}
+
+/// Gets a 128-bit identifier which is globally unique to the specified type,
+/// for where the odds of a collision among 64-bit [`type_id`]s are too high.
+///
+/// The value is the 128-bit FNV-1a hash of the 16 bytes of the type's
+/// [`TypeId`], little-endian, so it's the same on every target for a given
+/// compiler; `TypeId` itself may differ between compiler versions.
+///
+/// ```
+/// # use metatype::*;
+/// assert_eq!(type_id_u128::<str>() as u64, type_id::<str>());
+/// assert_ne!(type_id_u128::<str>(), type_id_u128::<[u8]>());
+/// ```
+pub fn type_id_u128<T: ?Sized + 'static>() -> u128 {
+	fnv1a_128(FNV_OFFSET_128, &type_id_bits(TypeId::of::<T>()).to_le_bytes())
+}
+
+/// Gets an identifier for the trait of the specified trait object type, for
+/// keying registries by `(type_id::<T>(), trait_id::<dyn Trait>())` pairs.
+///
+/// It's hashed in a separate domain from [`type_id`], so doesn't coincide
+/// with the id of the concrete type, nor with `type_id::<dyn Trait>()`. Each
+/// combination of auto traits, such as `dyn Trait + Send`, has its own id.
+///
+/// ```
+/// # use metatype::*;
+/// use std::fmt::Debug;
+///
+/// assert_ne!(trait_id::<dyn Debug>(), type_id::<dyn Debug>());
+/// assert_ne!(trait_id::<dyn Debug>(), trait_id::<dyn Debug + Send>());
+/// ```
+///
+/// ```compile_fail
+/// metatype::trait_id::<u8>();
+/// ```
+#[allow(clippy::cast_possible_truncation)] // the low 64 bits are wanted
+pub fn trait_id<U: ?Sized + IsTraitObject + 'static>() -> u64 {
+	let hash = fnv1a_128(FNV_OFFSET_128, b"dyn ");
+	fnv1a_128(hash, &type_id_bits(TypeId::of::<U>()).to_le_bytes()) as u64
+}
+
+/// Gets an identifier for the specified type by hashing it with `H`, for
+/// when a particular hash algorithm is needed, such as to match an existing
+/// format.
+///
+/// `H` is written the same 16 bytes that [`type_id_u128`] hashes, in a single
+/// [`Hasher::write`].
+///
+/// ```
+/// # use metatype::*;
+/// use std::hash::DefaultHasher;
+///
+/// let id = type_id_with::<str, DefaultHasher>();
+/// assert_eq!(id, type_id_with::<str, DefaultHasher>());
+/// assert_ne!(id, type_id_with::<[u8], DefaultHasher>());
+/// ```
+pub fn type_id_with<T: ?Sized + 'static, H: Hasher + Default>() -> u64 {
+	let mut hasher = H::default();
+	hasher.write(&type_id_bits(TypeId::of::<T>()).to_le_bytes());
 	hasher.finish()
 }
 
+/// Gets an identifier for the specified type from its name, rather than its
+/// [`TypeId`], so that it's stable across compiler versions, for long-lived
+/// serialized data.
+///
+/// The name, as given by [`type_name`], is normalized before being hashed
+/// with 64-bit FNV-1a: spaces are removed, and `std::`, `alloc::` and `core::`
+/// are stripped from the start of paths, as items have moved between them.
+///
+/// Unlike [`type_id`], this isn't guaranteed unique: `type_name` doesn't
+/// distinguish between types with the same path in different versions of a
+/// crate, nor between lifetimes, and its format may change in ways this
+/// doesn't normalize. Types that must be told apart reliably should be given
+/// a [`TypeTag`] instead; for a type without one, this is its
+/// [`registry::tag`].
+///
+/// This is usable in const contexts:
+///
+/// ```
+/// # use metatype::*;
+/// const ID: u64 = stable_type_id::<dyn std::error::Error>();
+/// assert_eq!(ID, stable_type_id::<dyn core::error::Error>());
+/// assert_ne!(ID, stable_type_id::<dyn std::fmt::Debug>());
+/// ```
+pub const fn stable_type_id<T: ?Sized>() -> u64 {
+	const CRATES: [&[u8]; 3] = [b"std::", b"alloc::", b"core::"];
+	let name = pointee_name(type_name::<*const T>()).as_bytes();
+	let mut hash = FNV_OFFSET;
+	let mut i = 0;
+	'bytes: while i < name.len() {
+		let prev = if i == 0 { b' ' } else { name[i - 1] };
+		if !(prev.is_ascii_alphanumeric() || prev == b'_' || prev == b':') {
+			let mut j = 0;
+			while j < CRATES.len() {
+				if starts_with(name, i, CRATES[j]) {
+					i += CRATES[j].len();
+					continue 'bytes;
+				}
+				j += 1;
+			}
+		}
+		if name[i] != b' ' {
+			hash = fnv1a(hash, &[name[i]]);
+		}
+		i += 1;
+	}
+	hash
+}
+
+/// An explicit tag for a type, from which its
+/// [`registry::tag`](crate::registry::tag) is derived in place of its name, so
+/// that it's stable across renames and refactors.
+///
+/// ```
+/// # use metatype::registry::{self, TypeTag};
+/// struct Square;
+/// impl TypeTag for Square {
+///     const TAG: &'static str = "square";
+/// }
+/// assert_eq!(registry::stable_type_id::<Square>(), Ok(registry::tag::<Square>()));
+/// ```
+#[cfg(any(feature = "std", feature = "static-registry"))]
+pub trait TypeTag {
+	/// The tag, which must be unique among the types serialized together.
+	const TAG: &'static str;
+}
+#[cfg(any(feature = "std", feature = "static-registry"))]
+trait MaybeTypeTag {
+	const TAG: Option<&'static str>;
+}
+#[cfg(any(feature = "std", feature = "static-registry"))]
+impl<T: ?Sized> MaybeTypeTag for T {
+	default const TAG: Option<&'static str> = None;
+}
+#[cfg(any(feature = "std", feature = "static-registry"))]
+impl<T: ?Sized + TypeTag> MaybeTypeTag for T {
+	const TAG: Option<&'static str> = Some(T::TAG);
+}
+
+/// The tag identifying `T` in serialized data: the hash of its [`TypeTag`] if
+/// it has one, and otherwise its [`stable_type_id`].
+#[cfg(any(feature = "std", feature = "static-registry"))]
+const fn type_tag<T: ?Sized>() -> u64 {
+	match <T as MaybeTypeTag>::TAG {
+		Some(tag) => fnv1a(FNV_OFFSET, tag.as_bytes()),
+		None => stable_type_id::<T>(),
+	}
+}
+
+/// Whether `bytes[at..]` starts with `prefix`.
+const fn starts_with(bytes: &[u8], at: usize, prefix: &[u8]) -> bool {
+	if bytes.len() - at < prefix.len() {
+		return false;
+	}
+	let mut i = 0;
+	while i < prefix.len() {
+		if bytes[at + i] != prefix[i] {
+			return false;
+		}
+		i += 1;
+	}
+	true
+}
+
+/// The bits of a [`TypeId`], which rustc computes as a 128-bit hash of the
+/// type. It's made of pointers, so this strips them of provenance, as
+/// `TypeId`'s private `as_u128` does.
+fn type_id_bits(type_id: TypeId) -> u128 {
+	const CHUNKS: usize = 16 / size_of::<*const ()>();
+	const _: () = assert!(size_of::<TypeId>() == 16);
+	let chunks: [*const (); CHUNKS] = unsafe { transmute_copy(&type_id) };
+	let mut bytes = [0; 16];
+	for (bytes, chunk) in bytes.chunks_exact_mut(16 / CHUNKS).zip(chunks) {
+		bytes.copy_from_slice(&chunk.addr().to_ne_bytes());
+	}
+	u128::from_ne_bytes(bytes)
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+const FNV_OFFSET_128: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME_128: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+const fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+	let mut i = 0;
+	while i < bytes.len() {
+		hash ^= bytes[i] as u64;
+		hash = hash.wrapping_mul(FNV_PRIME);
+		i += 1;
+	}
+	hash
+}
+
+const fn fnv1a_128(mut hash: u128, bytes: &[u8]) -> u128 {
+	let mut i = 0;
+	while i < bytes.len() {
+		hash ^= bytes[i] as u128;
+		hash = hash.wrapping_mul(FNV_PRIME_128);
+		i += 1;
+	}
+	hash
+}
+
+/// Offset from `addr` to the next multiple of `align`, which must be a power
+/// of two. Unlike `pointer::align_offset`, this doesn't check it with a panic.
+#[inline]
+fn align_offset(addr: usize, align: usize) -> usize {
+	addr.wrapping_neg() & align.wrapping_sub(1)
+}
+
+/// Drop in place the value of type `T` with the provided `T::Meta` at `data`,
+/// as [`ptr::drop_in_place`] does given the fat pointer.
+///
+/// ```
+/// # use metatype::*;
+/// use std::{fmt::Debug, mem::ManuallyDrop};
+///
+/// let mut a = ManuallyDrop::new(String::from("abc"));
+/// let b: &mut dyn Debug = &mut *a;
+/// let (data, meta) = Type::split_mut(b);
+/// unsafe { drop_in_place_unsized::<dyn Debug>(data, meta) };
+/// ```
+///
+/// # Safety
+///
+/// As for [`ptr::drop_in_place`], of a pointer to a valid value of type `T`
+/// with the provided `T::Meta` at `data`.
+#[inline]
+pub unsafe fn drop_in_place_unsized<T: ?Sized + Type>(data: *mut (), t: T::Meta) {
+	ptr::drop_in_place(T::fatten(data, t));
+}
+
+/// Copy the value of type `T` with the provided `T::Meta` at `src` into
+/// `buffer`, at the first suitably aligned offset, returning a reference to it
+/// bound to the lifetime of `buffer`.
+///
+/// The value is never dropped; call [`std::ptr::drop_in_place`] on the
+/// returned reference if needed.
+///
+/// # Errors
+///
+/// Returns a [`FattenError`] if the value doesn't fit in `buffer`.
+///
+/// # Safety
+///
+/// `src` must point to a valid value of type `T` with the provided `T::Meta`,
+/// which is logically moved into `buffer`.
+pub unsafe fn emplace_raw<T: ?Sized + Type>(
+	buffer: &mut [MaybeUninit<u8>], t: T::Meta, src: *const u8,
+) -> Result<&mut T, FattenError> {
+	let size = T::size_from_meta(&t).ok_or(FattenError::SizeOverflow)?;
+	let len = buffer.len();
+	let ptr = T::fatten(buffer.as_mut_ptr().cast(), t);
+	let align = align_of_val_raw(ptr);
+	let offset = align_offset(ptr.addr(), align);
+	if offset.checked_add(size).is_none_or(|end| end > len) {
+		return Err(FattenError::OutOfBounds { offset, size, len });
+	}
+	let ptr = ptr.wrapping_byte_add(offset);
+	ptr::copy_nonoverlapping(src, ptr.cast(), size);
+	Ok(&mut *ptr)
+}
+
+/// Move `value` out of its box and into `buffer`, at the first suitably
+/// aligned offset, returning a reference to it bound to the lifetime of
+/// `buffer`.
+///
+/// The value is never dropped; call [`std::ptr::drop_in_place`] on the
+/// returned reference if needed.
+///
+/// # Errors
+///
+/// Returns `value` if it doesn't fit in `buffer`.
+#[cfg(feature = "alloc")]
+pub fn emplace<T: ?Sized + Type>(
+	buffer: &mut [MaybeUninit<u8>], value: Box<T>,
+) -> Result<&mut T, Box<T>> {
+	let ptr = Box::into_raw(value);
+	let t = Type::meta(ptr);
+	match unsafe { emplace_raw::<T>(buffer, t, ptr.cast()) } {
+		Ok(emplaced) => {
+			let layout = unsafe { Layout::for_value_raw(ptr) };
+			if layout.size() != 0 {
+				unsafe { dealloc(ptr.cast(), layout) };
+			}
+			Ok(emplaced)
+		}
+		Err(_) => Err(unsafe { Box::from_raw(ptr) }),
+	}
+}
+
+/// Allocate a `Box<T>` for a value with the provided `T::Meta`, and
+/// initialize it in place with `init`.
+///
+/// This emulates returning an unsized value, for example a `Box<[T]>` of
+/// runtime length or a `Box<dyn Trait>`, without constructing it elsewhere
+/// first.
+///
+/// # Safety
+///
+/// `init` must fully initialize the value it's passed a pointer to. If `init`
+/// panics the allocation is freed without dropping the value.
+#[cfg(feature = "alloc")]
+pub unsafe fn box_with<T: ?Sized + Type>(t: T::Meta, init: impl FnOnce(*mut T)) -> Box<T> {
+	struct Dealloc(*mut u8, Layout);
+	impl Drop for Dealloc {
+		fn drop(&mut self) {
+			unsafe { dealloc(self.0, self.1) }
+		}
+	}
+	let layout = T::layout(t);
+	let size = layout.size();
+	let dangling = T::fatten(ptr::null_mut(), t);
+	let ptr = if size == 0 {
+		dangling.wrapping_byte_add(layout.align())
+	} else {
+		let thin = alloc(layout);
+		if thin.is_null() {
+			handle_alloc_error(layout);
+		}
+		ptr::from_raw_parts_mut(thin, ptr::metadata(dangling))
+	};
+	let guard = (size != 0).then(|| Dealloc(ptr.cast(), layout));
+	init(ptr);
+	forget(guard);
+	Box::from_raw(ptr)
+}
+
 #[cfg(test)]
 mod tests {
 	#![allow(clippy::cast_ptr_alignment, clippy::shadow_unrelated)]
-	use super::{type_coerce, MetaType, Slice, TraitObject, Type};
-	use std::{any, ptr::NonNull};
+	use super::{
+		type_coerce, Concrete, FattenError, IsConcrete, IsSlice, IsTraitObject, Meta, MetaType, Slice, TraitObject, Type
+	};
+	use std::{
+		alloc::Layout, any, collections::HashSet, convert::TryFrom, ffi::CStr, fmt, ptr::{self, NonNull}
+	};
 
 	#[test]
 	fn abc() {
@@ -333,4 +2570,725 @@ mod tests {
 		let dangling = <str as Type>::dangling(Slice { len: 100 });
 		let _fat = <str as Type>::fatten(dangling.as_ptr().cast(), Slice { len: 100 });
 	}
+
+	#[test]
+	fn markers() {
+		fn concrete<T: IsConcrete>() -> MetaType {
+			T::METATYPE
+		}
+		fn slice<T: ?Sized + IsSlice>() -> MetaType {
+			T::METATYPE
+		}
+		fn trait_object<T: ?Sized + IsTraitObject>() -> MetaType {
+			T::METATYPE
+		}
+		assert_eq!(concrete::<[u8; 4]>(), MetaType::Concrete);
+		assert_eq!(slice::<[u8]>(), MetaType::Slice);
+		assert_eq!(slice::<str>(), MetaType::Slice);
+		assert_eq!(
+			trait_object::<dyn fmt::Debug + Send>(),
+			MetaType::TraitObject
+		);
+		let a: &dyn any::Any = &0_u8;
+		assert_eq!(
+			<dyn any::Any>::trait_object(Type::meta(a)),
+			type_coerce(Type::meta(a))
+		);
+		assert_eq!(<[u8]>::slice(Type::meta(&[1_u8][..])), Slice { len: 1 });
+	}
+
+	#[test]
+	fn meta_bounds() {
+		fn count<T: ?Sized + Type>(metas: &[T::Meta]) -> usize {
+			let metas = metas.to_vec();
+			std::thread::spawn(move || metas.into_iter().collect::<HashSet<_>>().len())
+				.join()
+				.unwrap()
+		}
+		let (a, b): (&dyn fmt::Debug, &dyn fmt::Debug) = (&0_u8, &0_u16);
+		assert_eq!(
+			count::<dyn fmt::Debug>(&[Type::meta(a), Type::meta(b), Type::meta(a)]),
+			2
+		);
+		assert_eq!(count::<[u8]>(&[Slice { len: 1 }, Slice { len: 1 }]), 1);
+	}
+
+	#[test]
+	fn name() {
+		assert_eq!(u8::NAME, "u8");
+		assert_eq!(<[u16]>::NAME, "[u16]");
+		let a: &dyn any::Any = &0_u8;
+		assert_eq!(Type::name(a), "dyn core::any::Any");
+		assert_eq!(<*const str>::NAME, "*const str");
+	}
+
+	#[test]
+	fn size_align() {
+		assert_eq!((u32::SIZE, u32::ALIGN), (Some(4), Some(4)));
+		assert_eq!((<[u32]>::SIZE, <dyn any::Any>::ALIGN), (None, None));
+		assert_eq!(<[u32]>::size_from_meta(&Slice { len: 2 }), Some(8));
+		assert_eq!(<[u32]>::align_from_meta(&Slice { len: 2 }), Some(4));
+		assert_eq!(str::align_from_meta(&Slice { len: 2 }), Some(1));
+		let a: &dyn any::Any = &0_u16;
+		assert_eq!(<dyn any::Any>::align_from_meta(&Type::meta(a)), Some(2));
+	}
+
+	#[test]
+	fn coerce_meta() {
+		assert_eq!(super::coerce_meta::<[u16; 0], [u16]>(), Slice { len: 0 });
+		let a: &dyn fmt::Debug = &[1_u8, 2];
+		let meta: TraitObject = type_coerce(super::coerce_meta::<[u8; 2], dyn fmt::Debug>());
+		let b = <dyn fmt::Debug>::fatten_const(Type::data(a), type_coerce(meta));
+		assert_eq!(format!("{:?}", unsafe { &*b }), "[1, 2]");
+	}
+
+	#[test]
+	fn upcast() {
+		trait Sub: fmt::Debug + any::Any {}
+		impl Sub for u16 {}
+		let a: &dyn Sub = &3_u16;
+		let meta: TraitObject = type_coerce(Type::meta(a));
+		let b: *const dyn any::Any = super::upcast(ptr::from_ref(a));
+		assert_eq!(unsafe { &*b }.downcast_ref(), Some(&3_u16));
+		let meta = super::upcast_meta::<dyn Sub, dyn fmt::Debug>(meta);
+		let c = <dyn fmt::Debug>::fatten_const(Type::data(a), type_coerce(meta));
+		assert_eq!(format!("{:?}", unsafe { &*c }), "3");
+	}
+
+	#[test]
+	fn drop_in_place_unsized() {
+		use std::{cell::Cell, mem::ManuallyDrop, rc::Rc};
+
+		struct Guard(Rc<Cell<usize>>);
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = Rc::new(Cell::new(0));
+		let mut a = ManuallyDrop::new([Guard(drops.clone()), Guard(drops.clone())]);
+		let (data, meta) = Type::split_mut(ptr::from_mut(&mut a[..]));
+		unsafe { super::drop_in_place_unsized::<[Guard]>(data, meta) };
+		assert_eq!(drops.get(), 2);
+		let mut b = ManuallyDrop::new(Guard(drops.clone()));
+		let b: *mut dyn any::Any = &mut *b;
+		let (data, meta) = Type::split_mut(b);
+		unsafe { super::drop_in_place_unsized::<dyn any::Any>(data, meta) };
+		assert_eq!(drops.get(), 3);
+	}
+
+	#[test]
+	fn cstr() {
+		let a = CStr::from_bytes_with_nul(b"abc\0").unwrap();
+		assert_eq!(CStr::METATYPE, MetaType::Slice);
+		let meta = Type::meta(ptr::from_ref(a));
+		assert_eq!(meta, Slice { len: 4 });
+		assert_eq!(CStr::size_from_meta(&meta), Some(4));
+		let b = CStr::fatten_const(Type::data(ptr::from_ref(a)), meta);
+		assert_eq!(unsafe { &*b }, a);
+		let c = b"a\0b\0";
+		assert!(
+			unsafe { super::fatten_cstr_checked(c.as_ptr().cast(), Slice { len: 4 }) }.is_err()
+		);
+		assert_eq!(
+			Type::meta(CStr::dangling(Slice { len: 2 }).as_ptr().cast_const()),
+			Slice { len: 2 }
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn os_str_path() {
+		use std::{ffi::OsStr, path::Path};
+
+		let a = OsStr::new("abc");
+		let meta = Type::meta(ptr::from_ref(a));
+		assert_eq!((OsStr::METATYPE, meta), (MetaType::Slice, Slice { len: 3 }));
+		let b = Path::fatten_const(Type::data(ptr::from_ref(a)), meta);
+		assert_eq!(unsafe { &*b }, Path::new("abc"));
+		assert_eq!(super::path_as_os_str(b), ptr::from_ref(a));
+	}
+
+	#[test]
+	fn transparent_wrappers() {
+		use std::{cell::Cell, mem::ManuallyDrop};
+
+		let a: &ManuallyDrop<[u16]> = &ManuallyDrop::new([1_u16, 2, 3]);
+		let meta = Type::meta(ptr::from_ref(a));
+		assert_eq!(<ManuallyDrop<[u16]>>::METATYPE, MetaType::Slice);
+		assert_eq!(meta, Slice { len: 3 });
+		assert_eq!(<ManuallyDrop<[u16]>>::size_from_meta(&meta), Some(6));
+		assert_eq!(<ManuallyDrop<[u16]>>::align_from_meta(&meta), Some(2));
+		let b = <[u16]>::fatten_const(Type::data(ptr::from_ref(a)), meta);
+		assert_eq!(unsafe { &*b }, [1, 2, 3]);
+		let mut c = [1_u8, 2];
+		let c: &Cell<[u8]> = Cell::from_mut(&mut c[..]);
+		assert_eq!(Type::meta(ptr::from_ref(c)), Slice { len: 2 });
+		assert_eq!(
+			<Cell<str>>::dangling(Slice { len: 1 })
+				.as_ptr()
+				.cast::<u8>(),
+			NonNull::dangling().as_ptr()
+		);
+	}
+
+	#[test]
+	fn type_coerce_or() {
+		struct Counted<'a>(&'a std::cell::Cell<usize>);
+		impl Drop for Counted<'_> {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = std::cell::Cell::new(0);
+		let a = super::type_coerce_or::<_, u8>(Counted(&drops)).err().unwrap();
+		assert_eq!(drops.get(), 0);
+		let a = super::type_coerce_or::<_, Counted>(a).ok().unwrap();
+		assert_eq!(drops.get(), 0);
+		drop(a);
+		assert_eq!(drops.get(), 1);
+		assert_eq!(const { super::type_coerce_or::<u8, u8>(1) }, Ok(1));
+	}
+
+	#[test]
+	fn type_coerce_ref() {
+		use super::{try_type_coerce_mut, try_type_coerce_ref, type_coerce_mut, type_coerce_ref};
+		const D: &str = type_coerce_ref::<str, str>("e");
+
+		let a: &dyn fmt::Debug = &1_u8;
+		let b: &dyn fmt::Debug = type_coerce_ref::<dyn fmt::Debug, _>(a);
+		assert!(ptr::eq(a, b));
+		assert!(try_type_coerce_ref::<dyn fmt::Debug, dyn any::Any>(a).is_none());
+		let mut c = String::from("abc");
+		type_coerce_mut::<String, String>(&mut c).push('d');
+		assert_eq!(c, "abcd");
+		assert_eq!(try_type_coerce_mut::<str, [u8]>(c.as_mut_str()), None);
+		assert_eq!(D, "e");
+		let err = std::panic::catch_unwind(|| type_coerce_ref::<str, [u8]>("")).unwrap_err();
+		let err = err.downcast_ref::<String>().unwrap();
+		assert!(err.starts_with("can't coerce between differing types &str (size "));
+	}
+
+	#[test]
+	fn type_coerce_slice() {
+		use super::{try_type_coerce_slice_mut, try_type_coerce_str, type_coerce_slice, type_coerce_str};
+
+		let a = [1_u16, 2];
+		assert!(ptr::eq(type_coerce_slice::<u16, u16>(&a), ptr::from_ref(&a[..])));
+		let mut b = *b"ab";
+		assert_eq!(try_type_coerce_slice_mut::<u8, i8>(&mut b), None);
+		try_type_coerce_slice_mut::<u8, u8>(&mut b).unwrap()[0] = b'c';
+		assert_eq!(&b, b"cb");
+		assert_eq!(type_coerce_str::<str>("a"), "a");
+		assert_eq!(try_type_coerce_str::<[u8]>(b"a"), None);
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn type_coerce_vec() {
+		use super::{try_type_coerce_boxed_slice, type_coerce_boxed_slice, type_coerce_vec};
+
+		let a = vec![String::from("a"); 3];
+		let ptr = a.as_ptr();
+		let b = type_coerce_vec::<String, String>(a);
+		assert_eq!((b.as_ptr(), b.len()), (ptr, 3));
+		let b = b.into_boxed_slice();
+		let ptr = b.as_ptr();
+		let b = try_type_coerce_boxed_slice::<String, Box<str>>(b).unwrap_err();
+		let b = type_coerce_boxed_slice::<String, String>(b);
+		assert_eq!(b.as_ptr(), ptr);
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn type_coerce_box() {
+		use super::{try_type_coerce_arc, type_coerce_arc, type_coerce_box, type_coerce_rc};
+		use std::{rc::Rc, sync::Arc};
+
+		let boxed: Box<[u8]> = Box::new([1, 2]);
+		let ptr = ptr::from_ref(&*boxed);
+		let boxed = type_coerce_box::<[u8], [u8]>(boxed);
+		assert!(ptr::eq(ptr::from_ref(&*boxed), ptr));
+		let rc: Rc<str> = Rc::from("ab");
+		let rc2 = type_coerce_rc::<str, str>(Rc::clone(&rc));
+		assert!(Rc::ptr_eq(&rc, &rc2));
+		let arc: Arc<dyn fmt::Debug + Send + Sync> = Arc::new(1_u8);
+		let arc = try_type_coerce_arc::<_, dyn fmt::Debug>(arc).unwrap_err();
+		let arc2 = type_coerce_arc::<dyn fmt::Debug + Send + Sync, dyn fmt::Debug + Send + Sync>(
+			Arc::clone(&arc),
+		);
+		assert_eq!(Arc::strong_count(&arc2), 2);
+	}
+
+	#[test]
+	fn type_eq() {
+		use super::TypeEq;
+
+		assert!(TypeEq::<u8, u16>::try_new().is_none());
+		let eq = const { TypeEq::<String, String>::try_new().unwrap() };
+		let mut a = eq.coerce(String::from("a"));
+		eq.coerce_mut(&mut a).push('b');
+		assert_eq!(eq.inverse().coerce_ref(&a), "ab");
+		let eq = TypeEq::<dyn fmt::Debug, dyn fmt::Debug>::try_new().unwrap();
+		#[cfg(feature = "alloc")]
+		{
+			let b: Box<dyn fmt::Debug> = Box::new(1_u8);
+			assert_eq!(format!("{:?}", eq.coerce_box(b)), "1");
+		}
+		assert_eq!(format!("{eq:?}"), "TypeEq<dyn core::fmt::Debug, dyn core::fmt::Debug>");
+	}
+
+	#[test]
+	fn coerce_panic() {
+		let err = std::panic::catch_unwind(|| type_coerce::<u8, u16>(1)).unwrap_err();
+		let err = err.downcast_ref::<String>().unwrap();
+		assert!(err.starts_with("can't coerce between differing types u8 (size 1, align 1, TypeId("));
+		assert!(err.contains(") and u16 (size 2, align 2, TypeId("));
+		let err = std::panic::catch_unwind(|| unsafe { super::transmute_coerce::<u8, u16>(1) })
+			.unwrap_err();
+		let err = err.downcast_ref::<String>().unwrap();
+		assert!(err.starts_with("can't transmute_coerce u8 (size 1, align 1, TypeId("));
+	}
+
+	#[test]
+	fn type_id() {
+		// a hasher that records what it's written
+		#[derive(Default)]
+		struct Bytes(Vec<u8>);
+		impl std::hash::Hasher for Bytes {
+			#[allow(clippy::cast_possible_truncation)]
+			fn finish(&self) -> u64 {
+				super::fnv1a_128(super::FNV_OFFSET_128, &self.0) as u64
+			}
+			fn write(&mut self, bytes: &[u8]) {
+				self.0.extend_from_slice(bytes);
+			}
+		}
+
+		assert_eq!(super::fnv1a(super::FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+		assert_eq!(
+			super::fnv1a_128(super::FNV_OFFSET_128, b"a"),
+			0xd228_cb69_6f1a_8caf_7891_2b70_4e4a_8964
+		);
+		// `TypeId`'s `Debug` impl prints its bits
+		let type_id = any::TypeId::of::<str>();
+		assert_eq!(
+			format!("TypeId({:#034x})", super::type_id_bits(type_id)),
+			format!("{type_id:?}")
+		);
+		assert_eq!(super::type_id_with::<str, Bytes>(), super::type_id::<str>());
+		assert_eq!(super::type_id::<str>(), super::type_id::<str>());
+		assert_eq!(super::trait_id::<dyn any::Any>(), super::trait_id::<dyn any::Any>());
+		assert_ne!(super::trait_id::<dyn any::Any>(), super::type_id::<dyn any::Any>());
+		assert_eq!(
+			super::stable_type_id::<(Vec<String>, dyn fmt::Debug + Send)>(),
+			super::fnv1a(super::FNV_OFFSET, b"(vec::Vec<string::String>,dynfmt::Debug+marker::Send)")
+		);
+		assert_ne!(super::type_id::<str>(), super::type_id::<[u8]>());
+	}
+
+	#[test]
+	fn info() {
+		let a: &dyn fmt::Debug = &(0_u8, 0_u32);
+		let info = super::info_of_val(a);
+		assert_eq!(info.name(), "dyn core::fmt::Debug");
+		assert_eq!(info.type_id(), Some(any::TypeId::of::<dyn fmt::Debug>()));
+		assert_eq!(info.meta_type(), MetaType::TraitObject);
+		assert_eq!((info.size(), info.align()), (Some(8), 4));
+		let b = 0_u16;
+		let c: &&u16 = &&b;
+		let info = Type::info_non_static(c);
+		assert_eq!(
+			(info.type_id(), info.size()),
+			(None, Some(size_of::<usize>()))
+		);
+	}
+
+	#[test]
+	fn meta_erased() {
+		let a: &dyn fmt::Debug = &0_u8;
+		let metas = [
+			Type::meta_erased(a),
+			Type::meta_erased(ptr::from_ref(&0_u8)),
+			Type::meta_erased(ptr::from_ref("abc")),
+		];
+		assert_eq!(metas[0], Meta::TraitObject(type_coerce(Type::meta(a))));
+		assert_eq!(
+			metas.map(Meta::meta_type),
+			[MetaType::TraitObject, MetaType::Concrete, MetaType::Slice]
+		);
+		assert_eq!(Concrete::try_from(metas[1]), Ok(Concrete));
+		assert_eq!(Slice::try_from(metas[2]), Ok(Slice { len: 3 }));
+		assert_eq!(TraitObject::try_from(metas[2]), Err(metas[2]));
+		assert_eq!(Meta::from(Slice { len: 3 }), metas[2]);
+	}
+
+	#[test]
+	fn wire() {
+		use super::{Composite, WireError};
+
+		let composite = Meta::Composite(Composite {
+			tail: Slice { len: 5 },
+		});
+		assert_eq!(composite.to_wire(), Ok(5));
+		assert_eq!(Meta::from_wire(MetaType::Composite, 5), Ok(composite));
+		assert_eq!(Meta::Concrete(Concrete).to_wire(), Ok(0));
+		assert_eq!(
+			Meta::from_wire(MetaType::Concrete, 1),
+			Err(WireError::Overflow { wire: 1 })
+		);
+		let a: &dyn fmt::Debug = &0_u8;
+		assert_eq!(Type::meta_erased(a).to_wire(), Err(WireError::TraitObject));
+		assert_eq!(
+			Meta::from_wire(MetaType::TraitObject, 0),
+			Err(WireError::TraitObject)
+		);
+		let overflow = Slice::from_wire(u64::MAX);
+		if cfg!(target_pointer_width = "64") {
+			assert_eq!(overflow.map(Slice::to_wire), Ok(u64::MAX));
+		} else {
+			assert_eq!(overflow, Err(WireError::Overflow { wire: u64::MAX }));
+		}
+	}
+
+	#[test]
+	fn fat_pointer_layout() {
+		#[repr(C)]
+		struct Fat<M> {
+			data: *const (),
+			meta: M,
+		}
+		let a: &[u16] = &[1, 2, 3];
+		let fat: Fat<Slice> = unsafe { std::mem::transmute(ptr::from_ref(a)) };
+		assert_eq!((fat.data, fat.meta), (Type::data(a), Slice { len: 3 }));
+		let b: &dyn fmt::Debug = &0_u8;
+		let fat: Fat<TraitObject> = unsafe { std::mem::transmute(ptr::from_ref(b)) };
+		assert_eq!((fat.data, fat.meta), (Type::data(b), type_coerce(Type::meta(b))));
+		assert_eq!(
+			<dyn fmt::Debug>::fatten_const(fat.data, type_coerce(fat.meta)),
+			ptr::from_ref(b)
+		);
+	}
+
+	#[test]
+	fn layout() {
+		assert_eq!(
+			<[u16] as Type>::layout(Slice { len: 3 }),
+			Layout::new::<[u16; 3]>()
+		);
+		let a: &dyn fmt::Debug = &(0_u8, 0_u32);
+		assert_eq!(
+			<dyn fmt::Debug as Type>::layout(Type::meta(a)),
+			Layout::for_value(a)
+		);
+		assert_eq!(<str as Type>::layout(Slice { len: 0 }), Layout::new::<()>());
+	}
+
+	#[test]
+	fn repr_c() {
+		extern "C" fn trait_object(a: TraitObject) -> TraitObject {
+			a
+		}
+		extern "C" fn slice(a: Slice) -> Slice {
+			a
+		}
+		assert_eq!(Layout::new::<TraitObject>(), Layout::new::<*const ()>());
+		assert_eq!(Layout::new::<Slice>(), Layout::new::<usize>());
+		assert_eq!(Layout::new::<Concrete>(), Layout::new::<()>());
+		let a: &dyn fmt::Debug = &0_u8;
+		let a: TraitObject = type_coerce(Type::meta(a));
+		assert_eq!(trait_object(a), a);
+		assert_eq!(slice(Slice { len: 3 }), Slice { len: 3 });
+	}
+
+	#[test]
+	fn dangling() {
+		#[repr(align(4096))]
+		#[derive(Debug)]
+		struct Page(#[allow(dead_code)] u8);
+		let a: &dyn fmt::Debug = &Page(0);
+		let a = <dyn fmt::Debug as Type>::dangling(Type::meta(a));
+		assert_eq!(a.as_ptr().cast::<u8>().addr(), 4096);
+		let b: &dyn fmt::Debug = &0_u16;
+		let b = <dyn fmt::Debug as Type>::dangling(Type::meta(b));
+		assert_eq!(b.as_ptr().cast::<u8>().addr(), 2);
+	}
+
+	#[test]
+	fn dangling_with_align() {
+		let a = <[u16] as Type>::dangling_with_align(Slice { len: 3 }, 64);
+		assert_eq!((a.as_ptr().cast::<u8>().addr(), a.len()), (64, 3));
+		let b: &dyn fmt::Debug = &0_u8;
+		let b = <dyn fmt::Debug as Type>::dangling_with_align(Type::meta(b), 4096);
+		assert_eq!(b.as_ptr().cast::<u8>().addr(), 4096);
+	}
+
+	#[test]
+	fn thin() {
+		super::assert_thin::<u8>();
+		let a = 1_u16;
+		let thin = super::as_thin(ptr::from_ref(&a));
+		assert_eq!(unsafe { *super::from_thin::<u16>(thin) }, 1);
+	}
+
+	#[test]
+	fn split() {
+		let mut a = 123_u32;
+		let b: *mut dyn any::Any = &mut a;
+		let (thin, meta) = Type::split_mut(b);
+		assert_eq!(thin, b.cast::<()>());
+		assert_eq!(meta, Type::meta(b));
+		let c = <dyn any::Any>::fatten(thin, meta);
+		assert_eq!(unsafe { (*c).downcast_ref() }, Some(&123_u32));
+		assert_eq!(Type::split(c.cast_const()), (thin.cast_const(), meta));
+	}
+
+	#[test]
+	fn with_meta() {
+		let a: &dyn fmt::Debug = &1_u8;
+		let b: &dyn fmt::Debug = &"b";
+		let c = Type::with_meta_const(ptr::from_ref(a), Type::meta(b));
+		assert_eq!(c.cast::<()>(), ptr::from_ref(a).cast());
+		assert_eq!(Type::meta(c), Type::meta(b));
+		let mut buf = [1_u16, 2, 3];
+		let first = Type::with_meta(ptr::from_mut(&mut buf[..]), Slice { len: 1 });
+		unsafe { (*first)[0] = 4 };
+		assert_eq!(buf, [4, 2, 3]);
+	}
+
+	#[test]
+	fn fatten_within() {
+		let mut buf = [0_u8; 16];
+		let buf: *mut [u8] = &mut buf;
+		let x = <[u16] as Type>::fatten_within(buf, 4, Slice { len: 6 }).unwrap();
+		assert_eq!(x.len(), 6);
+		assert_eq!(
+			<[u16] as Type>::fatten_within(buf, 6, Slice { len: 6 }),
+			Err(FattenError::OutOfBounds {
+				offset: 6,
+				size: 12,
+				len: 16
+			})
+		);
+		assert_eq!(
+			<[u16] as Type>::fatten_within(buf, 0, Slice { len: usize::MAX }),
+			Err(FattenError::SizeOverflow)
+		);
+		assert!(<u64 as Type>::fatten_within(buf, 8, Concrete).is_ok());
+		assert!(<u64 as Type>::fatten_within(buf, usize::MAX, Concrete).is_err());
+		let a: &dyn any::Any = &0_u32;
+		let meta: TraitObject = type_coerce(Type::meta(a));
+		assert!(<dyn any::Any as Type>::fatten_within(buf, 12, type_coerce(meta)).is_ok());
+		assert!(<dyn any::Any as Type>::fatten_within(buf, 13, type_coerce(meta)).is_err());
+	}
+
+	#[test]
+	fn meta_unchecked() {
+		let a: &[u16] = &[1, 2];
+		assert_eq!(unsafe { Type::meta_unchecked(a) }, Slice { len: 2 });
+		assert_eq!(unsafe { Type::meta_unchecked("abc") }, Slice { len: 3 });
+		let b: &dyn fmt::Debug = &a;
+		assert_eq!(unsafe { Type::meta_unchecked(b) }, Type::meta(b));
+		assert_eq!(unsafe { Type::meta_unchecked(&1_u8) }, Concrete);
+	}
+
+	#[cfg(feature = "no-panic")]
+	#[test]
+	fn try_variants() {
+		use super::{try_type_coerce_err, MetatypeError};
+
+		let a: &[u16] = &[1, 2];
+		assert_eq!(Type::try_meta(a), Ok(Slice { len: 2 }));
+		assert_eq!(Type::try_meta("abc"), Ok(Slice { len: 3 }));
+		assert_eq!(
+			try_type_coerce_err::<_, u16>(0_u8),
+			Err(MetatypeError::TypeMismatch {
+				from: "u8",
+				to: "u16"
+			})
+		);
+		assert_eq!(
+			<[u16]>::try_layout(Slice { len: 2 }),
+			Ok(Layout::new::<[u16; 2]>())
+		);
+		assert_eq!(
+			<[u16]>::try_layout(Slice { len: usize::MAX }),
+			Err(MetatypeError::Fatten(FattenError::SizeOverflow))
+		);
+		assert_eq!(
+			<[u16]>::try_dangling_with_align(Slice { len: 2 }, 0),
+			Err(MetatypeError::ZeroAlign)
+		);
+		assert!(<[u16]>::try_dangling_with_align(Slice { len: 2 }, 8).is_ok());
+	}
+
+	#[test]
+	fn try_fatten() {
+		let a: &dyn any::Any = &0_u32;
+		let (thin, meta) = Type::split(ptr::from_ref(a));
+		let b = <dyn any::Any>::try_fatten(thin.cast_mut(), meta).unwrap();
+		assert_eq!(unsafe { (*b).downcast_ref() }, Some(&0_u32));
+		assert_eq!(
+			<dyn any::Any>::try_fatten(ptr::null_mut(), meta),
+			Err(FattenError::Null)
+		);
+		assert_eq!(
+			u32::try_fatten(ptr::without_provenance_mut(6), Concrete),
+			Err(FattenError::Misaligned { addr: 6, align: 4 })
+		);
+		if cfg!(debug_assertions) {
+			let bogus = TraitObject {
+				vtable: unsafe { &*ptr::without_provenance(8) },
+			};
+			assert_eq!(
+				<dyn any::Any>::try_fatten(thin.cast_mut(), type_coerce(bogus)),
+				Err(FattenError::InvalidVtable { vtable: 8 })
+			);
+		}
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn emplace() {
+		use std::mem::{align_of, MaybeUninit};
+
+		let mut buf = [MaybeUninit::<u8>::uninit(); 32];
+		let a: Box<dyn fmt::Debug> = Box::new(String::from("abc"));
+		let a = super::emplace(&mut buf, a).unwrap();
+		assert_eq!(format!("{a:?}"), "\"abc\"");
+		unsafe { ptr::drop_in_place(a) };
+		let a: Box<[u64]> = vec![1, 2, 3].into_boxed_slice();
+		let a = super::emplace(&mut buf, a).unwrap();
+		assert_eq!(a, &[1, 2, 3]);
+		assert_eq!(a.as_ptr().addr() % align_of::<u64>(), 0);
+		let a: Box<[u64]> = vec![0; 5].into_boxed_slice();
+		assert_eq!(*super::emplace(&mut buf, a).unwrap_err(), [0; 5]);
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn box_with() {
+		let a: Box<[String]> = unsafe {
+			super::box_with(Slice { len: 3 }, |a: *mut [String]| {
+				for i in 0..a.len() {
+					a.cast::<String>().add(i).write(i.to_string());
+				}
+			})
+		};
+		assert_eq!(&*a, ["0", "1", "2"]);
+		let a: Box<[()]> = unsafe { super::box_with(Slice { len: 3 }, |_| ()) };
+		assert_eq!(a.len(), 3);
+		let b: &dyn fmt::Debug = &0_u16;
+		let a: Box<dyn fmt::Debug> = unsafe {
+			super::box_with(Type::meta(b), |a: *mut dyn fmt::Debug| {
+				a.cast::<u16>().write(123);
+			})
+		};
+		assert_eq!(format!("{a:?}"), "123");
+	}
+
+	/// Functions that fail to link if they contain a reachable panic, in the
+	/// manner of the `no-panic` crate. This relies on optimizations to prune
+	/// unreachable panics, so is only run in release builds.
+	#[cfg(all(feature = "no-panic", not(debug_assertions)))]
+	mod no_panic {
+		use super::super::{
+			emplace_raw, try_type_coerce_err, Concrete, FattenError, MetatypeError, Slice, TraitObject, Type
+		};
+		use std::{alloc::Layout, any::Any, mem::MaybeUninit, ptr::NonNull};
+
+		type Meta = <dyn Any as Type>::Meta;
+
+		struct Guard;
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				extern "C" {
+					#[link_name = "\n\nerror: a function that must not panic can panic\n\n"]
+					fn panicked() -> !;
+				}
+				unsafe { panicked() }
+			}
+		}
+		macro_rules! no_panic {
+			($(fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty $body:block)*) => {$(
+				#[inline(never)]
+				fn $name($($arg: $ty),*) -> $ret {
+					$(let $arg = std::hint::black_box($arg);)*
+					let guard = Guard;
+					let ret = $body;
+					std::mem::forget(guard);
+					ret
+				}
+			)*};
+		}
+		no_panic! {
+			fn slice_meta(a: *const [u32]) -> Slice { Type::meta(a) }
+			fn str_meta(a: *const str) -> Slice { Type::meta(a) }
+			fn trait_object_meta(a: *const dyn Any) -> Meta { Type::meta(a) }
+			fn slice_dangling(t: Slice) -> NonNull<[u32]> { <[u32]>::dangling(t) }
+			fn str_dangling(t: Slice) -> NonNull<str> { <str>::dangling(t) }
+			fn trait_object_dangling(t: Meta) -> NonNull<dyn Any> { <dyn Any>::dangling(t) }
+			fn slice_fatten(buffer: *mut [u8], t: Slice) -> Result<*mut [u32], FattenError> {
+				<[u32]>::fatten_within(buffer, 4, t)
+			}
+			fn trait_object_fatten(buffer: *mut [u8], t: Meta) -> Result<*mut dyn Any, FattenError> {
+				<dyn Any>::fatten_within(buffer, 0, t)
+			}
+			fn slice_emplace(buffer: &mut [MaybeUninit<u8>], t: Slice, src: *const u8) -> bool {
+				unsafe { emplace_raw::<[u32]>(buffer, t, src) }.is_ok()
+			}
+			fn trait_object_emplace(buffer: &mut [MaybeUninit<u8>], t: Meta, src: *const u8) -> bool {
+				unsafe { emplace_raw::<dyn Any>(buffer, t, src) }.is_ok()
+			}
+			fn concrete_fatten(buffer: *mut [u8], t: Concrete) -> Result<*mut u64, FattenError> {
+				<u64>::fatten_within(buffer, 0, t)
+			}
+			fn slice_try_meta(a: *const [u32]) -> Result<Slice, MetatypeError> { Type::try_meta(a) }
+			fn coerce(a: Meta) -> Result<TraitObject, MetatypeError> { try_type_coerce_err(a) }
+			fn slice_try_layout(t: Slice) -> Result<Layout, MetatypeError> { <[u32]>::try_layout(t) }
+			fn trait_object_try_layout(t: Meta) -> Result<Layout, MetatypeError> { <dyn Any>::try_layout(t) }
+			fn slice_try_dangling(t: Slice, align: usize) -> Result<NonNull<[u32]>, MetatypeError> {
+				<[u32]>::try_dangling_with_align(t, align)
+			}
+			fn slice_try_fatten(thin: *mut (), t: Slice) -> Result<*mut [u32], FattenError> {
+				<[u32]>::try_fatten(thin, t)
+			}
+			fn trait_object_try_fatten(thin: *mut (), t: Meta) -> Result<*mut dyn Any, FattenError> {
+				<dyn Any>::try_fatten(thin, t)
+			}
+		}
+
+		#[test]
+		fn no_panic() {
+			let a: &[u32] = &[1, 2, 3];
+			let b: &dyn Any = &0_u32;
+			let mut buffer = [0_u8; 16];
+			let buffer: *mut [u8] = &mut buffer;
+			assert_eq!(slice_meta(a), Slice { len: 3 });
+			assert_eq!(str_meta("ab"), Slice { len: 2 });
+			assert!(slice_fatten(buffer, slice_meta(a)).is_ok());
+			assert!(trait_object_fatten(buffer, trait_object_meta(b)).is_ok());
+			assert!(concrete_fatten(buffer, Concrete).is_ok());
+			assert_eq!(slice_try_meta(a), Ok(Slice { len: 3 }));
+			assert!(coerce(trait_object_meta(b)).is_ok());
+			assert!(slice_try_layout(Slice { len: usize::MAX }).is_err());
+			assert!(trait_object_try_layout(trait_object_meta(b)).is_ok());
+			assert!(slice_try_dangling(Slice { len: 3 }, 0).is_err());
+			assert!(slice_try_fatten(a.as_ptr().cast_mut().cast(), slice_meta(a)).is_ok());
+			assert!(trait_object_try_fatten(buffer.cast(), trait_object_meta(b)).is_ok());
+			let _ = (
+				slice_dangling(Slice { len: 3 }),
+				str_dangling(Slice { len: 3 }),
+			);
+			let _ = trait_object_dangling(trait_object_meta(b));
+			let mut buffer = [MaybeUninit::uninit(); 32];
+			assert!(slice_emplace(&mut buffer, slice_meta(a), a.as_ptr().cast()));
+			let src: *const u32 = &0;
+			assert!(trait_object_emplace(
+				&mut buffer,
+				trait_object_meta(b),
+				src.cast()
+			));
+		}
+	}
 }
@@ -0,0 +1,234 @@
+//! Free functions over raw pointers.
+//!
+//! These mirror the methods of [`Type`], for callers that would rather not
+//! use its `self: *const Self` receivers, which need the
+//! `arbitrary_self_types_pointers` feature to be called with method syntax.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::{raw, Slice};
+//! let a: *const [u16] = &[1, 2, 3][..];
+//! let meta = raw::meta(a);
+//! assert_eq!(meta, Slice { len: 3 });
+//! assert_eq!(raw::fatten_const::<[u16]>(raw::data(a), meta), a);
+//! ```
+//!
+//! [`PtrExt`] provides the same as methods on `*const T` and `*mut T`, and
+//! [`NonNullExt`] on `NonNull<T>`.
+
+use super::{MetaType, Type};
+use core::{marker::PointeeSized, ptr::NonNull};
+
+/// Retrieve the [`MetaType`] of `ptr`'s pointee. See [`Type::meta_type`].
+///
+/// Unlike [`Type::meta_type`], this is usable in const contexts.
+#[inline]
+pub const fn meta_type<T: PointeeSized + Type>(_ptr: *const T) -> MetaType {
+	T::METATYPE
+}
+/// Retrieve the meta data of `ptr`. See [`Type::meta`].
+#[inline]
+pub fn meta<T: PointeeSized + Type>(ptr: *const T) -> T::Meta {
+	Type::meta(ptr)
+}
+/// Retrieve pointer to the data. See [`Type::data`].
+#[inline]
+pub fn data<T: PointeeSized + Type>(ptr: *const T) -> *const () {
+	Type::data(ptr)
+}
+/// Retrieve mut pointer to the data. See [`Type::data_mut`].
+#[inline]
+pub fn data_mut<T: PointeeSized + Type>(ptr: *mut T) -> *mut () {
+	Type::data_mut(ptr)
+}
+/// Create a `*mut T` with the provided data pointer and meta data. See
+/// [`Type::fatten`].
+#[inline]
+pub fn fatten<T: PointeeSized + Type>(thin: *mut (), t: T::Meta) -> *mut T {
+	T::fatten(thin, t)
+}
+/// Create a `*const T` with the provided data pointer and meta data. See
+/// [`Type::fatten_const`].
+#[inline]
+pub fn fatten_const<T: PointeeSized + Type>(thin: *const (), t: T::Meta) -> *const T {
+	T::fatten_const(thin, t)
+}
+/// Create a dangling non-null `*const T` with the provided meta data. See
+/// [`Type::dangling`].
+#[inline]
+pub fn dangling<T: PointeeSized + Type>(t: T::Meta) -> NonNull<T> {
+	T::dangling(t)
+}
+
+/// Extension methods for raw pointers, for working with their data pointer
+/// and meta data.
+///
+/// ```
+/// # use metatype::{raw::PtrExt, Slice};
+/// let a: *const [u16] = &[1, 2, 3][..];
+/// assert_eq!(a.meta(), Slice { len: 3 });
+/// assert_eq!(unsafe { &*a.with_meta(Slice { len: 2 }) }, [1, 2]);
+/// ```
+pub trait PtrExt: Copy {
+	/// The type pointed to
+	type Pointee: PointeeSized + Type;
+	/// The thin pointer type, `*const ()` or `*mut ()`
+	type Thin;
+
+	/// Retrieve the meta data of the pointee. See [`Type::meta`].
+	fn meta(self) -> <Self::Pointee as Type>::Meta;
+	/// Retrieve the [`MetaType`] of the pointee. See [`Type::meta_type`].
+	fn meta_type(self) -> MetaType;
+	/// Retrieve the pointer to the data. See [`Type::data`].
+	fn thin(self) -> Self::Thin;
+	/// Replace the meta data, keeping the data pointer.
+	#[must_use]
+	fn with_meta(self, t: <Self::Pointee as Type>::Meta) -> Self;
+}
+impl<T: PointeeSized + Type> PtrExt for *const T {
+	type Pointee = T;
+	type Thin = *const ();
+
+	#[inline]
+	fn meta(self) -> T::Meta {
+		Type::meta(self)
+	}
+	#[inline]
+	fn meta_type(self) -> MetaType {
+		Type::meta_type(self)
+	}
+	#[inline]
+	fn thin(self) -> *const () {
+		Type::data(self)
+	}
+	#[inline]
+	fn with_meta(self, t: T::Meta) -> Self {
+		Type::with_meta_const(self, t)
+	}
+}
+impl<T: PointeeSized + Type> PtrExt for *mut T {
+	type Pointee = T;
+	type Thin = *mut ();
+
+	#[inline]
+	fn meta(self) -> T::Meta {
+		Type::meta(self)
+	}
+	#[inline]
+	fn meta_type(self) -> MetaType {
+		Type::meta_type(self)
+	}
+	#[inline]
+	fn thin(self) -> *mut () {
+		Type::data_mut(self)
+	}
+	#[inline]
+	fn with_meta(self, t: T::Meta) -> Self {
+		Type::with_meta(self, t)
+	}
+}
+
+/// Extension methods for [`NonNull`], mirroring [`PtrExt`].
+///
+/// ```
+/// # use metatype::{raw::NonNullExt, Slice};
+/// use std::ptr::NonNull;
+///
+/// let mut a = [1_u16, 2, 3];
+/// let b = NonNull::from(&mut a[..]);
+/// let c = NonNull::<[u16]>::fatten_nonnull(b.thin(), Slice { len: 2 });
+/// assert_eq!(c.meta(), Slice { len: 2 });
+/// assert_eq!(unsafe { c.as_ref() }, [1, 2]);
+/// ```
+pub trait NonNullExt: Copy {
+	/// The type pointed to
+	type Pointee: PointeeSized + Type;
+
+	/// Create a `NonNull<T>` with the provided data pointer and meta data.
+	/// See [`Type::fatten`].
+	fn fatten_nonnull(thin: NonNull<()>, t: <Self::Pointee as Type>::Meta) -> Self;
+	/// Retrieve the meta data of the pointee. See [`Type::meta`].
+	fn meta(self) -> <Self::Pointee as Type>::Meta;
+	/// Retrieve the [`MetaType`] of the pointee. See [`Type::meta_type`].
+	fn meta_type(self) -> MetaType;
+	/// Retrieve the pointer to the data. See [`Type::data`].
+	fn thin(self) -> NonNull<()>;
+	/// Replace the meta data, keeping the data pointer.
+	#[must_use]
+	fn with_meta(self, t: <Self::Pointee as Type>::Meta) -> Self;
+}
+impl<T: PointeeSized + Type> NonNullExt for NonNull<T> {
+	type Pointee = T;
+
+	#[inline]
+	fn fatten_nonnull(thin: NonNull<()>, t: T::Meta) -> Self {
+		unsafe { Self::new_unchecked(T::fatten(thin.as_ptr(), t)) }
+	}
+	#[inline]
+	fn meta(self) -> T::Meta {
+		Type::meta(self.as_ptr())
+	}
+	#[inline]
+	fn meta_type(self) -> MetaType {
+		T::METATYPE
+	}
+	#[inline]
+	fn thin(self) -> NonNull<()> {
+		unsafe { NonNull::new_unchecked(Type::data_mut(self.as_ptr())) }
+	}
+	#[inline]
+	fn with_meta(self, t: T::Meta) -> Self {
+		Self::fatten_nonnull(self.thin(), t)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{dangling, data, fatten, meta, meta_type, NonNullExt, PtrExt};
+	use crate::{type_coerce, MetaType, Slice, TraitObject};
+	use std::{
+		any::Any, ptr::{self, NonNull}
+	};
+
+	#[test]
+	fn raw() {
+		let a: *const dyn Any = &123_u32;
+		let t: TraitObject = type_coerce(meta(a));
+		assert_eq!(meta_type(a), MetaType::TraitObject);
+		assert_eq!(
+			const { meta_type(ptr::slice_from_raw_parts(ptr::null::<u8>(), 0)) },
+			MetaType::Slice
+		);
+		assert_eq!(data(a), a.cast());
+		let b = fatten::<dyn Any>(data(a).cast_mut(), type_coerce(t));
+		assert_eq!(unsafe { (*b).downcast_ref::<u32>() }, Some(&123));
+		assert!(ptr::addr_eq(
+			dangling::<dyn Any>(type_coerce(t)).as_ptr(),
+			ptr::dangling::<u32>()
+		));
+	}
+
+	#[test]
+	fn ptr_ext() {
+		let mut a = [1_u8, 2, 3];
+		let b = ptr::from_mut(&mut a[..]);
+		assert_eq!(b.meta_type(), MetaType::Slice);
+		assert_eq!(b.thin(), b.cast::<()>());
+		let c = b.cast_const().with_meta(Slice { len: 1 });
+		assert_eq!(unsafe { &*c }, [1]);
+		assert_eq!(c.meta(), Slice { len: 1 });
+	}
+
+	#[test]
+	fn non_null_ext() {
+		let a: &dyn Any = &123_u64;
+		let a = NonNull::from(a);
+		let t = a.meta();
+		assert_eq!(NonNullExt::meta_type(a), MetaType::TraitObject);
+		assert_eq!(a.thin(), a.cast());
+		let b = NonNull::<dyn Any>::fatten_nonnull(a.thin(), t);
+		assert_eq!(unsafe { b.as_ref() }.downcast_ref(), Some(&123_u64));
+		assert_eq!(NonNullExt::with_meta(b, t).thin(), a.thin());
+	}
+}
@@ -0,0 +1,1092 @@
+//! A runtime registry of trait object vtables.
+//!
+//! Registering a concrete type against a `dyn Trait` records its vtable along
+//! with the trait's [`InterfaceDescriptor`], so that a [`TraitObject`] received
+//! across a plugin boundary can be checked against the trait it is expected to
+//! implement.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::{describe_trait, registry, TraitObject};
+//! trait Shape {
+//!     fn area(&self) -> f64;
+//! }
+//! describe_trait!(Shape { area });
+//!
+//! struct Square(f64);
+//! impl Shape for Square {
+//!     fn area(&self) -> f64 {
+//!         self.0 * self.0
+//!     }
+//! }
+//!
+//! let meta: TraitObject = registry::register::<Square, dyn Shape>();
+//! assert!(registry::check(meta, registry::descriptor::<dyn Shape>()));
+//! ```
+
+use std::{
+	any::{type_name, Any, TypeId}, collections::BTreeMap, error::Error, fmt, marker::Unsize, panic::Location, ptr::{self, NonNull}, rc::Rc, sync::{
+		atomic::{AtomicBool, Ordering}, Arc, RwLock
+	}
+};
+
+use super::{fnv1a, type_coerce, type_tag, MaybeTypeTag, TraitObject, Type, FNV_OFFSET};
+pub use crate::TypeTag;
+
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "perfect-hash")]
+mod table;
+#[cfg(feature = "serde")]
+pub use self::de::{
+	deserialize_box_dyn, deserialize_box_dyn_named, register_deserialize, register_deserialize_named, serialize_dyn, DeserializeFn, DuplicateName, Named, RegisteredTrait, RegistrySeed, Tagged, TraitRegistry
+};
+#[cfg(feature = "perfect-hash")]
+pub use self::table::PerfectTable;
+#[cfg(feature = "perfect-hash")]
+#[doc(hidden)]
+pub use self::table::{table_size, Slot};
+
+/// Runtime description of a `dyn Trait`, generated by [`describe_trait!`](crate::describe_trait).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct InterfaceDescriptor {
+	/// Name of the trait
+	pub name: &'static str,
+	/// Hash of the trait name and method names, stable across builds
+	pub id: u64,
+	/// Names of the trait's methods, in declaration order
+	pub methods: &'static [&'static str],
+}
+impl InterfaceDescriptor {
+	/// Create a descriptor for a trait with the given name and method names.
+	pub const fn new(name: &'static str, methods: &'static [&'static str]) -> Self {
+		let mut hash = fnv1a(FNV_OFFSET, name.as_bytes());
+		let mut i = 0;
+		while i < methods.len() {
+			hash = fnv1a(hash, &[0]);
+			hash = fnv1a(hash, methods[i].as_bytes());
+			i += 1;
+		}
+		Self {
+			name,
+			id: hash,
+			methods,
+		}
+	}
+	/// Number of methods in the trait.
+	pub const fn method_count(&self) -> usize {
+		self.methods.len()
+	}
+}
+
+/// Implemented on `dyn Trait` by [`describe_trait!`](crate::describe_trait).
+pub trait Interface: 'static {
+	/// The descriptor of this trait.
+	const DESCRIPTOR: InterfaceDescriptor;
+}
+
+/// Generate an [`InterfaceDescriptor`] for a trait, and implement [`Interface`]
+/// for its trait object.
+///
+/// The trait's methods must be listed for them to be included in the
+/// descriptor.
+///
+/// ```
+/// # use metatype::{describe_trait, registry};
+/// trait Shape {
+///     fn area(&self) -> f64;
+///     fn perimeter(&self) -> f64;
+/// }
+/// describe_trait!(Shape { area, perimeter });
+///
+/// let descriptor = registry::descriptor::<dyn Shape>();
+/// assert_eq!(descriptor.name, "Shape");
+/// assert_eq!(descriptor.method_count(), 2);
+/// ```
+#[macro_export]
+macro_rules! describe_trait {
+	($trait:path) => {
+		$crate::describe_trait!($trait {});
+	};
+	($trait:path { $($method:ident),* $(,)? }) => {
+		impl $crate::registry::Interface for dyn $trait {
+			const DESCRIPTOR: $crate::registry::InterfaceDescriptor =
+				$crate::registry::InterfaceDescriptor::new(
+					stringify!($trait),
+					&[$(stringify!($method)),*],
+				);
+		}
+	};
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+	type_id: TypeId,
+	type_name: &'static str,
+	trait_id: TypeId,
+	location: &'static Location<'static>,
+	tag: u64,
+	#[cfg(feature = "serde")]
+	tagged: bool,
+	descriptor: &'static InterfaceDescriptor,
+	canonical: TraitObject,
+	clone: Option<CloneFn>,
+	#[cfg(feature = "serde")]
+	serialize: Option<de::SerializeFn>,
+}
+
+/// Clones the value behind a thin pointer into a new allocation.
+type CloneFn = unsafe fn(*const ()) -> *mut ();
+
+trait MaybeClone {
+	const CLONE: Option<CloneFn>;
+}
+impl<T> MaybeClone for T {
+	default const CLONE: Option<CloneFn> = None;
+}
+impl<T: Clone> MaybeClone for T {
+	const CLONE: Option<CloneFn> = Some(clone::<T>);
+}
+unsafe fn clone<T: Clone>(data: *const ()) -> *mut () {
+	Box::into_raw(Box::new((*data.cast::<T>()).clone())).cast()
+}
+
+struct Registry {
+	/// Every vtable seen, keyed by address, with each (type, trait) pair it was
+	/// registered for: more than one if the compiler merged identical vtables
+	vtables: BTreeMap<usize, Vec<Entry>>,
+	/// The vtable chosen for each (type, trait) pair
+	canonical: BTreeMap<(TypeId, TypeId), TraitObject>,
+}
+
+static REGISTRY: RwLock<Registry> = RwLock::new(Registry {
+	vtables: BTreeMap::new(),
+	canonical: BTreeMap::new(),
+});
+
+/// Get the descriptor of a trait object type.
+pub fn descriptor<U: ?Sized + Interface>() -> &'static InterfaceDescriptor {
+	&U::DESCRIPTOR
+}
+
+/// Get the tag identifying `T` in serialized data, derived from its
+/// [`TypeTag`] if it has one, and otherwise its
+/// [`stable_type_id`](crate::stable_type_id).
+pub fn tag<T: ?Sized>() -> u64 {
+	type_tag::<T>()
+}
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable strict mode, in which types without a [`TypeTag`] have
+/// no stable id: [`stable_type_id`] fails for them, and with the `serde`
+/// feature so does serializing them with `Tagged`, rather than falling back
+/// to a tag derived from their name.
+///
+/// ```
+/// # use metatype::registry::{self, TypeTag};
+/// struct Tagged;
+/// impl TypeTag for Tagged {
+///     const TAG: &'static str = "tagged";
+/// }
+/// struct Untagged;
+///
+/// assert!(registry::stable_type_id::<Untagged>().is_ok());
+/// registry::set_strict(true);
+/// assert!(registry::stable_type_id::<Tagged>().is_ok());
+/// assert!(registry::stable_type_id::<Untagged>().is_err());
+/// ```
+pub fn set_strict(strict: bool) {
+	STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Whether strict mode is enabled; see [`set_strict`].
+pub fn is_strict() -> bool {
+	STRICT.load(Ordering::Relaxed)
+}
+
+/// Get the stable id of `T`, which is its [`tag`]: that of its [`TypeTag`] if
+/// it has one, and otherwise [`crate::stable_type_id`].
+///
+/// # Errors
+///
+/// Returns [`Untagged`] if `T` has no [`TypeTag`] and strict mode is
+/// enabled.
+pub fn stable_type_id<T: ?Sized>() -> Result<u64, Untagged> {
+	if is_strict() && <T as MaybeTypeTag>::TAG.is_none() {
+		return Err(Untagged {
+			type_name: type_name::<T>(),
+		});
+	}
+	Ok(tag::<T>())
+}
+
+/// Error returned when a type without a [`TypeTag`] is identified in strict
+/// mode.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Untagged {
+	/// Name of the type
+	pub type_name: &'static str,
+}
+impl fmt::Display for Untagged {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} has no TypeTag, which is required in strict mode",
+			self.type_name
+		)
+	}
+}
+impl Error for Untagged {}
+
+/// Register `T` as an implementor of `U`, returning the canonical vtable for
+/// the pair.
+///
+/// The first vtable registered for a (type, trait) pair becomes its canonical
+/// vtable; vtables registered subsequently for the same pair, for example by
+/// other crates, are recorded as duplicates of it.
+///
+/// If `T` is [`Clone`], values can subsequently be cloned with [`clone_box`];
+/// and with the `serde` feature, if `T` is [`Serialize`](serde::Serialize),
+/// serialized with [`serialize_dyn`].
+#[track_caller]
+pub fn register<T: Unsize<U> + 'static, U: ?Sized + Interface>() -> TraitObject {
+	let ptr: *const T = NonNull::dangling().as_ptr();
+	let ptr: *const U = ptr;
+	let meta: TraitObject = type_coerce(Type::meta(ptr));
+	let mut registry = REGISTRY.write().unwrap();
+	let canonical = *registry
+		.canonical
+		.entry((TypeId::of::<T>(), TypeId::of::<U>()))
+		.or_insert(meta);
+	let entry = Entry {
+		type_id: TypeId::of::<T>(),
+		type_name: type_name::<T>(),
+		trait_id: TypeId::of::<U>(),
+		location: Location::caller(),
+		tag: tag::<T>(),
+		#[cfg(feature = "serde")]
+		tagged: <T as MaybeTypeTag>::TAG.is_some(),
+		descriptor: descriptor::<U>(),
+		canonical,
+		clone: <T as MaybeClone>::CLONE,
+		#[cfg(feature = "serde")]
+		serialize: <T as de::MaybeSerialize>::SERIALIZE,
+	};
+	let entries = registry.vtables.entry(vtable_addr(meta)).or_default();
+	entries.retain(|other| (other.type_id, other.trait_id) != (entry.type_id, entry.trait_id));
+	entries.push(entry);
+	canonical
+}
+
+/// Register `T` as an implementor of `U` with [`register`]. With the `serde`
+/// feature, if `T` is [`Deserialize`](serde::Deserialize) and `U` has been set
+/// up with [`register_trait!`](crate::register_trait), also register `T` with
+/// [`register_deserialize`].
+///
+/// This is invoked for each type annotated with
+/// [`#[register_impl]`](crate::register_impl).
+#[track_caller]
+pub fn register_impl<T: Unsize<U> + 'static, U: ?Sized + Interface>() {
+	let _ = register::<T, U>();
+	#[cfg(feature = "serde")]
+	<T as de::MaybeDeserialize<U>>::register();
+}
+
+/// A registration submitted by [`#[register_impl]`](crate::register_impl),
+/// run the first time the registry is accessed.
+///
+/// Requires the `inventory` feature.
+#[cfg(feature = "inventory")]
+#[derive(Copy, Clone, Debug)]
+pub struct Registration(pub fn());
+#[cfg(feature = "inventory")]
+inventory::collect!(Registration);
+
+/// Run the registrations submitted by [`#[register_impl]`](crate::register_impl).
+fn submitted() {
+	#[cfg(feature = "inventory")]
+	{
+		static SUBMITTED: std::sync::Once = std::sync::Once::new();
+		SUBMITTED.call_once(|| {
+			for registration in inventory::iter::<Registration> {
+				(registration.0)();
+			}
+		});
+	}
+}
+
+/// Clone a trait object whose concrete type was registered and is [`Clone`].
+pub fn clone_box<U: ?Sized + Interface>(value: &U) -> Option<Box<U>> {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	let clone = lookup(meta)?.clone?;
+	let data = unsafe { clone(Type::data(value)) };
+	Some(unsafe { Box::from_raw(U::fatten(data, type_coerce(meta))) })
+}
+
+/// Get the tag of the concrete type of a trait object, if it was registered.
+pub fn tag_of_val<U: ?Sized + Interface>(value: &U) -> Option<u64> {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	lookup(meta).map(|entry| entry.tag)
+}
+
+/// Get the type id of the concrete type of a trait object, if it was
+/// registered.
+pub fn type_id_of_val<U: ?Sized + Interface>(value: &U) -> Option<TypeId> {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	lookup(meta).map(|entry| entry.type_id)
+}
+
+/// Whether the concrete type of a trait object is `T`, without requiring the
+/// trait to have [`Any`](std::any::Any) as a supertrait.
+///
+/// This compares `T` with the type recorded by [`register`] for the vtable.
+/// The vtable's address alone isn't proof of the type, as the compiler may
+/// merge the identical vtables of distinct types; a vtable registered for
+/// more than one type is recognised as neither. Only registered vtables are
+/// recognised, so a value whose vtable is an unregistered duplicate isn't;
+/// nor is a vtable merely identical in layout, as reported by [`identify`].
+///
+/// ```
+/// # use metatype::{describe_trait, registry};
+/// trait Shape {
+///     fn area(&self) -> f64;
+/// }
+/// describe_trait!(Shape { area });
+///
+/// struct Square(f64);
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+/// }
+/// let _ = registry::register::<Square, dyn Shape>();
+///
+/// let a: &dyn Shape = &Square(2.0);
+/// assert!(registry::is::<Square, dyn Shape>(a));
+/// assert_eq!(registry::downcast_ref::<Square, dyn Shape>(a).map(|a| a.0), Some(2.0));
+/// ```
+pub fn is<T: Unsize<U> + 'static, U: ?Sized + Interface>(value: &U) -> bool {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	lookup(meta).is_some_and(|entry| entry.type_id == TypeId::of::<T>())
+}
+
+/// Downcast a trait object to a `&T` if its concrete type is `T`, as
+/// determined by [`is`].
+pub fn downcast_ref<T: Unsize<U> + 'static, U: ?Sized + Interface>(value: &U) -> Option<&T> {
+	is::<T, U>(value).then(|| unsafe { &*Type::data(value).cast::<T>() })
+}
+
+/// Downcast a trait object to a `&mut T` if its concrete type is `T`, as
+/// determined by [`is`].
+pub fn downcast_mut<T: Unsize<U> + 'static, U: ?Sized + Interface>(
+	value: &mut U,
+) -> Option<&mut T> {
+	if !is::<T, U>(value) {
+		return None;
+	}
+	Some(unsafe { &mut *Type::data_mut(value).cast::<T>() })
+}
+
+/// Downcast a boxed trait object to a `Box<T>` if its concrete type is `T`,
+/// as determined by [`is`].
+///
+/// # Errors
+///
+/// Returns `value` if its concrete type isn't `T`.
+pub fn downcast_box<T: Unsize<U> + 'static, U: ?Sized + Interface>(
+	value: Box<U>,
+) -> Result<Box<T>, Box<U>> {
+	if !is::<T, U>(&*value) {
+		return Err(value);
+	}
+	Ok(unsafe { Box::from_raw(Type::data_mut(Box::into_raw(value)).cast()) })
+}
+
+/// Downcast an `Rc` trait object to an `Rc<T>` if its concrete type is `T`,
+/// as determined by [`is`].
+///
+/// # Errors
+///
+/// Returns `value` if its concrete type isn't `T`.
+pub fn downcast_rc<T: Unsize<U> + 'static, U: ?Sized + Interface>(
+	value: Rc<U>,
+) -> Result<Rc<T>, Rc<U>> {
+	if !is::<T, U>(&*value) {
+		return Err(value);
+	}
+	Ok(unsafe { Rc::from_raw(Type::data(Rc::into_raw(value)).cast()) })
+}
+
+/// Downcast an `Arc` trait object to an `Arc<T>` if its concrete type is
+/// `T`, as determined by [`is`].
+///
+/// # Errors
+///
+/// Returns `value` if its concrete type isn't `T`.
+pub fn downcast_arc<T: Unsize<U> + 'static, U: ?Sized + Interface>(
+	value: Arc<U>,
+) -> Result<Arc<T>, Arc<U>> {
+	if !is::<T, U>(&*value) {
+		return Err(value);
+	}
+	Ok(unsafe { Arc::from_raw(Type::data(Arc::into_raw(value)).cast()) })
+}
+
+/// Get the descriptor a vtable was registered under, if any.
+pub fn registered_under(meta: TraitObject) -> Option<&'static InterfaceDescriptor> {
+	lookup(meta).map(|entry| entry.descriptor)
+}
+
+/// Get the type id and name of the concrete type a vtable was registered for,
+/// if any.
+pub fn registered_type(meta: TraitObject) -> Option<(TypeId, &'static str)> {
+	lookup(meta).map(|entry| (entry.type_id, entry.type_name))
+}
+
+/// Check that no two registered types share a [`tag`], as can happen if they
+/// were given the same [`TypeTag`], in which case tagged values of either
+/// would be deserialized as the one registered first.
+///
+/// With the `serde` feature, this covers types registered for deserialization
+/// in a [`TraitRegistry`] as well as those registered with [`register`].
+///
+/// # Errors
+///
+/// Returns a [`Collision`] for each pair of types sharing a tag.
+pub fn check_collisions() -> Result<(), Vec<Collision>> {
+	submitted();
+	let registry = REGISTRY.read().unwrap();
+	let mut tags: BTreeMap<u64, &Entry> = BTreeMap::new();
+	let mut collisions = Vec::new();
+	for entry in registry.vtables.values().flatten() {
+		let first = *tags.entry(entry.tag).or_insert(entry);
+		if first.type_id != entry.type_id
+			&& !collisions.iter().any(|collision: &Collision| {
+				collision.tag == entry.tag && collision.types[1].0 == entry.type_name
+			}) {
+			collisions.push(Collision {
+				tag: entry.tag,
+				types: [
+					(first.type_name, first.location),
+					(entry.type_name, entry.location),
+				],
+			});
+		}
+	}
+	#[cfg(feature = "serde")]
+	for collision in de::COLLISIONS.lock().unwrap().iter() {
+		if !collisions.iter().any(|found| found.is_between(collision)) {
+			collisions.push(*collision);
+		}
+	}
+	if collisions.is_empty() {
+		Ok(())
+	} else {
+		Err(collisions)
+	}
+}
+
+/// Two registered types sharing a tag, as reported by [`check_collisions`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Collision {
+	/// The shared tag
+	pub tag: u64,
+	/// Names of the types, along with where each was registered
+	pub types: [(&'static str, &'static Location<'static>); 2],
+}
+impl Collision {
+	/// Whether this is between the same types as `other`, wherever they were
+	/// registered.
+	#[cfg(feature = "serde")]
+	fn is_between(&self, other: &Self) -> bool {
+		let [(a, _), (b, _)] = self.types;
+		let [(c, _), (d, _)] = other.types;
+		self.tag == other.tag && ((a, b) == (c, d) || (a, b) == (d, c))
+	}
+}
+impl fmt::Display for Collision {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let [(a, a_location), (b, b_location)] = self.types;
+		write!(
+			f,
+			"{a} (registered at {a_location}) and {b} (registered at {b_location}) share tag {:#x}; give one of them a distinct TypeTag",
+			self.tag
+		)
+	}
+}
+impl Error for Collision {}
+
+/// Check that a vtable was registered under the expected descriptor.
+///
+/// Returns `false` if the vtable was registered under a different trait, or
+/// wasn't registered at all.
+///
+/// Vtables are identified as described in [`canonicalize`].
+pub fn check(meta: TraitObject, expected: &InterfaceDescriptor) -> bool {
+	registered_under(meta) == Some(expected)
+}
+
+/// Get the canonical vtable of a type for a trait, given the [`TypeId`]s of
+/// the type and of the trait object, if the type has been registered against
+/// the trait.
+pub fn canonical_vtable(type_id: TypeId, trait_id: TypeId) -> Option<TraitObject> {
+	submitted();
+	REGISTRY
+		.read()
+		.unwrap()
+		.canonical
+		.get(&(type_id, trait_id))
+		.copied()
+}
+
+/// Iterate over the [`TypeId`]s of the trait objects a type has been
+/// registered against, given the type's [`TypeId`].
+pub fn impls_of(type_id: TypeId) -> impl Iterator<Item = TypeId> {
+	submitted();
+	let registry = REGISTRY.read().unwrap();
+	let traits: Vec<TypeId> = registry
+		.canonical
+		.keys()
+		.filter(|&&(type_, _)| type_ == type_id)
+		.map(|&(_, trait_)| trait_)
+		.collect();
+	traits.into_iter()
+}
+
+/// Check whether a type has been registered against a trait, given the
+/// [`TypeId`]s of the type and of the trait object.
+pub fn is_impl(type_id: TypeId, trait_id: TypeId) -> bool {
+	canonical_vtable(type_id, trait_id).is_some()
+}
+
+/// Map a vtable to the canonical vtable of its (type, trait) pair, or return
+/// it unchanged if it wasn't registered.
+///
+/// The compiler may emit duplicate vtables for the same type and trait in
+/// different crates and codegen units. Vtables are identified by address, so
+/// only those passed to [`register`] are recognised as duplicates; for traits
+/// with [`Any`] as a supertrait, [`canonicalize_of_val`] recognises any.
+pub fn canonicalize(meta: TraitObject) -> TraitObject {
+	lookup(meta).map_or(meta, |entry| entry.canonical)
+}
+
+/// Get the canonical vtable of the (type, trait) pair of a trait object whose
+/// trait has [`Any`] as a supertrait, or its vtable unchanged if the type
+/// wasn't registered against the trait.
+///
+/// Unlike [`canonicalize`], this recognises duplicate vtables that weren't
+/// passed to [`register`], as the concrete type is identified by its
+/// [`TypeId`] through the `Any` supertrait rather than by vtable address.
+///
+/// ```
+/// # use metatype::{describe_trait, registry, type_coerce, TraitObject, Type};
+/// use std::any::Any;
+///
+/// trait Shape: Any {
+///     fn area(&self) -> u64;
+/// }
+/// describe_trait!(Shape { area });
+///
+/// struct Square(u64);
+/// impl Shape for Square {
+///     fn area(&self) -> u64 {
+///         self.0 * self.0
+///     }
+/// }
+/// let canonical = registry::register::<Square, dyn Shape>();
+///
+/// let a: &dyn Shape = &Square(2);
+/// assert_eq!(registry::canonicalize_of_val(a), canonical);
+/// ```
+pub fn canonicalize_of_val<U: ?Sized + Interface + Unsize<dyn Any>>(value: &U) -> TraitObject {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	let any: &dyn Any = value;
+	canonical_vtable(any.type_id(), TypeId::of::<U>()).unwrap_or(meta)
+}
+
+/// Validate that a vtable was registered under the expected descriptor,
+/// describing the mismatch if not.
+///
+/// A vtable registered under a trait of the same name but with different
+/// methods, as exported by a plugin built against another version of the
+/// trait, is reported as [`InterfaceError::MissingMethod`] if that version
+/// lacks any of the expected methods, so that the vtable has no entry for
+/// them, and otherwise as [`InterfaceError::VersionMismatch`].
+///
+/// # Errors
+///
+/// Returns an [`InterfaceError`] describing how the registration didn't match
+/// `expected`.
+pub fn validate_interface(
+	meta: TraitObject, expected: &InterfaceDescriptor,
+) -> Result<(), InterfaceError> {
+	let found = *registered_under(meta).ok_or(InterfaceError::Unregistered)?;
+	if found.name != expected.name {
+		return Err(InterfaceError::WrongTrait {
+			expected: *expected,
+			found,
+		});
+	}
+	if let Some(&method) = expected
+		.methods
+		.iter()
+		.find(|method| !found.methods.contains(method))
+	{
+		return Err(InterfaceError::MissingMethod { method });
+	}
+	if found != *expected {
+		return Err(InterfaceError::VersionMismatch {
+			expected: *expected,
+			found,
+		});
+	}
+	Ok(())
+}
+
+/// Error returned by [`validate_interface`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum InterfaceError {
+	/// The vtable wasn't registered
+	Unregistered,
+	/// The vtable was registered under a different trait
+	WrongTrait {
+		/// The expected descriptor
+		expected: InterfaceDescriptor,
+		/// The descriptor the vtable was registered under
+		found: InterfaceDescriptor,
+	},
+	/// The vtable was registered under a different version of the trait
+	VersionMismatch {
+		/// The expected descriptor
+		expected: InterfaceDescriptor,
+		/// The descriptor the vtable was registered under
+		found: InterfaceDescriptor,
+	},
+	/// The vtable was registered under a version of the trait without a method
+	MissingMethod {
+		/// Name of the method
+		method: &'static str,
+	},
+}
+impl fmt::Display for InterfaceError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Unregistered => f.write_str("vtable not registered"),
+			Self::WrongTrait { expected, found } => write!(
+				f,
+				"vtable registered for trait {} rather than {}",
+				found.name, expected.name
+			),
+			Self::VersionMismatch { expected, found } => write!(
+				f,
+				"vtable registered for a different version of trait {}: methods {:?} rather than {:?}",
+				expected.name, found.methods, expected.methods
+			),
+			Self::MissingMethod { method } => write!(f, "vtable has no entry for method {method}"),
+		}
+	}
+}
+impl Error for InterfaceError {}
+
+fn lookup(meta: TraitObject) -> Option<Entry> {
+	submitted();
+	match REGISTRY.read().unwrap().vtables.get(&vtable_addr(meta))?[..] {
+		[entry] => Some(entry),
+		// The vtables of distinct types or traits were merged, so which it is
+		// can't be told.
+		_ => None,
+	}
+}
+
+/// How the concrete type of a trait object was recognised by [`identify`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Identity {
+	/// The vtable was passed to [`register`] for this type
+	Registered(TypeId),
+	/// The vtable is identical in header and methods to the one registered for
+	/// this type, so the concrete type is probably, but not certainly, this
+	/// one: distinct types of the same size and align with no drop glue and
+	/// merged methods have identical vtables
+	#[cfg(feature = "vtable-layout")]
+	Layout(TypeId),
+}
+
+/// Identify the concrete type of a trait object from its vtable.
+///
+/// A vtable registered with [`register`] is identified with certainty. With
+/// the `vtable-layout` feature enabled, a vtable that isn't recognised by
+/// address is compared entry-by-entry with the vtables registered for `U`, and
+/// if it's identical to exactly one of them reported as
+/// [`Identity::Layout`]. This relies on the unspecified layout of vtables
+/// emitted by rustc, and on the methods listed in `U`'s descriptor being its
+/// only methods. Such a match isn't used by [`is`] or the downcasts.
+pub fn identify<U: ?Sized + Interface>(value: &U) -> Option<Identity> {
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	if let Some(entry) = lookup(meta) {
+		return Some(Identity::Registered(entry.type_id));
+	}
+	#[cfg(feature = "vtable-layout")]
+	{
+		let registry = REGISTRY.read().unwrap();
+		let descriptor = descriptor::<U>();
+		let mut found = registry
+			.canonical
+			.iter()
+			.filter(|((_, trait_id), _)| *trait_id == TypeId::of::<U>())
+			.filter(|(_, canonical)| unsafe { vtable_eq(meta, **canonical, descriptor) })
+			.map(|((type_id, _), _)| *type_id);
+		let type_id = found.next()?;
+		return found.next().is_none().then_some(Identity::Layout(type_id));
+	}
+	#[allow(unreachable_code)]
+	None
+}
+
+/// Compare the header and method entries of two vtables of the trait described
+/// by `descriptor`.
+#[cfg(feature = "vtable-layout")]
+unsafe fn vtable_eq(a: TraitObject, b: TraitObject, descriptor: &InterfaceDescriptor) -> bool {
+	let (a, b): (*const (), *const ()) = (a.vtable, b.vtable);
+	let (a, b) = (a.cast::<usize>(), b.cast::<usize>());
+	(0..3 + descriptor.method_count()).all(|i| *a.add(i) == *b.add(i))
+}
+
+fn vtable_addr(meta: TraitObject) -> usize {
+	ptr::from_ref(meta.vtable).addr()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		canonical_vtable, canonicalize_of_val, check, check_collisions, descriptor, downcast_arc, downcast_box, downcast_mut, downcast_rc, downcast_ref, identify, impls_of, is, is_impl, register, registered_type, registered_under, stable_type_id, tag, type_id_of_val, validate_interface, Identity, InterfaceDescriptor, InterfaceError, TypeTag
+	};
+	use crate::{type_coerce, TraitObject, Type};
+	use std::{
+		any::{Any, TypeId}, ptr, rc::Rc, sync::Arc
+	};
+
+	trait Shape {
+		fn area(&self) -> u64;
+	}
+	describe_trait!(Shape { area });
+	trait Named {
+		fn name(&self) -> String;
+	}
+	describe_trait!(Named { name });
+
+	struct Square(u64);
+	impl Shape for Square {
+		fn area(&self) -> u64 {
+			self.0 * self.0
+		}
+	}
+	struct Triangle;
+	impl Shape for Triangle {
+		fn area(&self) -> u64 {
+			0
+		}
+	}
+	impl Named for Square {
+		fn name(&self) -> String {
+			String::from("square")
+		}
+	}
+
+	#[test]
+	fn downcast() {
+		// Duplicate vtables are only recognised with `vtable-layout`, so use
+		// the registered one.
+		let meta = register::<Square, dyn Shape>();
+		let mut a = Square(2);
+		let a: &mut dyn Shape =
+			unsafe { &mut *<dyn Shape>::fatten(ptr::from_mut(&mut a).cast(), type_coerce(meta)) };
+		assert!(is::<Square, dyn Shape>(a) && !is::<Triangle, dyn Shape>(a));
+		downcast_mut::<Square, dyn Shape>(a).unwrap().0 = 3;
+		assert_eq!(downcast_ref::<Square, dyn Shape>(a).map(|a| a.0), Some(3));
+		assert!(downcast_ref::<Triangle, dyn Shape>(a).is_none());
+	}
+
+	#[test]
+	fn downcast_smart() {
+		let meta = register::<Square, dyn Shape>();
+		let a = Box::into_raw(Box::new(Square(2)));
+		let a = unsafe { Box::from_raw(<dyn Shape>::fatten(a.cast(), type_coerce(meta))) };
+		let a = downcast_box::<Triangle, dyn Shape>(a).err().unwrap();
+		assert_eq!(downcast_box::<Square, dyn Shape>(a).ok().unwrap().0, 2);
+		let b = Rc::into_raw(Rc::new(Square(3)));
+		let b = unsafe { Rc::from_raw(<dyn Shape>::fatten_const(b.cast(), type_coerce(meta))) };
+		let c = b.clone();
+		let b = downcast_rc::<Square, dyn Shape>(b).ok().unwrap();
+		assert_eq!((b.0, Rc::strong_count(&b)), (3, 2));
+		drop(c);
+		let d = Arc::into_raw(Arc::new(Square(4)));
+		let d = unsafe { Arc::from_raw(<dyn Shape>::fatten_const(d.cast(), type_coerce(meta))) };
+		let d = downcast_arc::<Triangle, dyn Shape>(d).err().unwrap();
+		assert_eq!(downcast_arc::<Square, dyn Shape>(d).ok().unwrap().0, 4);
+	}
+
+	#[test]
+	fn register_check() {
+		let shape = register::<Square, dyn Shape>();
+		let named = register::<Square, dyn Named>();
+		assert_eq!(descriptor::<dyn Shape>().methods, &["area"]);
+		assert_ne!(descriptor::<dyn Shape>().id, descriptor::<dyn Named>().id);
+		assert!(check(shape, descriptor::<dyn Shape>()));
+		assert!(!check(shape, descriptor::<dyn Named>()));
+		assert!(check(named, descriptor::<dyn Named>()));
+		assert_eq!(registered_under(named), Some(descriptor::<dyn Named>()));
+		assert_eq!(registered_type(shape).unwrap().0, TypeId::of::<Square>());
+
+		let mut square = Square(2);
+		let square = ptr::from_mut(&mut square);
+		let square: *mut dyn Shape = Type::fatten(square.cast(), type_coerce(shape));
+		assert_eq!(unsafe { &*square }.area(), 4);
+		let square: &dyn Named = &Square(2);
+		assert_eq!(square.name(), "square");
+	}
+
+	#[test]
+	fn impls() {
+		let _ = register::<Square, dyn Shape>();
+		let _ = register::<Square, dyn Named>();
+		let impls: Vec<TypeId> = impls_of(TypeId::of::<Square>()).collect();
+		assert!(impls.contains(&TypeId::of::<dyn Shape>()));
+		assert!(impls.contains(&TypeId::of::<dyn Named>()));
+		assert!(is_impl(TypeId::of::<Square>(), TypeId::of::<dyn Named>()));
+		assert!(!is_impl(
+			TypeId::of::<Triangle>(),
+			TypeId::of::<dyn Shape>()
+		));
+		assert_eq!(impls_of(TypeId::of::<Triangle>()).count(), 0);
+	}
+
+	#[test]
+	fn type_tag() {
+		struct Renamed;
+		impl TypeTag for Renamed {
+			const TAG: &'static str = "Square";
+		}
+		assert_eq!(tag::<Renamed>(), super::fnv1a(super::FNV_OFFSET, b"Square"));
+		assert_ne!(tag::<Square>(), tag::<Triangle>());
+		assert_eq!(tag::<String>(), crate::stable_type_id::<String>());
+		assert_eq!(
+			stable_type_id::<String>(),
+			Ok(crate::stable_type_id::<String>())
+		);
+	}
+
+	#[test]
+	fn merged_vtables() {
+		struct X;
+		struct Y;
+		impl Shape for X {
+			fn area(&self) -> u64 {
+				0
+			}
+		}
+		let meta = register::<X, dyn Shape>();
+		let x: &dyn Shape = unsafe {
+			&*<dyn Shape>::fatten(ptr::from_ref(&X).cast_mut().cast(), type_coerce(meta))
+		};
+		assert!(is::<X, dyn Shape>(x));
+		// Simulate the compiler merging the vtable with that of another type.
+		{
+			let mut registry = super::REGISTRY.write().unwrap();
+			let entries = registry.vtables.get_mut(&super::vtable_addr(meta)).unwrap();
+			let mut entry = entries[0];
+			entry.type_id = TypeId::of::<Y>();
+			entry.tag = tag::<Y>();
+			entries.push(entry);
+		}
+		assert!(!is::<X, dyn Shape>(x) && downcast_ref::<X, dyn Shape>(x).is_none());
+		assert_eq!(type_id_of_val(x), None);
+	}
+
+	#[test]
+	fn collisions() {
+		struct A;
+		struct B;
+		impl TypeTag for A {
+			const TAG: &'static str = "collides";
+		}
+		impl TypeTag for B {
+			const TAG: &'static str = "collides";
+		}
+		impl Shape for A {
+			fn area(&self) -> u64 {
+				0
+			}
+		}
+		impl Shape for B {
+			fn area(&self) -> u64 {
+				0
+			}
+		}
+		let _ = register::<A, dyn Shape>();
+		let _ = register::<B, dyn Shape>();
+		let collisions = check_collisions().unwrap_err();
+		let collision = collisions
+			.iter()
+			.find(|collision| collision.tag == tag::<A>())
+			.unwrap();
+		assert_eq!(
+			collisions
+				.iter()
+				.filter(|collision| collision.tag == tag::<A>())
+				.count(),
+			1
+		);
+		assert!(collision.types[0].1.file().ends_with("registry.rs"));
+		assert!(collision.to_string().contains("distinct TypeTag"));
+	}
+
+	#[test]
+	fn validate() {
+		let shape = register::<Square, dyn Shape>();
+		assert_eq!(validate_interface(shape, descriptor::<dyn Shape>()), Ok(()));
+		assert!(matches!(
+			validate_interface(shape, descriptor::<dyn Named>()),
+			Err(InterfaceError::WrongTrait { .. })
+		));
+		let newer = InterfaceDescriptor::new("Shape", &["area", "perimeter"]);
+		assert_eq!(
+			validate_interface(shape, &newer),
+			Err(InterfaceError::MissingMethod {
+				method: "perimeter"
+			})
+		);
+		let older = InterfaceDescriptor::new("Shape", &[]);
+		assert!(matches!(
+			validate_interface(shape, &older),
+			Err(InterfaceError::VersionMismatch { .. })
+		));
+		let unregistered: &dyn Shape = &Triangle;
+		let unregistered: TraitObject = type_coerce(Type::meta(unregistered));
+		assert_eq!(
+			validate_interface(unregistered, descriptor::<dyn Shape>()),
+			Err(InterfaceError::Unregistered)
+		);
+	}
+
+	#[test]
+	fn canonical() {
+		let canonical = register::<Square, dyn Shape>();
+		assert_eq!(register::<Square, dyn Shape>(), canonical);
+		assert_eq!(
+			canonical_vtable(TypeId::of::<Square>(), TypeId::of::<dyn Shape>()),
+			Some(canonical)
+		);
+		assert_eq!(
+			canonical_vtable(TypeId::of::<Triangle>(), TypeId::of::<dyn Shape>()),
+			None
+		);
+		assert_eq!(canonical.canonicalize(), canonical);
+
+		let square: &dyn Shape = &Square(1);
+		let meta: TraitObject = type_coerce(Type::meta(square));
+		let registered: &dyn Shape = unsafe {
+			&*<dyn Shape>::fatten(
+				ptr::from_ref(square).cast_mut().cast(),
+				type_coerce(canonical),
+			)
+		};
+		assert_eq!(
+			identify(registered),
+			Some(Identity::Registered(TypeId::of::<Square>()))
+		);
+		if meta != canonical {
+			// An unregistered duplicate, which can't be identified by address,
+			// and needn't share method pointers with the registered vtable.
+			assert_eq!(meta.canonicalize(), meta);
+			assert!(!is::<Triangle, dyn Shape>(square));
+		}
+		if let Some(Identity::Registered(type_id)) = identify::<dyn Shape>(square) {
+			assert_eq!(type_id, TypeId::of::<Square>());
+		}
+		#[cfg(feature = "vtable-layout")]
+		if let Some(Identity::Layout(type_id)) = identify::<dyn Shape>(square) {
+			assert_eq!(type_id, TypeId::of::<Square>());
+		}
+	}
+
+	#[test]
+	fn canonicalize_any() {
+		trait AnyShape: Any {}
+		crate::describe_trait!(AnyShape);
+		impl AnyShape for Square {}
+		impl AnyShape for Triangle {}
+		let canonical = register::<Square, dyn AnyShape>();
+		let square: &dyn AnyShape = &Square(1);
+		assert_eq!(canonicalize_of_val(square), canonical);
+		let triangle: &dyn AnyShape = &Triangle;
+		let meta: TraitObject = type_coerce(Type::meta(triangle));
+		assert_eq!(canonicalize_of_val(triangle), meta);
+	}
+
+	#[cfg(feature = "derive")]
+	#[test]
+	fn dyn_trait() {
+		#[crate::dyn_trait]
+		trait Sided {
+			fn sides(&self) -> usize;
+		}
+		impl Sided for Square {
+			fn sides(&self) -> usize {
+				4
+			}
+		}
+		impl Sided for Triangle {
+			fn sides(&self) -> usize {
+				3
+			}
+		}
+
+		assert_eq!(descriptor::<dyn Sided>().methods, ["sides"]);
+		let meta = <dyn Sided>::vtable_of::<Square>();
+		assert_eq!(
+			canonical_vtable(TypeId::of::<Square>(), TypeId::of::<dyn Sided>()),
+			Some(meta)
+		);
+		let a = Box::into_raw(Box::new(Square(2)));
+		let mut a = unsafe { Box::from_raw(<dyn Sided>::fatten(a.cast(), type_coerce(meta))) };
+		assert_eq!(a.concrete_type_id(), Some(TypeId::of::<Square>()));
+		assert!(a.is::<Square>() && !a.is::<Triangle>());
+		a.downcast_mut::<Square>().unwrap().0 = 3;
+		assert_eq!(a.downcast_ref::<Square>().map(|a| a.0), Some(3));
+		let a = a.downcast::<Triangle>().err().unwrap();
+		assert_eq!((a.sides(), a.downcast::<Square>().ok().unwrap().0), (4, 3));
+		let b: &dyn Sided = &Triangle;
+		assert_eq!(b.concrete_type_id(), None);
+	}
+
+	#[cfg(feature = "inventory")]
+	#[test]
+	fn submitted() {
+		use super::{clone_box, tag, tag_of_val};
+
+		#[crate::register_impl(dyn Shape)]
+		#[derive(Clone)]
+		struct Circle(u64);
+		impl Shape for Circle {
+			fn area(&self) -> u64 {
+				self.0 * self.0 * 3
+			}
+		}
+
+		let meta = canonical_vtable(TypeId::of::<Circle>(), TypeId::of::<dyn Shape>()).unwrap();
+		let mut circle = Circle(2);
+		let circle = ptr::from_mut(&mut circle);
+		let circle: &dyn Shape = unsafe { &*Type::fatten(circle.cast(), type_coerce(meta)) };
+		assert_eq!(tag_of_val(circle), Some(tag::<Circle>()));
+		let cloned: Box<dyn Shape> = clone_box(circle).unwrap();
+		assert_eq!(cloned.area(), 12);
+
+		let meta = register::<Square, dyn Shape>();
+		let mut square = Square(2);
+		let square = ptr::from_mut(&mut square);
+		let square: &dyn Shape = unsafe { &*Type::fatten(square.cast(), type_coerce(meta)) };
+		assert!(clone_box(square).is_none());
+	}
+}
@@ -0,0 +1,883 @@
+//! Serialization and deserialization of trait objects, enabled by the `serde`
+//! feature.
+
+use serde::{
+	de::{DeserializeSeed, Error as _, SeqAccess, Visitor}, ser::{Error as _, SerializeTuple}, Deserialize, Deserializer, Serialize, Serializer
+};
+use std::{
+	any::{type_name, TypeId}, collections::BTreeMap, error::Error, fmt, marker::Unsize, panic::Location, sync::{Mutex, RwLock}
+};
+
+use super::{is_strict, lookup, submitted, tag, tag_of_val, Collision, Interface, Untagged};
+use crate::{type_coerce, TraitObject, Type};
+
+/// Function deserializing a `Box<U>` from an erased deserializer.
+pub type DeserializeFn<U> =
+	for<'de> fn(&mut dyn erased_serde::Deserializer<'de>) -> Result<Box<U>, erased_serde::Error>;
+
+/// The registry of deserializable implementors of a trait, set up by
+/// [`register_trait!`](crate::register_trait).
+///
+/// Implementors are identified by their [`tag`](super::tag), and optionally
+/// also by an explicit name.
+pub struct TraitRegistry<U: ?Sized> {
+	entries: RwLock<Entries<U>>,
+}
+struct Entries<U: ?Sized> {
+	/// Implementors keyed by tag
+	tags: BTreeMap<u64, Implementor<U>>,
+	/// Tags keyed by name
+	names: BTreeMap<&'static str, u64>,
+}
+struct Implementor<U: ?Sized> {
+	type_id: TypeId,
+	type_name: &'static str,
+	location: &'static Location<'static>,
+	name: Option<&'static str>,
+	deserialize: DeserializeFn<U>,
+}
+impl<U: ?Sized> Clone for Implementor<U> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<U: ?Sized> Copy for Implementor<U> {}
+impl<U: ?Sized> Entries<U> {
+	/// Get the implementor registered under `tag`, first registering `T` under
+	/// it if there's none.
+	///
+	/// If another type is already registered under `tag` it's left in place, and
+	/// the collision is recorded for [`check_collisions`](super::check_collisions)
+	/// and returned.
+	#[track_caller]
+	fn implementor<T>(&mut self, tag: u64) -> Result<&mut Implementor<U>, Collision>
+	where
+		T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	{
+		let location = Location::caller();
+		let implementor = self
+			.tags
+			.entry(tag)
+			.or_insert_with(|| implementor::<T, U>(location));
+		if implementor.type_id == TypeId::of::<T>() {
+			return Ok(implementor);
+		}
+		let collision = Collision {
+			tag,
+			types: [
+				(implementor.type_name, implementor.location),
+				(type_name::<T>(), location),
+			],
+		};
+		let mut collisions = COLLISIONS.lock().unwrap();
+		if !collisions
+			.iter()
+			.any(|recorded| recorded.is_between(&collision))
+		{
+			collisions.push(collision);
+		}
+		Err(collision)
+	}
+}
+
+/// Tags shared by distinct implementors registered in any [`TraitRegistry`].
+pub(super) static COLLISIONS: Mutex<Vec<Collision>> = Mutex::new(Vec::new());
+
+impl<U: ?Sized> TraitRegistry<U> {
+	/// Create an empty registry.
+	pub const fn new() -> Self {
+		Self {
+			entries: RwLock::new(Entries {
+				tags: BTreeMap::new(),
+				names: BTreeMap::new(),
+			}),
+		}
+	}
+	/// Register `T` as a deserializable implementor of `U` in this registry,
+	/// returning the tag it's registered under.
+	///
+	/// If a different type is already registered under the same tag, it's kept,
+	/// and the collision is reported by
+	/// [`check_collisions`](super::check_collisions).
+	#[track_caller]
+	pub fn register<T>(&self) -> u64
+	where
+		T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	{
+		let tag = tag::<T>();
+		let _ = self.entries.write().unwrap().implementor::<T>(tag);
+		tag
+	}
+	/// Register `T` as a deserializable implementor of `U` in this registry,
+	/// under the explicit name `name` as well as its tag.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `name` is already registered for a different type,
+	/// or if a different type is already registered under the tag of `T`, in
+	/// which case that type is left as it was.
+	#[track_caller]
+	pub fn register_named<T>(&self, name: &'static str) -> Result<u64, DuplicateName>
+	where
+		T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	{
+		let tag = tag::<T>();
+		let mut entries = self.entries.write().unwrap();
+		if let Some(existing) = entries.names.get(name).filter(|&&existing| existing != tag) {
+			return Err(DuplicateName {
+				name,
+				existing: entries.tags[existing].type_name,
+				new: type_name::<T>(),
+			});
+		}
+		let implementor = entries
+			.implementor::<T>(tag)
+			.map_err(|collision| DuplicateName {
+				name,
+				existing: collision.types[0].0,
+				new: type_name::<T>(),
+			})?;
+		implementor.name = Some(name);
+		let _ = entries.names.insert(name, tag);
+		Ok(tag)
+	}
+	/// Deserialize a `Box<U>` from `payload`, using the implementor of `U`
+	/// registered in this registry under `tag`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no implementor is registered under `tag`, or if
+	/// deserializing the implementor from `payload` fails.
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		&self, tag: u64, payload: D,
+	) -> Result<Box<U>, D::Error> {
+		submitted();
+		let entry = self.entries.read().unwrap().tags.get(&tag).copied();
+		let implementor = entry.ok_or_else(|| {
+			D::Error::custom(format_args!(
+				"unknown tag {tag:#x} for {}",
+				type_name::<U>()
+			))
+		})?;
+		(implementor.deserialize)(&mut <dyn erased_serde::Deserializer>::erase(payload))
+			.map_err(D::Error::custom)
+	}
+	/// Deserialize a `Box<U>` from `payload`, using the implementor of `U`
+	/// registered in this registry under the explicit name `name`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no implementor is registered under `name`, naming the
+	/// closest registered name if there's one similar, or if deserializing the
+	/// implementor from `payload` fails.
+	pub fn deserialize_named<'de, D: Deserializer<'de>>(
+		&self, name: &str, payload: D,
+	) -> Result<Box<U>, D::Error> {
+		submitted();
+		let entries = self.entries.read().unwrap();
+		let Some(&tag) = entries.names.get(name) else {
+			let suggestion = entries
+				.names
+				.keys()
+				.map(|candidate| (edit_distance(name, candidate), candidate))
+				.filter(|&(distance, _)| distance <= (name.len() / 3).max(1))
+				.min();
+			return Err(match suggestion {
+				Some((_, suggestion)) => D::Error::custom(format_args!(
+					"unknown tag `{name}` for {}, did you mean `{suggestion}`?",
+					type_name::<U>()
+				)),
+				None => D::Error::custom(format_args!(
+					"unknown tag `{name}` for {}",
+					type_name::<U>()
+				)),
+			});
+		};
+		drop(entries);
+		self.deserialize(tag, payload)
+	}
+	/// Get the explicit name the implementor registered under `tag` was
+	/// registered with, if any.
+	pub fn name(&self, tag: u64) -> Option<&'static str> {
+		submitted();
+		self.entries.read().unwrap().tags.get(&tag)?.name
+	}
+}
+impl<U: ?Sized> Default for TraitRegistry<U> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<U: ?Sized> fmt::Debug for TraitRegistry<U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(
+				self.entries
+					.read()
+					.unwrap()
+					.tags
+					.iter()
+					.map(|(tag, implementor)| (tag, implementor.type_name)),
+			)
+			.finish()
+	}
+}
+
+fn implementor<T, U: ?Sized>(location: &'static Location<'static>) -> Implementor<U>
+where
+	T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+{
+	Implementor {
+		type_id: TypeId::of::<T>(),
+		type_name: type_name::<T>(),
+		location,
+		name: None,
+		deserialize: deserialize::<T, U>,
+	}
+}
+pub(super) fn deserialize<T, U: ?Sized>(
+	deserializer: &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<Box<U>, erased_serde::Error>
+where
+	T: for<'de> Deserialize<'de> + Unsize<U>,
+{
+	let value: Box<T> = Box::new(erased_serde::deserialize(deserializer)?);
+	Ok(value)
+}
+
+/// Levenshtein distance between two strings, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, a) in a.chars().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, &b) in b.iter().enumerate() {
+			let substitution = diagonal + usize::from(a != b);
+			diagonal = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+		}
+	}
+	row[b.len()]
+}
+
+/// Error returned when registering a name that's already registered for a
+/// different type, or for a type whose tag is already registered for a
+/// different type.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DuplicateName {
+	/// The name
+	pub name: &'static str,
+	/// Name of the type the name or tag is already registered for
+	pub existing: &'static str,
+	/// Name of the type that was being registered
+	pub new: &'static str,
+}
+impl fmt::Display for DuplicateName {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"tag `{}` is registered for both {} and {}",
+			self.name, self.existing, self.new
+		)
+	}
+}
+impl Error for DuplicateName {}
+
+/// Implemented on `dyn Trait` by [`register_trait!`](crate::register_trait).
+pub trait RegisteredTrait: 'static {
+	/// The registry of deserializable implementors of this trait.
+	fn registry() -> &'static TraitRegistry<Self>;
+}
+
+/// Set up the registry of deserializable implementors of a trait object type,
+/// enabling [`deserialize_box_dyn`].
+///
+/// Requires the `serde` feature.
+///
+/// ```
+/// # use metatype::{register_trait, registry};
+/// # use serde::Deserialize;
+/// trait Shape {
+///     fn area(&self) -> u64;
+/// }
+/// register_trait!(dyn Shape);
+///
+/// #[derive(Deserialize)]
+/// struct Square(u64);
+/// impl Shape for Square {
+///     fn area(&self) -> u64 {
+///         self.0 * self.0
+///     }
+/// }
+///
+/// let tag = registry::register_deserialize::<Square, dyn Shape>();
+/// let payload = &mut serde_json::Deserializer::from_str("3");
+/// let shape: Box<dyn Shape> = registry::deserialize_box_dyn(tag, payload).unwrap();
+/// assert_eq!(shape.area(), 9);
+/// ```
+#[macro_export]
+macro_rules! register_trait {
+	($trait_object:ty) => {
+		impl $crate::registry::RegisteredTrait for $trait_object {
+			fn registry() -> &'static $crate::registry::TraitRegistry<Self> {
+				static REGISTRY: $crate::registry::TraitRegistry<$trait_object> =
+					$crate::registry::TraitRegistry::new();
+				&REGISTRY
+			}
+		}
+	};
+}
+
+/// Define a `static` [`PerfectTable`](crate::registry::PerfectTable) of
+/// deserializable implementors of a trait, each of which must have a
+/// [`TypeTag`](crate::registry::TypeTag).
+///
+/// Requires the `perfect-hash` feature.
+///
+/// ```
+/// # use metatype::{perfect_table, registry::{self, TypeTag}};
+/// # use serde::Deserialize;
+/// trait Shape {
+///     fn area(&self) -> u64;
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Square(u64);
+/// impl Shape for Square {
+///     fn area(&self) -> u64 {
+///         self.0 * self.0
+///     }
+/// }
+/// impl TypeTag for Square {
+///     const TAG: &'static str = "square";
+/// }
+///
+/// perfect_table!(static SHAPES: dyn Shape = [Square]);
+///
+/// let payload = &mut serde_json::Deserializer::from_str("3");
+/// let shape = SHAPES.deserialize(registry::tag::<Square>(), payload).unwrap();
+/// assert_eq!(shape.area(), 9);
+/// ```
+#[cfg(feature = "perfect-hash")]
+#[macro_export]
+macro_rules! perfect_table {
+	($vis:vis static $name:ident: $trait_object:ty = [$($t:ty),* $(,)?]) => {
+		$vis static $name: $crate::registry::PerfectTable<
+			$trait_object,
+			{ $crate::registry::table_size([$(stringify!($t)),*].len()) },
+		> = $crate::registry::PerfectTable::new([$($crate::registry::Slot::<$trait_object>::of::<$t>()),*]);
+	};
+}
+
+/// Register `T` as a deserializable implementor of `U`, returning the tag it's
+/// registered under.
+#[track_caller]
+pub fn register_deserialize<T, U>() -> u64
+where
+	T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	U: ?Sized + RegisteredTrait,
+{
+	U::registry().register::<T>()
+}
+
+/// Register `T` as a deserializable implementor of `U`, under the explicit
+/// name `name` as well as its tag.
+///
+/// # Errors
+///
+/// Returns an error if `name` is already registered for a different type, or
+/// if a different type is already registered under the tag of `T`.
+#[track_caller]
+pub fn register_deserialize_named<T, U>(name: &'static str) -> Result<u64, DuplicateName>
+where
+	T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	U: ?Sized + RegisteredTrait,
+{
+	U::registry().register_named::<T>(name)
+}
+
+/// Register `T` with [`register_deserialize`] if it's deserializable and `U` has
+/// been set up with [`register_trait!`](crate::register_trait).
+pub(super) trait MaybeDeserialize<U: ?Sized> {
+	fn register();
+}
+impl<T, U: ?Sized> MaybeDeserialize<U> for T {
+	#[track_caller]
+	default fn register() {}
+}
+impl<T, U> MaybeDeserialize<U> for T
+where
+	T: for<'de> Deserialize<'de> + Unsize<U> + 'static,
+	U: ?Sized + RegisteredTrait,
+{
+	#[track_caller]
+	fn register() {
+		let _ = register_deserialize::<T, U>();
+	}
+}
+
+/// Views the value behind a thin pointer as an erased [`Serialize`].
+pub(super) type SerializeFn = unsafe fn(*const ()) -> *const dyn erased_serde::Serialize;
+
+pub(super) trait MaybeSerialize {
+	const SERIALIZE: Option<SerializeFn>;
+}
+impl<T> MaybeSerialize for T {
+	default const SERIALIZE: Option<SerializeFn> = None;
+}
+impl<T: Serialize + 'static> MaybeSerialize for T {
+	const SERIALIZE: Option<SerializeFn> = Some(as_serialize::<T>);
+}
+unsafe fn as_serialize<T: Serialize + 'static>(
+	data: *const (),
+) -> *const dyn erased_serde::Serialize {
+	data.cast::<T>()
+}
+
+/// Serialize a trait object whose concrete type was registered with
+/// [`register`](super::register) and is [`Serialize`]. This serializes the
+/// value alone; its tag can be retrieved with [`tag_of_val`](super::tag_of_val).
+///
+/// # Errors
+///
+/// Returns an error if the concrete type wasn't registered or isn't
+/// [`Serialize`], or if serializing the value fails.
+pub fn serialize_dyn<U, S>(value: &U, serializer: S) -> Result<S::Ok, S::Error>
+where
+	U: ?Sized + Interface,
+	S: Serializer,
+{
+	let meta: TraitObject = type_coerce(Type::meta(value));
+	let serialize = lookup(meta)
+		.and_then(|entry| entry.serialize)
+		.ok_or_else(|| {
+			S::Error::custom(format_args!(
+				"{} value isn't registered as serializable",
+				type_name::<U>()
+			))
+		})?;
+	erased_serde::serialize(unsafe { &*serialize(Type::data(value)) }, serializer)
+}
+
+/// Deserialize a `Box<U>` from `payload`, using the implementor of `U`
+/// registered under `tag`.
+///
+/// # Errors
+///
+/// Returns an error if no implementor is registered under `tag`, or if
+/// deserializing the implementor from `payload` fails.
+pub fn deserialize_box_dyn<'de, U, D>(tag: u64, payload: D) -> Result<Box<U>, D::Error>
+where
+	U: ?Sized + RegisteredTrait,
+	D: Deserializer<'de>,
+{
+	U::registry().deserialize(tag, payload)
+}
+
+/// Deserialize a `Box<U>` from `payload`, using the implementor of `U`
+/// registered under the explicit name `name`.
+///
+/// # Errors
+///
+/// Returns an error if no implementor is registered under `name`, naming the
+/// closest registered name if there's one similar, or if deserializing the
+/// implementor from `payload` fails.
+pub fn deserialize_box_dyn_named<'de, U, D>(name: &str, payload: D) -> Result<Box<U>, D::Error>
+where
+	U: ?Sized + RegisteredTrait,
+	D: Deserializer<'de>,
+{
+	U::registry().deserialize_named(name, payload)
+}
+
+/// A [`DeserializeSeed`] deserializing a `Box<U>` from a `(tag, payload)`
+/// pair, as serialized by [`Tagged`], using the implementors registered in a
+/// [`TraitRegistry`]. With [`by_name`](Self::by_name), it instead deserializes
+/// from a `(name, payload)` pair, as serialized by [`Named`].
+///
+/// This enables trait objects to be deserialized within larger structures,
+/// for example with [`SeqAccess::next_element_seed`].
+pub struct RegistrySeed<'a, U: ?Sized> {
+	registry: &'a TraitRegistry<U>,
+	by_name: bool,
+}
+impl<'a, U: ?Sized> RegistrySeed<'a, U> {
+	/// Create a seed deserializing with the implementors registered in
+	/// `registry`.
+	pub const fn new(registry: &'a TraitRegistry<U>) -> Self {
+		Self {
+			registry,
+			by_name: false,
+		}
+	}
+	/// Identify implementors by their explicit names rather than by their tags.
+	#[must_use]
+	pub const fn by_name(self) -> Self {
+		Self {
+			by_name: true,
+			..self
+		}
+	}
+}
+impl<U: ?Sized + RegisteredTrait> Default for RegistrySeed<'static, U> {
+	fn default() -> Self {
+		Self::new(U::registry())
+	}
+}
+impl<U: ?Sized> Clone for RegistrySeed<'_, U> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<U: ?Sized> Copy for RegistrySeed<'_, U> {}
+impl<U: ?Sized> fmt::Debug for RegistrySeed<'_, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("RegistrySeed")
+			.field("registry", self.registry)
+			.field("by_name", &self.by_name)
+			.finish()
+	}
+}
+impl<'de, U: ?Sized> DeserializeSeed<'de> for RegistrySeed<'_, U> {
+	type Value = Box<U>;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Box<U>, D::Error> {
+		struct TaggedVisitor<'a, U: ?Sized>(RegistrySeed<'a, U>);
+		impl<'de, U: ?Sized> Visitor<'de> for TaggedVisitor<'_, U> {
+			type Value = Box<U>;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				let key = if self.0.by_name { "name" } else { "tag" };
+				write!(f, "a ({key}, payload) pair for {}", type_name::<U>())
+			}
+			fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Box<U>, A::Error> {
+				let key = if self.0.by_name {
+					seq.next_element()?.map(Key::Name)
+				} else {
+					seq.next_element()?.map(Key::Tag)
+				};
+				let key = key.ok_or_else(|| A::Error::invalid_length(0, &self))?;
+				seq.next_element_seed(PayloadSeed(self.0.registry, key))?
+					.ok_or_else(|| A::Error::invalid_length(1, &self))
+			}
+		}
+		enum Key {
+			Tag(u64),
+			Name(String),
+		}
+		struct PayloadSeed<'a, U: ?Sized>(&'a TraitRegistry<U>, Key);
+		impl<'de, U: ?Sized> DeserializeSeed<'de> for PayloadSeed<'_, U> {
+			type Value = Box<U>;
+
+			fn deserialize<D: Deserializer<'de>>(
+				self, deserializer: D,
+			) -> Result<Box<U>, D::Error> {
+				match self.1 {
+					Key::Tag(tag) => self.0.deserialize(tag, deserializer),
+					Key::Name(name) => self.0.deserialize_named(&name, deserializer),
+				}
+			}
+		}
+		deserializer.deserialize_tuple(2, TaggedVisitor(self))
+	}
+}
+
+/// Serializes a trait object whose concrete type was registered with
+/// [`register`](super::register) and is [`Serialize`] as a `(tag, payload)`
+/// pair, for deserialization with [`RegistrySeed`].
+pub struct Tagged<'a, U: ?Sized>(pub &'a U);
+impl<U: ?Sized> fmt::Debug for Tagged<'_, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Tagged").field(&type_name::<U>()).finish()
+	}
+}
+impl<U: ?Sized + Interface> Serialize for Tagged<'_, U> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let meta: TraitObject = type_coerce(Type::meta(self.0));
+		let entry = lookup(meta).ok_or_else(|| {
+			S::Error::custom(format_args!("{} value isn't registered", type_name::<U>()))
+		})?;
+		if is_strict() && !entry.tagged {
+			return Err(S::Error::custom(Untagged {
+				type_name: entry.type_name,
+			}));
+		}
+		let tag = entry.tag;
+		let mut tuple = serializer.serialize_tuple(2)?;
+		tuple.serialize_element(&tag)?;
+		tuple.serialize_element(&Payload(self.0))?;
+		tuple.end()
+	}
+}
+
+/// Serializes a trait object whose concrete type was registered with
+/// [`register`](super::register) and is [`Serialize`] as a `(name, payload)`
+/// pair, for deserialization with [`RegistrySeed::by_name`]. The concrete type
+/// must have been registered with an explicit name.
+pub struct Named<'a, U: ?Sized>(pub &'a U);
+impl<U: ?Sized> fmt::Debug for Named<'_, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Named").field(&type_name::<U>()).finish()
+	}
+}
+impl<U: ?Sized + Interface + RegisteredTrait> Serialize for Named<'_, U> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let name = tag_of_val(self.0)
+			.and_then(|tag| U::registry().name(tag))
+			.ok_or_else(|| {
+				S::Error::custom(format_args!(
+					"{} value isn't registered with a name",
+					type_name::<U>()
+				))
+			})?;
+		let mut tuple = serializer.serialize_tuple(2)?;
+		tuple.serialize_element(name)?;
+		tuple.serialize_element(&Payload(self.0))?;
+		tuple.end()
+	}
+}
+
+struct Payload<'a, U: ?Sized>(&'a U);
+impl<U: ?Sized + Interface> Serialize for Payload<'_, U> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serialize_dyn(self.0, serializer)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		deserialize_box_dyn, deserialize_box_dyn_named, register_deserialize, register_deserialize_named, serialize_dyn, DuplicateName, Named, RegistrySeed, Tagged, TraitRegistry
+	};
+	use crate::{
+		registry::{check_collisions, register, tag_of_val, TypeTag}, type_coerce, Type
+	};
+	use serde::{
+		de::{DeserializeSeed, SeqAccess, Visitor}, Deserialize, Deserializer, Serialize
+	};
+	use std::{fmt, ptr};
+
+	trait Shape {
+		fn area(&self) -> u64;
+	}
+	crate::describe_trait!(Shape { area });
+	register_trait!(dyn Shape);
+
+	#[derive(Serialize, Deserialize)]
+	struct Square(u64);
+	impl Shape for Square {
+		fn area(&self) -> u64 {
+			self.0 * self.0
+		}
+	}
+	#[derive(Serialize, Deserialize)]
+	struct Rectangle {
+		width: u64,
+		height: u64,
+	}
+	impl Shape for Rectangle {
+		fn area(&self) -> u64 {
+			self.width * self.height
+		}
+	}
+	#[derive(Deserialize)]
+	struct Circle(u64);
+	impl Shape for Circle {
+		fn area(&self) -> u64 {
+			self.0 * self.0 * 3
+		}
+	}
+	impl TypeTag for Circle {
+		const TAG: &'static str = "round";
+	}
+	#[derive(Deserialize)]
+	struct Disc(u64);
+	impl Shape for Disc {
+		fn area(&self) -> u64 {
+			self.0 * 3
+		}
+	}
+	impl TypeTag for Disc {
+		const TAG: &'static str = "round";
+	}
+
+	#[test]
+	fn deserialize() {
+		let square = register_deserialize::<Square, dyn Shape>();
+		let rectangle = register_deserialize::<Rectangle, dyn Shape>();
+		assert_ne!(square, rectangle);
+
+		let payload = &mut serde_json::Deserializer::from_str("3");
+		let shape: Box<dyn Shape> = deserialize_box_dyn(square, payload).unwrap();
+		assert_eq!(shape.area(), 9);
+		let payload = &mut serde_json::Deserializer::from_str(r#"{"width":2,"height":5}"#);
+		let shape: Box<dyn Shape> = deserialize_box_dyn(rectangle, payload).unwrap();
+		assert_eq!(shape.area(), 10);
+
+		let payload = &mut serde_json::Deserializer::from_str("3");
+		let err = deserialize_box_dyn::<dyn Shape, _>(0, payload)
+			.err()
+			.unwrap();
+		assert!(err.to_string().starts_with("unknown tag 0x0"));
+	}
+
+	#[test]
+	fn round_trip() {
+		let meta = register::<Square, dyn Shape>();
+		let tag = register_deserialize::<Square, dyn Shape>();
+		let mut square = Square(4);
+		let square = ptr::from_mut(&mut square);
+		let square: &dyn Shape = unsafe { &*Type::fatten(square.cast(), type_coerce(meta)) };
+		assert_eq!(tag_of_val(square), Some(tag));
+
+		let mut payload = Vec::new();
+		serialize_dyn(square, &mut serde_json::Serializer::new(&mut payload)).unwrap();
+		assert_eq!(payload, b"4");
+		let payload = &mut serde_json::Deserializer::from_slice(&payload);
+		let shape: Box<dyn Shape> = deserialize_box_dyn(tag, payload).unwrap();
+		assert_eq!(shape.area(), 16);
+	}
+
+	#[test]
+	fn seed() {
+		struct Shapes<'a>(RegistrySeed<'a, dyn Shape>);
+		impl<'de> Visitor<'de> for Shapes<'_> {
+			type Value = Vec<Box<dyn Shape>>;
+
+			fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str("a sequence of shapes")
+			}
+			fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+				let mut shapes = Vec::new();
+				while let Some(shape) = seq.next_element_seed(self.0)? {
+					shapes.push(shape);
+				}
+				Ok(shapes)
+			}
+		}
+
+		let square = register::<Square, dyn Shape>();
+		let rectangle = register::<Rectangle, dyn Shape>();
+		let _ = register_deserialize::<Square, dyn Shape>();
+		let _ = register_deserialize::<Rectangle, dyn Shape>();
+		let mut shapes = (
+			Square(3),
+			Rectangle {
+				width: 2,
+				height: 5,
+			},
+		);
+		let a = ptr::from_mut(&mut shapes.0);
+		let b = ptr::from_mut(&mut shapes.1);
+		let a: &dyn Shape = unsafe { &*Type::fatten(a.cast(), type_coerce(square)) };
+		let b: &dyn Shape = unsafe { &*Type::fatten(b.cast(), type_coerce(rectangle)) };
+
+		let json = serde_json::to_string(&[Tagged(a), Tagged(b)]).unwrap();
+		let shapes = serde_json::Deserializer::from_str(&json)
+			.deserialize_seq(Shapes(RegistrySeed::default()))
+			.unwrap();
+		let areas: Vec<u64> = shapes.iter().map(|shape| shape.area()).collect();
+		assert_eq!(areas, [9, 10]);
+
+		let registry = TraitRegistry::<dyn Shape>::new();
+		let tag = registry.register::<Square>();
+		let json = format!("[{tag},2]");
+		let seed = RegistrySeed::new(&registry);
+		let shape = seed
+			.deserialize(&mut serde_json::Deserializer::from_str(&json))
+			.unwrap();
+		assert_eq!(shape.area(), 4);
+		let json = format!("[{},2]", tag + 1);
+		assert!(seed
+			.deserialize(&mut serde_json::Deserializer::from_str(&json))
+			.is_err());
+	}
+
+	#[test]
+	fn collisions() {
+		let registry = TraitRegistry::<dyn Shape>::new();
+		let tag = registry.register::<Circle>();
+		assert_eq!(registry.register::<Disc>(), tag);
+		let payload = &mut serde_json::Deserializer::from_str("2");
+		assert_eq!(registry.deserialize(tag, payload).unwrap().area(), 12);
+		let collisions = check_collisions().unwrap_err();
+		let collision = collisions
+			.iter()
+			.find(|collision| collision.tag == tag)
+			.unwrap();
+		assert_eq!(
+			collision.types.map(|(name, _)| name),
+			[
+				std::any::type_name::<Circle>(),
+				std::any::type_name::<Disc>()
+			]
+		);
+		assert!(collision.types[1].1.file().ends_with("de.rs"));
+	}
+
+	#[test]
+	fn named() {
+		let registry = TraitRegistry::<dyn Shape>::new();
+		let tag = registry.register_named::<Square>("Square").unwrap();
+		assert_eq!(registry.register_named::<Square>("Square"), Ok(tag));
+		assert_eq!(
+			registry.register_named::<Rectangle>("Square"),
+			Err(DuplicateName {
+				name: "Square",
+				existing: std::any::type_name::<Square>(),
+				new: std::any::type_name::<Rectangle>(),
+			})
+		);
+		let _ = registry.register_named::<Rectangle>("Rectangle").unwrap();
+		assert_eq!(registry.name(tag), Some("Square"));
+
+		let round = registry.register_named::<Circle>("Round").unwrap();
+		assert_eq!(
+			registry.register_named::<Disc>("Disc"),
+			Err(DuplicateName {
+				name: "Disc",
+				existing: std::any::type_name::<Circle>(),
+				new: std::any::type_name::<Disc>(),
+			})
+		);
+		assert_eq!(registry.name(round), Some("Round"));
+		let payload = &mut serde_json::Deserializer::from_str("2");
+		assert!(registry.deserialize_named("Disc", payload).is_err());
+
+		let payload = &mut serde_json::Deserializer::from_str("3");
+		let shape = registry.deserialize_named("Square", payload).unwrap();
+		assert_eq!(shape.area(), 9);
+		let payload = &mut serde_json::Deserializer::from_str("3");
+		let err = registry.deserialize_named("Squar", payload).err().unwrap();
+		assert_eq!(
+			err.to_string(),
+			format!(
+				"unknown tag `Squar` for {}, did you mean `Square`?",
+				std::any::type_name::<dyn Shape>()
+			)
+		);
+		let payload = &mut serde_json::Deserializer::from_str("3");
+		let err = registry.deserialize_named("Circle", payload).err().unwrap();
+		assert!(!err.to_string().contains("did you mean"));
+
+		let meta = register::<Rectangle, dyn Shape>();
+		let _ = register_deserialize_named::<Rectangle, dyn Shape>("Rectangle").unwrap();
+		let mut rectangle = Rectangle {
+			width: 3,
+			height: 4,
+		};
+		let rectangle = ptr::from_mut(&mut rectangle);
+		let rectangle: &dyn Shape = unsafe { &*Type::fatten(rectangle.cast(), type_coerce(meta)) };
+		let json = serde_json::to_string(&Named(rectangle)).unwrap();
+		assert_eq!(json, r#"["Rectangle",{"width":3,"height":4}]"#);
+		let shape = RegistrySeed::<dyn Shape>::default()
+			.by_name()
+			.deserialize(&mut serde_json::Deserializer::from_str(&json))
+			.unwrap();
+		assert_eq!(shape.area(), 12);
+		let payload = &mut serde_json::Deserializer::from_str(r#"{"width":1,"height":2}"#);
+		let shape: Box<dyn Shape> = deserialize_box_dyn_named("Rectangle", payload).unwrap();
+		assert_eq!(shape.area(), 2);
+	}
+}
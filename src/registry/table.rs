@@ -0,0 +1,242 @@
+//! Perfect-hash tables of deserializable implementors, built at compile time
+//! and enabled by the `perfect-hash` feature.
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{
+	any::type_name, fmt, marker::Unsize, ptr::{self, DynMetadata, Pointee}
+};
+
+use super::{de::DeserializeFn, fnv1a, TypeTag, FNV_OFFSET};
+use crate::{transmute_coerce, TraitObject};
+
+/// A table of deserializable implementors of `U` keyed by their
+/// [`tag`](super::tag), built in a `static` by
+/// [`perfect_table!`](crate::perfect_table).
+///
+/// The table is built when compiling, with a perfect hash of the tags, so a
+/// lookup is a single probe without hashing, locking or lazy initialization.
+pub struct PerfectTable<U: ?Sized, const N: usize> {
+	seed: u64,
+	slots: [Option<Slot<U>>; N],
+}
+/// An implementor in a [`PerfectTable`].
+#[doc(hidden)]
+pub struct Slot<U: ?Sized> {
+	tag: u64,
+	name: &'static str,
+	meta: DynMetadata<U>,
+	deserialize: DeserializeFn<U>,
+}
+impl<U: ?Sized> Clone for Slot<U> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<U: ?Sized> Copy for Slot<U> {}
+impl<U: ?Sized> fmt::Debug for Slot<U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Slot")
+			.field("tag", &self.tag)
+			.field("name", &self.name)
+			.finish_non_exhaustive()
+	}
+}
+impl<U: ?Sized + Pointee<Metadata = DynMetadata<U>>> Slot<U> {
+	#[doc(hidden)]
+	pub const fn of<T>() -> Self
+	where
+		T: TypeTag + for<'de> Deserialize<'de> + Unsize<U>,
+	{
+		let ptr: *const U = ptr::null::<T>();
+		Self {
+			tag: fnv1a(FNV_OFFSET, T::TAG.as_bytes()),
+			name: T::TAG,
+			meta: ptr::metadata(ptr),
+			deserialize: super::de::deserialize::<T, U>,
+		}
+	}
+}
+
+/// The number of slots in a table of `len` implementors.
+#[doc(hidden)]
+pub const fn table_size(len: usize) -> usize {
+	(len * 2).next_power_of_two()
+}
+
+#[inline]
+#[allow(clippy::cast_possible_truncation)] // the result is less than `len`
+const fn index(tag: u64, seed: u64, len: usize) -> usize {
+	let bits = len.trailing_zeros();
+	if bits == 0 {
+		0
+	} else {
+		(tag.wrapping_mul(seed) >> (64 - bits)) as usize
+	}
+}
+
+impl<U: ?Sized, const N: usize> PerfectTable<U, N> {
+	/// Build the table, failing to compile if two implementors share a tag.
+	#[doc(hidden)]
+	pub const fn new<const M: usize>(slots: [Slot<U>; M]) -> Self {
+		let mut i = 0;
+		while i < M {
+			let mut j = i + 1;
+			while j < M {
+				assert!(
+					slots[i].tag != slots[j].tag,
+					"two implementors share a tag; give one of them a distinct TypeTag"
+				);
+				j += 1;
+			}
+			i += 1;
+		}
+		let mut seed: u64 = 1;
+		let mut attempts = 0;
+		loop {
+			let mut table = Self {
+				seed,
+				slots: [None; N],
+			};
+			let mut i = 0;
+			while i < M {
+				let index = index(slots[i].tag, seed, N);
+				if table.slots[index].is_some() {
+					break;
+				}
+				table.slots[index] = Some(slots[i]);
+				i += 1;
+			}
+			if i == M {
+				return table;
+			}
+			attempts += 1;
+			assert!(
+				attempts < 1 << 16,
+				"couldn't find a perfect hash for the tags"
+			);
+			seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15 << 1);
+		}
+	}
+	/// The number of implementors in the table.
+	pub fn len(&self) -> usize {
+		self.slots.iter().filter(|slot| slot.is_some()).count()
+	}
+	/// Whether the table has no implementors.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+	#[inline]
+	fn get(&self, tag: u64) -> Option<&Slot<U>> {
+		let slot = self.slots.get(index(tag, self.seed, N))?.as_ref()?;
+		(slot.tag == tag).then_some(slot)
+	}
+	/// Get the vtable of the implementor registered under `tag`, if any.
+	#[inline]
+	pub fn vtable(&self, tag: u64) -> Option<TraitObject> {
+		let meta = self.get(tag)?.meta;
+		Some(TraitObject {
+			vtable: unsafe { transmute_coerce(meta) },
+		})
+	}
+	/// Deserialize a `Box<U>` from `payload`, using the implementor registered
+	/// under `tag`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no implementor is registered under `tag`, or if
+	/// deserializing the implementor from `payload` fails.
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		&self, tag: u64, payload: D,
+	) -> Result<Box<U>, D::Error> {
+		let slot = self.get(tag).ok_or_else(|| {
+			D::Error::custom(format_args!(
+				"unknown tag {tag:#x} for {}",
+				type_name::<U>()
+			))
+		})?;
+		(slot.deserialize)(&mut <dyn erased_serde::Deserializer>::erase(payload))
+			.map_err(D::Error::custom)
+	}
+}
+impl<U: ?Sized, const N: usize> fmt::Debug for PerfectTable<U, N> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(
+				self.slots
+					.iter()
+					.flatten()
+					.map(|slot| (slot.tag, slot.name)),
+			)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		registry::{tag, TypeTag}, type_coerce, Type
+	};
+	use serde::Deserialize;
+	use std::ptr;
+
+	trait Shape {
+		fn area(&self) -> u64;
+	}
+	#[derive(Deserialize)]
+	struct Square(u64);
+	impl Shape for Square {
+		fn area(&self) -> u64 {
+			self.0 * self.0
+		}
+	}
+	impl TypeTag for Square {
+		const TAG: &'static str = "square";
+	}
+	#[derive(Deserialize)]
+	struct Circle(u64);
+	impl Shape for Circle {
+		fn area(&self) -> u64 {
+			self.0 * self.0 * 3
+		}
+	}
+	impl TypeTag for Circle {
+		const TAG: &'static str = "circle";
+	}
+	crate::perfect_table!(static SHAPES: dyn Shape = [Square, Circle]);
+
+	macro_rules! shapes {
+		($($name:ident),*) => {
+			$(
+				#[derive(Deserialize)]
+				struct $name;
+				impl Shape for $name {
+					fn area(&self) -> u64 {
+						0
+					}
+				}
+				impl TypeTag for $name {
+					const TAG: &'static str = stringify!($name);
+				}
+			)*
+			crate::perfect_table!(static LARGE: dyn Shape = [$($name),*]);
+		};
+	}
+	shapes!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+	#[test]
+	fn perfect_table() {
+		assert_eq!(SHAPES.len(), 2);
+		assert_eq!(LARGE.len(), 16);
+		let payload = &mut serde_json::Deserializer::from_str("2");
+		let circle = SHAPES.deserialize(tag::<Circle>(), payload).unwrap();
+		assert_eq!(circle.area(), 12);
+		let payload = &mut serde_json::Deserializer::from_str("2");
+		assert!(SHAPES.deserialize(0, payload).is_err());
+		let meta = SHAPES.vtable(tag::<Square>()).unwrap();
+		let square = Square(3);
+		let square: *mut dyn Shape =
+			Type::fatten(ptr::from_ref(&square).cast_mut().cast(), type_coerce(meta));
+		assert_eq!(unsafe { &*square }.area(), 9);
+		assert_eq!(SHAPES.vtable(0), None);
+	}
+}
@@ -0,0 +1,160 @@
+//! References with their lifetime erased to `'static`, that can only be
+//! re-borrowed while the scope they were created in is running.
+//!
+//! Within [`scope`], [`Scope::erase`] turns a `&'env T` into a
+//! [`ScopedRef<T>`], which is `'static` and so can be stored in type-erased
+//! containers such as `Box<dyn Any>`. Getting the reference back requires
+//! presenting the same [`Scope`], which only exists for the duration of the
+//! scope, so the reference can't outlive the data it points to.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::scoped::{scope, ScopedRef};
+//! use std::any::Any;
+//!
+//! let data = String::from("abc");
+//! scope(|scope| {
+//!     let erased: Box<dyn Any> = Box::new(scope.erase(&data));
+//!     let erased = erased.downcast_ref::<ScopedRef<String>>().unwrap();
+//!     assert_eq!(erased.get(scope).unwrap(), "abc");
+//! });
+//! ```
+
+use core::{
+	fmt, marker::PhantomData, ptr::NonNull, sync::atomic::{AtomicU64, Ordering}
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Run `f` with a new [`Scope`], within which references to data borrowed for
+/// `'env` can be erased.
+pub fn scope<'env, R>(f: impl for<'s> FnOnce(&'s Scope<'env>) -> R) -> R {
+	let scope = Scope {
+		id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+		marker: PhantomData,
+	};
+	f(&scope)
+}
+
+/// A token proving that a [`scope`] is running, created by [`scope`].
+pub struct Scope<'env> {
+	id: u64,
+	marker: PhantomData<&'env mut &'env ()>,
+}
+impl<'env> Scope<'env> {
+	/// Erase the lifetime of `value`.
+	pub fn erase<T: ?Sized>(&self, value: &'env T) -> ScopedRef<T> {
+		ScopedRef {
+			ptr: NonNull::from(value),
+			scope: self.id,
+		}
+	}
+	/// Erase the lifetime of `value`.
+	pub fn erase_mut<T: ?Sized>(&self, value: &'env mut T) -> ScopedMut<T> {
+		ScopedMut {
+			ptr: NonNull::from(value),
+			scope: self.id,
+		}
+	}
+}
+impl fmt::Debug for Scope<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Scope").field("id", &self.id).finish()
+	}
+}
+
+/// A shared reference with its lifetime erased, created by [`Scope::erase`].
+pub struct ScopedRef<T: ?Sized> {
+	ptr: NonNull<T>,
+	scope: u64,
+}
+impl<T: ?Sized> ScopedRef<T> {
+	/// Re-borrow the reference, or `None` if `scope` isn't the scope it was
+	/// created in.
+	pub fn get<'a>(&'a self, scope: &'a Scope<'_>) -> Option<&'a T> {
+		if scope.id == self.scope {
+			Some(unsafe { self.ptr.as_ref() })
+		} else {
+			None
+		}
+	}
+}
+impl<T: ?Sized> Copy for ScopedRef<T> {}
+impl<T: ?Sized> Clone for ScopedRef<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+unsafe impl<T: ?Sized + Sync> Send for ScopedRef<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for ScopedRef<T> {}
+impl<T: ?Sized> fmt::Debug for ScopedRef<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ScopedRef")
+			.field("ptr", &self.ptr.cast::<()>())
+			.field("scope", &self.scope)
+			.finish()
+	}
+}
+
+/// A mutable reference with its lifetime erased, created by
+/// [`Scope::erase_mut`].
+pub struct ScopedMut<T: ?Sized> {
+	ptr: NonNull<T>,
+	scope: u64,
+}
+impl<T: ?Sized> ScopedMut<T> {
+	/// Re-borrow the reference, or `None` if `scope` isn't the scope it was
+	/// created in.
+	pub fn get<'a>(&'a self, scope: &'a Scope<'_>) -> Option<&'a T> {
+		if scope.id == self.scope {
+			Some(unsafe { self.ptr.as_ref() })
+		} else {
+			None
+		}
+	}
+	/// Mutably re-borrow the reference, or `None` if `scope` isn't the scope
+	/// it was created in.
+	pub fn get_mut<'a>(&'a mut self, scope: &'a Scope<'_>) -> Option<&'a mut T> {
+		if scope.id == self.scope {
+			Some(unsafe { self.ptr.as_mut() })
+		} else {
+			None
+		}
+	}
+}
+unsafe impl<T: ?Sized + Send> Send for ScopedMut<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for ScopedMut<T> {}
+impl<T: ?Sized> fmt::Debug for ScopedMut<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ScopedMut")
+			.field("ptr", &self.ptr.cast::<()>())
+			.field("scope", &self.scope)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{scope, ScopedRef};
+	use std::fmt::Debug;
+
+	#[test]
+	fn scoped() {
+		let a = String::from("a");
+		let mut b = vec![1];
+		let mut escaped: Option<ScopedRef<dyn Debug>> = None;
+		scope(|outer| {
+			let erased = outer.erase::<dyn Debug>(&a);
+			let mut erased_mut = outer.erase_mut(&mut b);
+			erased_mut.get_mut(outer).unwrap().push(2);
+			scope(|inner| {
+				assert!(erased.get(inner).is_none());
+				assert_eq!(format!("{:?}", erased.get(outer).unwrap()), "\"a\"");
+			});
+			escaped = Some(erased);
+		});
+		assert_eq!(b, [1, 2]);
+		scope(|other| assert!(escaped.unwrap().get(other).is_none()));
+	}
+}
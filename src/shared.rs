@@ -0,0 +1,155 @@
+//! Construction of shared unsized pointers, `Rc<T>` and `Arc<T>`, without an
+//! intermediate `Box`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::shared;
+//! use std::sync::Arc;
+//!
+//! let a: Arc<[u32]> = shared::arc_from_iter((0..4).map(|x| x * x));
+//! assert_eq!(&*a, [0, 1, 4, 9]);
+//! ```
+//!
+//! # Identity
+//!
+//! Comparing fat pointers with `==` or [`ptr::eq`] compares their metadata as
+//! well as their address. The vtable of a `dyn Trait` isn't unique: it is
+//! duplicated across codegen units and crates, so two pointers to the same
+//! value can carry different vtables (a false negative), and conversely
+//! distinct zero-sized values can share an address. [`arc_data_ptr_eq`] and
+//! [`rc_data_ptr_eq`] compare only the data address, which is what identity
+//! caches keyed by shared trait objects want.
+
+use super::Type;
+use alloc::{rc::Rc, sync::Arc};
+use core::{mem::MaybeUninit, ptr};
+
+macro_rules! shared {
+	(
+		$rc:ident, $from_iter:ident, $from_raw_parts:ident, $str_from_utf8_unchecked:ident,
+		$data_ptr_eq:ident
+	) => {
+		#[doc = concat!("Collect an exact-size iterator directly into an `", stringify!($rc), "<[T]>`.")]
+		///
+		/// Panics if the iterator yields fewer items than its reported length;
+		/// any beyond it are ignored.
+		pub fn $from_iter<I>(iter: I) -> $rc<[I::Item]>
+		where
+			I: IntoIterator,
+			I::IntoIter: ExactSizeIterator,
+		{
+			let iter = iter.into_iter();
+			let len = iter.len();
+			let mut slice = $rc::<[I::Item]>::new_uninit_slice(len);
+			let uninit = $rc::get_mut(&mut slice).unwrap();
+			let mut written = 0;
+			for item in iter.take(len) {
+				let _ = uninit[written].write(item);
+				written += 1;
+			}
+			if written != len {
+				unsafe { drop_init(&mut uninit[..written]) };
+				panic!("iterator yielded fewer items than its reported length");
+			}
+			unsafe { slice.assume_init() }
+		}
+
+		#[doc = concat!("Reconstruct an `", stringify!($rc), "<T>` from its data pointer and `T::Meta`.")]
+		///
+		/// # Safety
+		///
+		#[doc = concat!("`thin` and `t` must be the parts of a pointer returned by [`", stringify!($rc), "::into_raw`].")]
+		pub unsafe fn $from_raw_parts<T: ?Sized + Type>(thin: *const (), t: T::Meta) -> $rc<T> {
+			$rc::from_raw(T::fatten_const(thin, t))
+		}
+
+		#[doc = concat!("Convert an `", stringify!($rc), "<[u8]>` into an `", stringify!($rc), "<str>` without copying or checking that it is valid UTF-8.")]
+		///
+		/// # Safety
+		///
+		/// `bytes` must be valid UTF-8.
+		pub unsafe fn $str_from_utf8_unchecked(bytes: $rc<[u8]>) -> $rc<str> {
+			let meta = Type::meta(&*bytes);
+			$from_raw_parts::<str>($rc::into_raw(bytes).cast(), meta)
+		}
+
+		#[doc = concat!("Whether two `", stringify!($rc), "`s point to the same allocation, ignoring metadata such as vtables.")]
+		pub fn $data_ptr_eq<T: ?Sized, U: ?Sized>(a: &$rc<T>, b: &$rc<U>) -> bool {
+			$rc::as_ptr(a).cast::<()>() == $rc::as_ptr(b).cast::<()>()
+		}
+	};
+}
+shared!(
+	Rc,
+	rc_from_iter,
+	rc_from_raw_parts,
+	rc_str_from_utf8_unchecked,
+	rc_data_ptr_eq
+);
+shared!(
+	Arc,
+	arc_from_iter,
+	arc_from_raw_parts,
+	arc_str_from_utf8_unchecked,
+	arc_data_ptr_eq
+);
+
+unsafe fn drop_init<T>(init: &mut [MaybeUninit<T>]) {
+	let init: *mut [MaybeUninit<T>] = init;
+	ptr::drop_in_place(init as *mut [T]);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		arc_data_ptr_eq, arc_from_iter, arc_from_raw_parts, rc_data_ptr_eq, rc_from_iter, rc_str_from_utf8_unchecked
+	};
+	use crate::{type_coerce, TraitObject, Type};
+	use std::{fmt::Debug, panic, rc::Rc, sync::Arc};
+
+	#[test]
+	fn from_iter() {
+		struct Liar(usize);
+		impl Iterator for Liar {
+			type Item = String;
+			fn next(&mut self) -> Option<String> {
+				self.0 = self.0.checked_sub(1)?;
+				Some(self.0.to_string())
+			}
+		}
+		impl ExactSizeIterator for Liar {
+			fn len(&self) -> usize {
+				self.0 + 1
+			}
+		}
+		let a = rc_from_iter(vec![String::from("a"), String::from("b")]);
+		assert_eq!(&*a, ["a", "b"]);
+		let a: Rc<str> = unsafe { rc_str_from_utf8_unchecked(rc_from_iter(*b"abc")) };
+		assert_eq!(&*a, "abc");
+
+		assert!(panic::catch_unwind(|| arc_from_iter(Liar(2))).is_err());
+	}
+
+	#[test]
+	fn from_raw_parts() {
+		let a: Arc<dyn Debug> = Arc::new(123_u8);
+		let meta: TraitObject = type_coerce(Type::meta(Arc::as_ptr(&a)));
+		let thin = Arc::into_raw(a).cast::<()>();
+		let a: Arc<dyn Debug> = unsafe { arc_from_raw_parts(thin, type_coerce(meta)) };
+		assert_eq!(format!("{a:?}"), "123");
+	}
+
+	#[test]
+	fn data_ptr_eq() {
+		let a: Arc<String> = Arc::new(String::new());
+		let b: Arc<dyn Debug> = a.clone();
+		let c: Arc<dyn Debug + Send> = a.clone();
+		assert!(arc_data_ptr_eq(&b, &c));
+		assert!(arc_data_ptr_eq(&a, &b));
+		assert!(!arc_data_ptr_eq(&a, &Arc::new(String::new())));
+		let a: Rc<[u8]> = Rc::new([1, 2, 3]);
+		let b: Rc<[u8]> = Rc::from(&a[..]);
+		assert!(rc_data_ptr_eq(&a, &a.clone()) && !rc_data_ptr_eq(&a, &b));
+	}
+}
@@ -0,0 +1,194 @@
+//! Structure-of-arrays storage for trait objects.
+//!
+//! [`DynGroups`] stores the thin data pointers of many `&dyn Trait` grouped by
+//! vtable, with each vtable stored once per group, reconstituting the fat
+//! references on iteration. Iterating touches half the pointer data of a
+//! `Vec<&dyn Trait>`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::soa::DynGroups;
+//! use std::fmt::Display;
+//!
+//! let (a, b, c) = (1_u8, "two", 3_u8);
+//! let mut groups = DynGroups::<dyn Display>::new();
+//! groups.push(&a);
+//! groups.push(&b);
+//! groups.push(&c);
+//! assert!(groups.group_count() >= 2);
+//! let mut all: Vec<String> = groups.iter().map(|x| x.to_string()).collect();
+//! all.sort();
+//! assert_eq!(all, ["1", "3", "two"]);
+//! ```
+
+use alloc::vec::Vec;
+use core::{
+	fmt, iter, marker::PhantomData, ptr::{self, Pointee}, slice
+};
+
+/// Borrowed unsized values of type `U`, stored as thin pointers grouped by
+/// metadata.
+///
+/// Values are iterated group by group, in order of insertion within each
+/// group. As vtables can be duplicated across codegen units, values of the
+/// same concrete type may fall into more than one group.
+pub struct DynGroups<'a, U: ?Sized> {
+	groups: Vec<Group<U>>,
+	len: usize,
+	marker: PhantomData<&'a U>,
+}
+struct Group<U: ?Sized> {
+	meta: <U as Pointee>::Metadata,
+	data: Vec<*const ()>,
+}
+impl<'a, U: ?Sized> DynGroups<'a, U> {
+	/// Create an empty `DynGroups`.
+	pub const fn new() -> Self {
+		Self {
+			groups: Vec::new(),
+			len: 0,
+			marker: PhantomData,
+		}
+	}
+	/// Append a value to the group sharing its metadata.
+	pub fn push(&mut self, value: &'a U) {
+		let value: *const U = value;
+		let (data, meta) = value.to_raw_parts();
+		let group = if let Some(group) = self.groups.iter_mut().position(|group| group.meta == meta)
+		{
+			group
+		} else {
+			self.groups.push(Group {
+				meta,
+				data: Vec::new(),
+			});
+			self.groups.len() - 1
+		};
+		self.groups[group].data.push(data);
+		self.len += 1;
+	}
+	/// The number of values.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	/// Whether there are no values.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The number of distinct metadata groups.
+	pub fn group_count(&self) -> usize {
+		self.groups.len()
+	}
+	/// Remove all values.
+	pub fn clear(&mut self) {
+		self.groups.clear();
+		self.len = 0;
+	}
+	/// Iterate over the values, group by group.
+	pub fn iter(&self) -> Iter<'a, '_, U> {
+		Iter {
+			groups: self.groups.iter(),
+			group: None,
+			marker: PhantomData,
+		}
+	}
+	/// Iterate over the groups, each yielding its values.
+	pub fn groups(&self) -> impl Iterator<Item = impl Iterator<Item = &'a U> + '_> + '_ {
+		self.groups.iter().map(|group| {
+			group
+				.data
+				.iter()
+				.map(move |&data| unsafe { &*ptr::from_raw_parts(data, group.meta) })
+		})
+	}
+}
+impl<U: ?Sized> Default for DynGroups<'_, U> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<'a, U: ?Sized> Extend<&'a U> for DynGroups<'a, U> {
+	fn extend<I: IntoIterator<Item = &'a U>>(&mut self, iter: I) {
+		for value in iter {
+			self.push(value);
+		}
+	}
+}
+impl<'a, U: ?Sized> iter::FromIterator<&'a U> for DynGroups<'a, U> {
+	fn from_iter<I: IntoIterator<Item = &'a U>>(iter: I) -> Self {
+		let mut groups = Self::new();
+		groups.extend(iter);
+		groups
+	}
+}
+impl<'a, 'b, U: ?Sized> IntoIterator for &'b DynGroups<'a, U> {
+	type IntoIter = Iter<'a, 'b, U>;
+	type Item = &'a U;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+impl<U: ?Sized> fmt::Debug for DynGroups<'_, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("DynGroups")
+			.field("len", &self.len)
+			.field("groups", &self.groups.len())
+			.finish()
+	}
+}
+
+/// Iterator over the values of a [`DynGroups`].
+pub struct Iter<'a, 'b, U: ?Sized> {
+	groups: slice::Iter<'b, Group<U>>,
+	group: Option<(<U as Pointee>::Metadata, slice::Iter<'b, *const ()>)>,
+	marker: PhantomData<&'a U>,
+}
+impl<'a, U: ?Sized> Iterator for Iter<'a, '_, U> {
+	type Item = &'a U;
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a U> {
+		loop {
+			if let Some((meta, data)) = &mut self.group {
+				if let Some(&data) = data.next() {
+					return Some(unsafe { &*ptr::from_raw_parts(data, *meta) });
+				}
+			}
+			let group = self.groups.next()?;
+			self.group = Some((group.meta, group.data.iter()));
+		}
+	}
+}
+impl<U: ?Sized> fmt::Debug for Iter<'_, '_, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Iter").finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DynGroups;
+	use std::fmt::Debug;
+
+	#[test]
+	fn dyn_groups() {
+		let values: Vec<Box<dyn Debug>> = vec![
+			Box::new(1_u8),
+			Box::new("a"),
+			Box::new(2_u8),
+			Box::new(String::from("b")),
+		];
+		let groups: DynGroups<dyn Debug> = values.iter().map(|x| &**x).collect();
+		// Duplicate vtables, as under Miri, can split a type across groups.
+		assert_eq!(groups.len(), 4);
+		assert!(groups.group_count() >= 3);
+		let mut all: Vec<String> = groups.iter().map(|x| format!("{x:?}")).collect();
+		all.sort();
+		assert_eq!(all, ["\"a\"", "\"b\"", "1", "2"]);
+		let sizes: Vec<usize> = groups.groups().map(Iterator::count).collect();
+		assert_eq!((sizes.len(), sizes.iter().sum()), (groups.group_count(), 4));
+		assert!(!sizes.contains(&0));
+	}
+}
@@ -0,0 +1,150 @@
+//! Stack-allocated storage for unsized values.
+//!
+//! [`StackDyn`] holds a value inline in caller-chosen space, and derefs to it
+//! as an unsized type such as `dyn Trait`, without heap allocation.
+//!
+//! # Examples
+//!
+//! ```
+//! # use metatype::stack::StackDyn;
+//! use std::fmt::Debug;
+//!
+//! let a = StackDyn::<[usize; 4], dyn Debug>::new(String::from("abc")).unwrap();
+//! assert_eq!(format!("{:?}", &*a), "\"abc\"");
+//! ```
+
+use core::{
+	fmt, marker::{PhantomData, Unsize}, mem::{align_of, size_of, MaybeUninit}, ops::{Deref, DerefMut}, pin::Pin, ptr::{self, Pointee}
+};
+
+/// A value of unsized type `U`, stored inline in space of type `S`.
+///
+/// `S` determines the size and alignment available; a value fits if it is no
+/// larger and no more aligned than `S`.
+pub struct StackDyn<S, U: ?Sized> {
+	space: MaybeUninit<S>,
+	meta: <U as Pointee>::Metadata,
+	marker: PhantomData<U>,
+}
+impl<S, U: ?Sized> StackDyn<S, U> {
+	/// Store `value` inline, or return it if it doesn't fit in `S`.
+	///
+	/// # Errors
+	///
+	/// Returns `value` if it is larger or more aligned than `S`.
+	pub fn new<T: Unsize<U>>(value: T) -> Result<Self, T> {
+		if size_of::<T>() > size_of::<S>() || align_of::<T>() > align_of::<S>() {
+			return Err(value);
+		}
+		let unsized_: &U = &value;
+		let meta = ptr::metadata(unsized_);
+		let mut space = MaybeUninit::<S>::uninit();
+		unsafe { space.as_mut_ptr().cast::<T>().write(value) };
+		Ok(Self {
+			space,
+			meta,
+			marker: PhantomData,
+		})
+	}
+	/// Store `value` inline, using exactly its own size and alignment.
+	pub fn of(value: S) -> Self
+	where
+		S: Unsize<U>,
+	{
+		Self::new(value).unwrap_or_else(|_| unreachable!())
+	}
+	/// Get a pinned reference to the stored value.
+	pub fn as_pin_mut(self: Pin<&mut Self>) -> Pin<&mut U> {
+		unsafe { self.map_unchecked_mut(|this| &mut **this) }
+	}
+	#[inline]
+	fn as_ptr(&self) -> *const U {
+		ptr::from_raw_parts(self.space.as_ptr().cast::<()>(), self.meta)
+	}
+	#[inline]
+	fn as_mut_ptr(&mut self) -> *mut U {
+		ptr::from_raw_parts_mut(self.space.as_mut_ptr().cast::<()>(), self.meta)
+	}
+}
+impl<S, U: ?Sized> Deref for StackDyn<S, U> {
+	type Target = U;
+
+	#[inline]
+	fn deref(&self) -> &U {
+		unsafe { &*self.as_ptr() }
+	}
+}
+impl<S, U: ?Sized> DerefMut for StackDyn<S, U> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut U {
+		unsafe { &mut *self.as_mut_ptr() }
+	}
+}
+impl<S, U: ?Sized> Drop for StackDyn<S, U> {
+	fn drop(&mut self) {
+		unsafe { ptr::drop_in_place(self.as_mut_ptr()) }
+	}
+}
+impl<S, U: ?Sized + fmt::Debug> fmt::Debug for StackDyn<S, U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).fmt(f)
+	}
+}
+
+/// Place a value on the stack, binding a pinned reference to it as an unsized
+/// type.
+///
+/// ```
+/// # use metatype::stack_dyn;
+/// use std::future::Future;
+///
+/// stack_dyn!(let fut: dyn Future<Output = u32> = async { 123 });
+/// let _: std::pin::Pin<&mut dyn Future<Output = u32>> = fut;
+/// ```
+#[macro_export]
+macro_rules! stack_dyn {
+	(let $name:ident: $u:ty = $value:expr) => {
+		let stack = ::std::pin::pin!($crate::stack::StackDyn::<_, $u>::of($value));
+		let $name: ::std::pin::Pin<&mut $u> = $crate::stack::StackDyn::as_pin_mut(stack);
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::StackDyn;
+	use std::{any::Any, cell::Cell, fmt::Debug, rc::Rc};
+
+	#[test]
+	fn stack_dyn() {
+		struct Guard(Rc<Cell<usize>>);
+		impl Drop for Guard {
+			fn drop(&mut self) {
+				self.0.set(self.0.get() + 1);
+			}
+		}
+		let drops = Rc::new(Cell::new(0));
+		let a = StackDyn::<[usize; 2], dyn Any>::new(Guard(drops.clone()));
+		drop(a);
+		assert_eq!(drops.get(), 1);
+
+		let mut a = StackDyn::<[u64; 1], dyn FnMut() -> u64>::new({
+			let mut x = 0;
+			move || {
+				x += 1;
+				x
+			}
+		})
+		.unwrap_or_else(|_| panic!());
+		assert_eq!((a(), a()), (1, 2));
+
+		assert!(StackDyn::<[u8; 8], dyn Debug>::new([0_u8; 9]).is_err());
+		assert!(StackDyn::<[u8; 8], dyn Debug>::new(0_u64).is_err());
+		assert_eq!(
+			format!("{:?}", StackDyn::<u64, dyn Debug>::new(1_u32).unwrap()),
+			"1"
+		);
+
+		crate::stack_dyn!(let b: dyn Debug = String::from("abc"));
+		assert_eq!(format!("{b:?}"), "\"abc\"");
+	}
+}
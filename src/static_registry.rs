@@ -0,0 +1,247 @@
+//! A registry of trait object implementors in a sorted `static` slice, built
+//! at compile time by [`static_registry!`](crate::static_registry) and
+//! enabled by the `static-registry` feature.
+//!
+//! Unlike [`registry`](crate::registry) this needs neither `std` nor a heap,
+//! nor any initialization at run time, so is usable on bare-metal targets. A
+//! lookup is a binary search of the implementors by [`tag`], which is that
+//! given by [`registry::tag`](crate::registry::tag), so tags serialized by a
+//! `std` host resolve here. The vtable found gives the size and alignment of
+//! the value to read into storage, and fattens a pointer to it with
+//! [`Type::fatten`](crate::Type::fatten).
+//!
+//! ```
+//! # use metatype::{static_registry, static_registry::tag, type_coerce, Type, TypeTag};
+//! trait Shape {
+//!     fn area(&self) -> u64;
+//! }
+//!
+//! struct Square(u64);
+//! impl Shape for Square {
+//!     fn area(&self) -> u64 {
+//!         self.0 * self.0
+//!     }
+//! }
+//! impl TypeTag for Square {
+//!     const TAG: &'static str = "square";
+//! }
+//!
+//! static_registry!(static SHAPES: dyn Shape = [Square]);
+//!
+//! let meta = SHAPES.vtable(tag::<Square>()).unwrap();
+//! assert_eq!(meta.size_of(), 8);
+//! let mut square = Square(3);
+//! let square: *mut dyn Shape =
+//!     Type::fatten(std::ptr::from_mut(&mut square).cast(), type_coerce(meta));
+//! assert_eq!(unsafe { &*square }.area(), 9);
+//! ```
+
+use core::{
+	fmt, marker::Unsize, ptr::{self, DynMetadata, Pointee}
+};
+
+use super::{transmute_coerce, type_tag, TraitObject};
+
+/// Get the tag identifying `T`, derived from its [`TypeTag`](crate::TypeTag)
+/// if it has one, and otherwise its [`stable_type_id`](crate::stable_type_id).
+///
+/// This is the same as [`registry::tag`](crate::registry::tag), and is usable
+/// in const contexts.
+#[must_use]
+pub const fn tag<T: ?Sized>() -> u64 {
+	type_tag::<T>()
+}
+
+/// The implementors of `U`, sorted by [`tag`], built in a `static` by
+/// [`static_registry!`](crate::static_registry).
+pub struct StaticRegistry<U: ?Sized + 'static> {
+	entries: &'static [Entry<U>],
+}
+/// An implementor in a [`StaticRegistry`].
+#[doc(hidden)]
+pub struct Entry<U: ?Sized> {
+	tag: u64,
+	name: &'static str,
+	meta: DynMetadata<U>,
+}
+impl<U: ?Sized> Clone for Entry<U> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<U: ?Sized> Copy for Entry<U> {}
+impl<U: ?Sized> fmt::Debug for Entry<U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Entry")
+			.field("tag", &self.tag)
+			.field("name", &self.name)
+			.finish_non_exhaustive()
+	}
+}
+impl<U: ?Sized + Pointee<Metadata = DynMetadata<U>>> Entry<U> {
+	#[doc(hidden)]
+	pub const fn of<T: Unsize<U>>() -> Self {
+		let ptr: *const U = ptr::null::<T>();
+		Self {
+			tag: tag::<T>(),
+			name: core::any::type_name::<T>(),
+			meta: ptr::metadata(ptr),
+		}
+	}
+}
+
+/// Sort the implementors by tag, failing to compile if two share a tag.
+#[doc(hidden)]
+pub const fn sort<U: ?Sized, const N: usize>(mut entries: [Entry<U>; N]) -> [Entry<U>; N] {
+	let mut i = 1;
+	while i < N {
+		let mut j = i;
+		while j > 0 && entries[j - 1].tag > entries[j].tag {
+			let entry = entries[j];
+			entries[j] = entries[j - 1];
+			entries[j - 1] = entry;
+			j -= 1;
+		}
+		assert!(
+			j == 0 || entries[j - 1].tag != entries[j].tag,
+			"two implementors share a tag; give one of them a distinct TypeTag"
+		);
+		i += 1;
+	}
+	entries
+}
+
+impl<U: ?Sized> StaticRegistry<U> {
+	#[doc(hidden)]
+	pub const fn new(entries: &'static [Entry<U>]) -> Self {
+		Self { entries }
+	}
+	/// The number of implementors in the registry.
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.entries.len()
+	}
+	/// Whether the registry has no implementors.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+	#[inline]
+	fn get(&self, tag: u64) -> Option<&Entry<U>> {
+		let index = self
+			.entries
+			.binary_search_by_key(&tag, |entry| entry.tag)
+			.ok()?;
+		Some(&self.entries[index])
+	}
+	/// Get the vtable of the implementor registered under `tag`, if any.
+	#[inline]
+	#[must_use]
+	pub fn vtable(&self, tag: u64) -> Option<TraitObject> {
+		let meta = self.get(tag)?.meta;
+		Some(TraitObject {
+			vtable: unsafe { transmute_coerce(meta) },
+		})
+	}
+	/// Get the name of the implementor registered under `tag`, if any.
+	#[inline]
+	#[must_use]
+	pub fn type_name(&self, tag: u64) -> Option<&'static str> {
+		self.get(tag).map(|entry| entry.name)
+	}
+}
+impl<U: ?Sized> fmt::Debug for StaticRegistry<U> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(self.entries.iter().map(|entry| (entry.tag, entry.name)))
+			.finish()
+	}
+}
+
+/// Build a [`StaticRegistry`](crate::static_registry::StaticRegistry) of the
+/// listed implementors of a trait object type, sorted at compile time.
+///
+/// Requires the `static-registry` feature.
+///
+/// ```
+/// # use metatype::static_registry;
+/// trait Shape {
+///     fn area(&self) -> u64;
+/// }
+/// # struct Square;
+/// # impl Shape for Square { fn area(&self) -> u64 { 0 } }
+/// # struct Circle;
+/// # impl Shape for Circle { fn area(&self) -> u64 { 0 } }
+/// static_registry!(pub static SHAPES: dyn Shape = [Square, Circle]);
+/// assert_eq!(SHAPES.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! static_registry {
+	($vis:vis static $name:ident: $trait_object:ty = [$($t:ty),* $(,)?]) => {
+		$vis static $name: $crate::static_registry::StaticRegistry<$trait_object> =
+			$crate::static_registry::StaticRegistry::new({
+				const ENTRIES: &[$crate::static_registry::Entry<$trait_object>] =
+					&$crate::static_registry::sort([$($crate::static_registry::Entry::<$trait_object>::of::<$t>()),*]);
+				ENTRIES
+			});
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::tag;
+	use crate::{type_coerce, Type, TypeTag};
+	use core::ptr;
+
+	trait Shape {
+		fn area(&self) -> u64;
+	}
+	struct Square(u64);
+	impl Shape for Square {
+		fn area(&self) -> u64 {
+			self.0 * self.0
+		}
+	}
+	impl TypeTag for Square {
+		const TAG: &'static str = "square";
+	}
+	struct Circle(u64);
+	impl Shape for Circle {
+		fn area(&self) -> u64 {
+			self.0 * self.0 * 3
+		}
+	}
+
+	macro_rules! shapes {
+		($($name:ident),*) => {
+			$(
+				struct $name;
+				impl Shape for $name {
+					fn area(&self) -> u64 {
+						0
+					}
+				}
+			)*
+			crate::static_registry!(static SHAPES: dyn Shape = [Square, Circle, $($name),*]);
+		};
+	}
+	shapes!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+	#[test]
+	fn static_registry() {
+		assert_eq!(SHAPES.len(), 18);
+		assert!(SHAPES.entries.is_sorted_by_key(|entry| entry.tag));
+		let meta = SHAPES.vtable(tag::<Circle>()).unwrap();
+		let mut circle = Circle(2);
+		let circle: *mut dyn Shape =
+			Type::fatten(ptr::from_mut(&mut circle).cast(), type_coerce(meta));
+		assert_eq!(unsafe { &*circle }.area(), 12);
+		assert_eq!(
+			SHAPES.type_name(tag::<Square>()),
+			Some(core::any::type_name::<Square>())
+		);
+		assert_eq!(SHAPES.vtable(0), None);
+		#[cfg(feature = "std")]
+		assert_eq!(tag::<Square>(), crate::registry::tag::<Square>());
+	}
+}
@@ -0,0 +1,101 @@
+//! Helpers for moving unsized values by value, for use with the nightly
+//! `unsized_fn_params` feature.
+//!
+//! Requires the `unsized-locals` feature.
+//!
+//! Unsized function parameters can be passed by moving out of a `Box`, but
+//! once bound they can't be moved again: rustc doesn't support dynamically
+//! sized stack allocations, and moving an unsized parameter into another call
+//! is rejected or ICEs. These helpers instead take the parameter wrapped in
+//! [`ManuallyDrop`], copy its bytes into storage sized from its metadata, and
+//! hand ownership to that storage.
+//!
+//! Closures can't take unsized parameters, so these helpers are plain
+//! functions.
+//!
+//! # Examples
+//!
+//! ```
+//! #![feature(unsized_fn_params)]
+//! # #![allow(internal_features)]
+//! # use metatype::unsized_locals;
+//! use std::{fmt::Debug, mem::ManuallyDrop};
+//!
+//! fn keep(value: ManuallyDrop<dyn Debug>) -> Box<dyn Debug> {
+//!     unsized_locals::into_box(value)
+//! }
+//!
+//! let a: Box<ManuallyDrop<dyn Debug>> = Box::new(ManuallyDrop::new(String::from("abc")));
+//! let a = keep(*a);
+//! assert_eq!(format!("{:?}", a), "\"abc\"");
+//! ```
+
+use super::{box_with, emplace_raw, FattenError, Type};
+use alloc::boxed::Box;
+use core::{
+	mem::{size_of_val, ManuallyDrop, MaybeUninit}, ptr
+};
+
+/// Move an unsized value into a new `Box`.
+pub fn into_box<T: ?Sized + Type>(value: ManuallyDrop<T>) -> Box<T> {
+	let src = ptr::from_ref(&*value);
+	let size = size_of_val(&*value);
+	unsafe {
+		box_with(Type::meta(src), |dst: *mut T| {
+			src.cast::<u8>().copy_to_nonoverlapping(dst.cast(), size);
+		})
+	}
+}
+
+/// Move an unsized value into `buffer`, at the first suitably aligned offset,
+/// returning a reference to it bound to the lifetime of `buffer`.
+///
+/// The value is never dropped; call [`std::ptr::drop_in_place`] on the
+/// returned reference if needed.
+///
+/// # Errors
+///
+/// Returns a [`FattenError`] if the value doesn't fit in `buffer`, in which
+/// case it is dropped.
+pub fn emplace<T: ?Sized + Type>(
+	buffer: &mut [MaybeUninit<u8>], mut value: ManuallyDrop<T>,
+) -> Result<&mut T, FattenError> {
+	let src = ptr::from_ref(&*value);
+	let ret = unsafe { emplace_raw::<T>(buffer, Type::meta(src), src.cast()) };
+	if ret.is_err() {
+		unsafe { ManuallyDrop::drop(&mut value) };
+	}
+	ret
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		fmt::Debug, mem::{ManuallyDrop, MaybeUninit}, ptr
+	};
+
+	fn into_box(value: ManuallyDrop<[String]>) -> Box<[String]> {
+		super::into_box(value)
+	}
+	fn emplace(
+		buffer: &mut [MaybeUninit<u8>], value: ManuallyDrop<dyn Debug>,
+	) -> Result<&mut dyn Debug, super::FattenError> {
+		super::emplace(buffer, value)
+	}
+
+	#[test]
+	fn unsized_locals() {
+		let a: Box<ManuallyDrop<[String]>> =
+			Box::new(ManuallyDrop::new([String::from("a"), String::from("b")]));
+		assert_eq!(&*into_box(*a), ["a", "b"]);
+
+		let mut buf = [MaybeUninit::uninit(); 32];
+		let a: Box<ManuallyDrop<dyn Debug>> = Box::new(ManuallyDrop::new(vec![1_u8]));
+		let a = emplace(&mut buf, *a).unwrap();
+		assert_eq!(format!("{a:?}"), "[1]");
+		unsafe { ptr::drop_in_place(a) };
+		let a: Box<ManuallyDrop<dyn Debug>> =
+			Box::new(ManuallyDrop::new([String::new(), String::new()]));
+		assert!(emplace(&mut buf[..8], *a).is_err());
+	}
+}
@@ -0,0 +1,189 @@
+//! Proof harnesses for the [Kani](https://github.com/model-checking/kani)
+//! model checker, covering the arithmetic and pointer manipulation underlying
+//! the core unsafe primitives.
+//!
+//! These are compiled only by `cargo kani`, which runs each of them, as does
+//! the `kani` CI job.
+
+use std::{
+	convert::TryFrom, mem::size_of, ptr::{self, slice_from_raw_parts_mut}
+};
+
+use super::{
+	align_offset, Composite, Concrete, FattenError, Meta, MetaType, Slice, Type, WireError
+};
+
+/// Fattening a thin pointer with slice meta data and taking it apart again
+/// yields the original address and length.
+#[kani::proof]
+fn slice_fatten_round_trip() {
+	let addr: usize = kani::any();
+	let len: usize = kani::any();
+	let fat = <[u32] as Type>::fatten(ptr::without_provenance_mut(addr), Slice { len });
+	assert_eq!(Type::data(fat).addr(), addr);
+	assert_eq!(fat.len(), len);
+	let fat = <str as Type>::fatten(ptr::without_provenance_mut(addr), Slice { len });
+	assert_eq!((Type::data(fat).addr(), ptr::metadata(fat)), (addr, len));
+}
+
+/// Splitting a fattened pointer yields the thin pointer and meta data it was
+/// built from, and fattening the parts rebuilds it.
+#[kani::proof]
+fn split_round_trip() {
+	let addr: usize = kani::any();
+	let len: usize = kani::any();
+	let fat = <[u32] as Type>::fatten(ptr::without_provenance_mut(addr), Slice { len });
+	let (thin, meta) = Type::split_mut(fat);
+	assert_eq!((thin.addr(), meta), (addr, Slice { len }));
+	assert_eq!(<[u32] as Type>::fatten(thin, meta), fat);
+	let fat = <str as Type>::fatten_const(ptr::without_provenance(addr), Slice { len });
+	let (thin, meta) = Type::split(fat);
+	assert_eq!((thin.addr(), meta), (addr, Slice { len }));
+	let (thin, Concrete) = Type::split(ptr::without_provenance::<u64>(addr));
+	assert_eq!(thin.addr(), addr);
+}
+
+/// `try_fatten` accepts exactly the non-null, aligned pointers whose value's
+/// size doesn't overflow `isize`, and otherwise reports the first check to
+/// fail.
+#[kani::proof]
+fn try_fatten_validates() {
+	let addr: usize = kani::any();
+	let len: usize = kani::any();
+	let thin = ptr::without_provenance_mut(addr);
+	let size = len.checked_mul(size_of::<u32>());
+	match <[u32] as Type>::try_fatten(thin, Slice { len }) {
+		Ok(fat) => {
+			assert!(addr != 0 && addr.is_multiple_of(4));
+			assert!(size.is_some_and(|size| isize::try_from(size).is_ok()));
+			assert_eq!((Type::data(fat).addr(), fat.len()), (addr, len));
+		}
+		Err(FattenError::Null) => assert_eq!(addr, 0),
+		Err(FattenError::SizeOverflow) => {
+			assert!(addr != 0);
+			assert!(size.is_none_or(|size| isize::try_from(size).is_err()));
+		}
+		Err(FattenError::Misaligned { addr: found, align }) => {
+			assert_eq!((found, align), (addr, 4));
+			assert!(addr != 0 && !addr.is_multiple_of(4));
+		}
+		Err(FattenError::InvalidVtable { .. } | FattenError::OutOfBounds { .. }) => {
+			unreachable!("slices have no vtable, and there's no buffer")
+		}
+	}
+	assert_eq!(
+		<u64 as Type>::try_fatten(thin, Concrete).is_ok(),
+		addr != 0 && addr.is_multiple_of(8)
+	);
+}
+
+/// Decoding wire meta data accepts exactly the lengths that fit in a `usize`,
+/// round trips with encoding it, and refuses trait objects.
+#[kani::proof]
+fn wire_decoding() {
+	let wire: u64 = kani::any();
+	match Slice::from_wire(wire) {
+		Ok(slice) => {
+			assert_eq!(u64::try_from(slice.len), Ok(wire));
+			assert_eq!(slice.to_wire(), wire);
+		}
+		Err(error) => {
+			assert_eq!(error, WireError::Overflow { wire });
+			assert!(usize::try_from(wire).is_err());
+		}
+	}
+	assert_eq!(
+		Composite::from_wire(wire).map(Composite::to_wire),
+		Slice::from_wire(wire).map(Slice::to_wire)
+	);
+	let meta_types = [
+		MetaType::TraitObject,
+		MetaType::Slice,
+		MetaType::Concrete,
+		MetaType::Extern,
+		MetaType::Composite,
+	];
+	let index: usize = kani::any();
+	kani::assume(index < meta_types.len());
+	let meta_type = meta_types[index];
+	match Meta::from_wire(meta_type, wire) {
+		Ok(meta) => assert_eq!(meta.to_wire(), Ok(wire)),
+		Err(WireError::TraitObject) => assert_eq!(meta_type, MetaType::TraitObject),
+		Err(WireError::Overflow { wire: found }) => {
+			assert_eq!(found, wire);
+			assert!(
+				usize::try_from(wire).is_err()
+					|| matches!(meta_type, MetaType::Concrete | MetaType::Extern)
+			);
+		}
+	}
+}
+
+/// The size computed from slice meta data is exact, and is only refused when
+/// it would overflow `isize`.
+#[kani::proof]
+fn slice_size_from_meta() {
+	let len: usize = kani::any();
+	let exact = len.checked_mul(size_of::<u32>());
+	match <[u32] as Type>::size_from_meta(&Slice { len }) {
+		Some(size) => {
+			assert_eq!(Some(size), exact);
+			assert!(isize::try_from(size).is_ok());
+		}
+		None => assert!(exact.is_none_or(|size| isize::try_from(size).is_err())),
+	}
+	assert_eq!(<u64 as Type>::size_from_meta(&Concrete), Some(8));
+}
+
+/// A pointer built by `fatten_within` lies entirely within the buffer, and
+/// one is built whenever the value would.
+#[kani::proof]
+fn fatten_within_bounds() {
+	let buffer_len: usize = kani::any();
+	let offset: usize = kani::any();
+	let len: usize = kani::any();
+	kani::assume(isize::try_from(buffer_len).is_ok());
+	let buffer = slice_from_raw_parts_mut(ptr::without_provenance_mut::<u8>(8), buffer_len);
+	let size = len.checked_mul(size_of::<u16>());
+	match <[u16] as Type>::fatten_within(buffer, offset, Slice { len }) {
+		Ok(fat) => {
+			let size = size.unwrap();
+			assert!(offset + size <= buffer_len);
+			assert_eq!(Type::data(fat).addr(), 8 + offset);
+			assert_eq!(fat.len(), len);
+		}
+		Err(FattenError::SizeOverflow) => {
+			assert!(size.is_none_or(|size| isize::try_from(size).is_err()));
+		}
+		Err(FattenError::OutOfBounds {
+			offset: found,
+			size: found_size,
+			len: found_len,
+		}) => {
+			assert_eq!(
+				(found, Some(found_size), found_len),
+				(offset, size, buffer_len)
+			);
+			assert!(offset
+				.checked_add(found_size)
+				.is_none_or(|end| end > buffer_len));
+		}
+		Err(
+			FattenError::Null | FattenError::Misaligned { .. } | FattenError::InvalidVtable { .. },
+		) => {
+			unreachable!("the buffer is non-null and aligned, and slices have no vtable")
+		}
+	}
+}
+
+/// The offset used by `emplace_raw` is the least that aligns the address.
+#[kani::proof]
+fn align_offset_aligns() {
+	let addr: usize = kani::any();
+	let shift: u32 = kani::any();
+	kani::assume(shift < usize::BITS);
+	let align = 1_usize << shift;
+	let offset = align_offset(addr, align);
+	assert!(offset < align);
+	assert_eq!(addr.wrapping_add(offset) % align, 0);
+}